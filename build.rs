@@ -1,6 +1,6 @@
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 fn main() {
@@ -16,7 +16,7 @@ fn main() {
     }
 }
 
-fn framework_supports_target_arch(framework: &PathBuf, target_arch: &str) -> bool {
+fn framework_supports_target_arch(framework: &Path, target_arch: &str) -> bool {
     let bin = framework.join("Syphon");
     if !bin.exists() {
         return false;
@@ -145,18 +145,18 @@ fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> std::io::Result<()> {
 }
 
 /// Resolve lib/bin dirs from a candidate root: accept root, root/lib, root/bin (grabs what it needs).
-fn resolve_spout_prebuilt(root: &PathBuf) -> Option<(PathBuf, PathBuf)> {
+fn resolve_spout_prebuilt(root: &Path) -> Option<(PathBuf, PathBuf)> {
     let lib_dir = if root.join("lib").join("SpoutLibrary.lib").exists() {
         root.join("lib")
     } else if root.join("SpoutLibrary.lib").exists() {
-        root.clone()
+        root.to_path_buf()
     } else {
         return None;
     };
     let bin_dir = if root.join("bin").join("SpoutLibrary.dll").exists() {
         root.join("bin")
     } else if root.join("SpoutLibrary.dll").exists() {
-        root.clone()
+        root.to_path_buf()
     } else {
         root.join("bin") // copy_dll will no-op if dll not present
     };
@@ -256,7 +256,7 @@ fn build_spout_stub_macos() {
     println!("cargo:rerun-if-changed=spout_glue/spout_glue_stub.c");
 }
 
-fn copy_dll(bin_dir: &PathBuf, dll_name: &str) {
+fn copy_dll(bin_dir: &Path, dll_name: &str) {
     let src = bin_dir.join(dll_name);
     if !src.exists() {
         return;
@@ -11,7 +11,7 @@ use rusty_syphon_spout::{
     cgl_create_headless_context, cgl_destroy_context, cgl_make_current,
     gl_create_texture_rectangle_rgba8, gl_delete_texture, gl_read_texture_rectangle_rgba8,
     OpenGLClient, OpenGLImage, OpenGLServer, MetalClient, MetalServer, MetalTexture,
-    GL_TEXTURE_RECTANGLE,
+    Frame, FrameCompare, PixelFormat, PublishRequest, Size, GL_TEXTURE_RECTANGLE,
 };
 use std::time::Duration;
 
@@ -69,15 +69,14 @@ fn test_metal_roundtrip() {
     .expect("create Metal server");
 
     let cmd_buf = queue.new_command_buffer();
-    server.publish_frame(
-        texture.as_ptr() as *mut _,
-        cmd_buf.as_ptr() as *mut _,
-        0.0,
-        0.0,
-        W as f64,
-        H as f64,
-        false,
-    );
+    let request = PublishRequest::new(Size::new(W as u32, H as u32));
+    server
+        .publish(
+            texture.as_ptr() as *mut _,
+            cmd_buf.as_ptr() as *mut _,
+            &request,
+        )
+        .expect("publish Metal frame");
     cmd_buf.commit();
     cmd_buf.wait_until_completed();
 
@@ -110,19 +109,10 @@ fn test_metal_roundtrip() {
     std::mem::forget(received_tex);
     drop(received);
 
-    // 5. Compare: readback is BGRA, pattern is RGBA — convert readback to RGBA for comparison
-    let mut received_rgba = vec![0u8; TOTAL_BYTES];
-    for i in (0..TOTAL_BYTES).step_by(4) {
-        received_rgba[i] = readback[i + 2];     // R
-        received_rgba[i + 1] = readback[i + 1]; // G
-        received_rgba[i + 2] = readback[i];     // B
-        received_rgba[i + 3] = readback[i + 3]; // A
-    }
-
-    assert_eq!(
-        pattern, received_rgba,
-        "Metal roundtrip: sent and received pixels differ"
-    );
+    // 5. Compare: readback is BGRA, pattern is RGBA; FrameCompare matches channels by meaning
+    let sent = Frame::from_vec(W as u32, H as u32, PixelFormat::Rgba8, pattern).unwrap();
+    let received = Frame::from_vec(W as u32, H as u32, PixelFormat::Bgra8, readback).unwrap();
+    FrameCompare::new().assert(&sent, &received);
     println!("OK Metal: sent and received images match ({} bytes)", TOTAL_BYTES);
 }
 
@@ -136,17 +126,10 @@ fn test_opengl_roundtrip() {
 
     let server = OpenGLServer::new(Some("rusty-syphon-roundtrip-opengl"), ctx, None)
         .expect("create OpenGL server");
-    server.publish_frame(
-        tex_id,
-        GL_TEXTURE_RECTANGLE,
-        0.0,
-        0.0,
-        W as f64,
-        H as f64,
-        W as f64,
-        H as f64,
-        false,
-    );
+    let request = PublishRequest::new(Size::new(W as u32, H as u32));
+    server
+        .publish(tex_id, GL_TEXTURE_RECTANGLE, &request)
+        .expect("publish OpenGL frame");
 
     let server_desc = server.server_description().expect("server description");
     std::thread::sleep(Duration::from_millis(50));
@@ -169,21 +152,9 @@ fn test_opengl_roundtrip() {
     gl_read_texture_rectangle_rgba8(recv_tex, W, H, &mut readback);
     drop(image);
 
-    if pattern != readback {
-        let mut first_diff = None;
-        for (i, (a, b)) in pattern.iter().zip(readback.iter()).enumerate() {
-            if a != b {
-                first_diff = Some((i, *a, *b));
-                break;
-            }
-        }
-        panic!(
-            "OpenGL roundtrip: first diff at byte {:?} (sent {}, received {})",
-            first_diff.map(|(i, _, _)| i),
-            first_diff.map(|(_, a, _)| a).unwrap_or(0),
-            first_diff.map(|(_, _, b)| b).unwrap_or(0)
-        );
-    }
+    let sent = Frame::from_vec(W as u32, H as u32, PixelFormat::Rgba8, pattern).unwrap();
+    let received = Frame::from_vec(W as u32, H as u32, PixelFormat::Rgba8, readback).unwrap();
+    FrameCompare::new().assert(&sent, &received);
     println!("OK OpenGL: sent and received images match ({} bytes)", TOTAL_BYTES);
 
     gl_delete_texture(tex_id);
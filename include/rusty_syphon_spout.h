@@ -0,0 +1,125 @@
+/*
+ * rusty_syphon_spout.h - C ABI for rusty-syphon-spout.
+ *
+ * Backend-agnostic frame sharing: discover sources (local, Syphon, Spout), receive CPU frames
+ * from any of them, and publish CPU frames on any backend available on this platform.
+ *
+ * Link against the `rusty_syphon_spout` cdylib (librusty_syphon_spout.so / .dylib /
+ * rusty_syphon_spout.dll) built with `cargo build --release`.
+ *
+ * Conventions:
+ * - Every fallible call returns an rss_error (RSS_OK == 0, errors < 0) and writes results
+ *   through out pointers, which are left untouched on error.
+ * - Handles are opaque. Free them with the matching *_destroy / *_free call; null is ignored.
+ * - Handles are not thread-safe; use each one from the thread that created it. Spout needs an
+ *   OpenGL context current on that thread.
+ * - Strings are UTF-8 and NUL-terminated.
+ * - Pixels are 8 bits per channel, 4 bytes per pixel, rows top to bottom.
+ *
+ * Versioning: RSS_ABI_VERSION changes on any incompatible change to this header. Check it
+ * against rss_abi_version() at startup. Additions that keep existing declarations compatible
+ * do not change it.
+ */
+
+#ifndef RUSTY_SYPHON_SPOUT_H
+#define RUSTY_SYPHON_SPOUT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RSS_ABI_VERSION 1
+
+/* Error codes. */
+typedef int32_t rss_error;
+#define RSS_OK 0
+#define RSS_ERROR_NULL_ARGUMENT (-1)     /* a required pointer was null */
+#define RSS_ERROR_INVALID_ARGUMENT (-2)  /* out of range, unknown enum value or invalid UTF-8 */
+#define RSS_ERROR_NOT_FOUND (-3)         /* no source with that id or name */
+#define RSS_ERROR_UNAVAILABLE (-4)       /* backend not available on this platform */
+#define RSS_ERROR_NO_FRAME (-5)          /* no new frame since the last receive */
+#define RSS_ERROR_BUFFER_TOO_SMALL (-6)  /* see rss_receiver_receive_into */
+#define RSS_ERROR_PUBLISH_FAILED (-7)    /* the backend rejected the frame */
+#define RSS_ERROR_DISCONNECTED (-8)      /* the source has gone */
+
+/* Backends. */
+typedef int32_t rss_backend;
+#define RSS_BACKEND_LOCAL 0   /* in-process servers (pure Rust, every platform) */
+#define RSS_BACKEND_SYPHON 1  /* macOS */
+#define RSS_BACKEND_SPOUT 2   /* Windows */
+
+/* Pixel formats. */
+typedef int32_t rss_pixel_format;
+#define RSS_PIXEL_FORMAT_UNKNOWN (-1)
+#define RSS_PIXEL_FORMAT_RGBA8 0
+#define RSS_PIXEL_FORMAT_BGRA8 1
+
+typedef struct rss_source_list rss_source_list;
+typedef struct rss_sender rss_sender;
+typedef struct rss_receiver rss_receiver;
+
+/* A discovered source. Strings belong to the list or callback call that produced it. */
+typedef struct rss_source_info {
+    rss_backend backend;
+    const char *id;        /* "backend:uuid", unique across backends; pass to rss_receiver_connect */
+    const char *uuid;
+    const char *name;
+    const char *app_name;  /* empty when unknown */
+    uint32_t width;        /* 0 when the backend does not report a size */
+    uint32_t height;
+    rss_pixel_format format; /* RSS_PIXEL_FORMAT_UNKNOWN when not reported */
+} rss_source_info;
+
+/* A received frame. */
+typedef struct rss_frame {
+    const uint8_t *pixels;
+    uint32_t width;
+    uint32_t height;
+    size_t stride;         /* bytes between row starts, >= width * 4 */
+    rss_pixel_format format;
+} rss_frame;
+
+/* Callbacks receive the user_data pointer given to the call that invokes them. The info or
+ * frame pointer is only valid during the callback. */
+typedef void (*rss_source_callback)(const rss_source_info *info, void *user_data);
+typedef void (*rss_frame_callback)(const rss_frame *frame, void *user_data);
+
+/* Version and errors. */
+uint32_t rss_abi_version(void);
+const char *rss_error_message(rss_error error); /* static string, never null */
+
+/* Discovery. */
+rss_error rss_discover(rss_source_list **out);
+size_t rss_source_list_len(const rss_source_list *list);
+rss_error rss_source_list_get(const rss_source_list *list, size_t index, rss_source_info *out);
+void rss_source_list_free(rss_source_list *list);
+rss_error rss_discover_each(rss_source_callback callback, void *user_data);
+
+/* Sending. `pixels` holds `height` rows of `width` pixels, `stride` bytes apart. */
+rss_error rss_sender_create(rss_backend backend, const char *name, rss_sender **out);
+rss_error rss_sender_send(rss_sender *sender, const uint8_t *pixels, uint32_t width,
+                          uint32_t height, size_t stride, rss_pixel_format format);
+void rss_sender_destroy(rss_sender *sender);
+
+/* Receiving. `source` is an rss_source_info id, or a source name (first match). */
+rss_error rss_receiver_connect(const char *source, rss_receiver **out);
+bool rss_receiver_is_connected(const rss_receiver *receiver);
+/* Calls `callback` with the new frame, if any; RSS_ERROR_NO_FRAME otherwise. */
+rss_error rss_receiver_receive(rss_receiver *receiver, rss_frame_callback callback,
+                               void *user_data);
+/* Copies the new frame, tightly packed, into `buffer` and describes it in `out`. If the buffer
+ * is too small, `out` gets the dimensions (pixels == NULL), the frame is kept for the next call
+ * and RSS_ERROR_BUFFER_TOO_SMALL is returned. */
+rss_error rss_receiver_receive_into(rss_receiver *receiver, uint8_t *buffer, size_t buffer_len,
+                                    rss_frame *out);
+void rss_receiver_destroy(rss_receiver *receiver);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUSTY_SYPHON_SPOUT_H */
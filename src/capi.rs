@@ -0,0 +1,495 @@
+//! Stable C ABI over discovery, `create_sender` and `connect_source`, declared in
+//! `include/rusty_syphon_spout.h` (see there for the contract).
+//!
+//! Handles are boxed Rust objects passed as opaque pointers; every call returns an `rss_error`
+//! and writes results through out pointers. Strings are UTF-8 and NUL-terminated. Handles are
+//! not thread-safe and should stay on the thread that created them (Spout needs that thread's
+//! OpenGL context anyway).
+
+#![allow(non_camel_case_types)]
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+
+use crate::discovery::{
+    connect_source, create_sender, discover_sources, SourceBackend, SourceInfo,
+};
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+
+/// Bumped on any incompatible change to the header. Matches `RSS_ABI_VERSION`.
+pub const RSS_ABI_VERSION: u32 = 1;
+
+pub type rss_error = i32;
+pub const RSS_OK: rss_error = 0;
+pub const RSS_ERROR_NULL_ARGUMENT: rss_error = -1;
+pub const RSS_ERROR_INVALID_ARGUMENT: rss_error = -2;
+pub const RSS_ERROR_NOT_FOUND: rss_error = -3;
+pub const RSS_ERROR_UNAVAILABLE: rss_error = -4;
+pub const RSS_ERROR_NO_FRAME: rss_error = -5;
+pub const RSS_ERROR_BUFFER_TOO_SMALL: rss_error = -6;
+pub const RSS_ERROR_PUBLISH_FAILED: rss_error = -7;
+pub const RSS_ERROR_DISCONNECTED: rss_error = -8;
+
+pub type rss_backend = i32;
+pub const RSS_BACKEND_LOCAL: rss_backend = 0;
+pub const RSS_BACKEND_SYPHON: rss_backend = 1;
+pub const RSS_BACKEND_SPOUT: rss_backend = 2;
+
+pub type rss_pixel_format = i32;
+pub const RSS_PIXEL_FORMAT_UNKNOWN: rss_pixel_format = -1;
+pub const RSS_PIXEL_FORMAT_RGBA8: rss_pixel_format = 0;
+pub const RSS_PIXEL_FORMAT_BGRA8: rss_pixel_format = 1;
+
+/// A discovered source. Strings are owned by the list or callback call it came from.
+#[repr(C)]
+pub struct rss_source_info {
+    pub backend: rss_backend,
+    pub id: *const c_char,
+    pub uuid: *const c_char,
+    pub name: *const c_char,
+    pub app_name: *const c_char,
+    /// 0 when the backend does not report a size.
+    pub width: u32,
+    pub height: u32,
+    pub format: rss_pixel_format,
+}
+
+/// A received frame's pixels, valid for the duration of the callback.
+#[repr(C)]
+pub struct rss_frame {
+    pub pixels: *const u8,
+    pub width: u32,
+    pub height: u32,
+    pub stride: usize,
+    pub format: rss_pixel_format,
+}
+
+pub type rss_source_callback =
+    Option<unsafe extern "C" fn(info: *const rss_source_info, user_data: *mut c_void)>;
+pub type rss_frame_callback =
+    Option<unsafe extern "C" fn(frame: *const rss_frame, user_data: *mut c_void)>;
+
+/// `SourceInfo` with NUL-terminated copies of its strings for `rss_source_info`.
+struct CSource {
+    backend: SourceBackend,
+    id: CString,
+    uuid: CString,
+    name: CString,
+    app_name: CString,
+    width: u32,
+    height: u32,
+    format: rss_pixel_format,
+}
+
+impl CSource {
+    fn new(source: &SourceInfo) -> Self {
+        let c = |s: String| CString::new(s.replace('\0', "")).unwrap_or_default();
+        let size = source.size.unwrap_or_default();
+        Self {
+            backend: source.backend,
+            id: c(source.id()),
+            uuid: c(source.uuid.clone()),
+            name: c(source.name.clone()),
+            app_name: c(source.app_name.clone()),
+            width: size.width,
+            height: size.height,
+            format: source.format.map_or(RSS_PIXEL_FORMAT_UNKNOWN, format_to_c),
+        }
+    }
+
+    fn info(&self) -> rss_source_info {
+        rss_source_info {
+            backend: backend_to_c(self.backend),
+            id: self.id.as_ptr(),
+            uuid: self.uuid.as_ptr(),
+            name: self.name.as_ptr(),
+            app_name: self.app_name.as_ptr(),
+            width: self.width,
+            height: self.height,
+            format: self.format,
+        }
+    }
+}
+
+pub struct rss_source_list {
+    sources: Vec<CSource>,
+}
+
+pub struct rss_sender {
+    sender: Box<dyn FrameSender>,
+}
+
+pub struct rss_receiver {
+    receiver: Box<dyn FrameReceiver>,
+    /// A frame `rss_receiver_receive_into` could not fit, kept for the next call.
+    pending: Option<Frame>,
+}
+
+fn backend_to_c(backend: SourceBackend) -> rss_backend {
+    match backend {
+        SourceBackend::Local => RSS_BACKEND_LOCAL,
+        SourceBackend::Syphon => RSS_BACKEND_SYPHON,
+        SourceBackend::Spout => RSS_BACKEND_SPOUT,
+    }
+}
+
+fn backend_from_c(backend: rss_backend) -> Option<SourceBackend> {
+    match backend {
+        RSS_BACKEND_LOCAL => Some(SourceBackend::Local),
+        RSS_BACKEND_SYPHON => Some(SourceBackend::Syphon),
+        RSS_BACKEND_SPOUT => Some(SourceBackend::Spout),
+        _ => None,
+    }
+}
+
+fn format_to_c(format: PixelFormat) -> rss_pixel_format {
+    match format {
+        PixelFormat::Rgba8 => RSS_PIXEL_FORMAT_RGBA8,
+        PixelFormat::Bgra8 => RSS_PIXEL_FORMAT_BGRA8,
+    }
+}
+
+fn format_from_c(format: rss_pixel_format) -> Option<PixelFormat> {
+    match format {
+        RSS_PIXEL_FORMAT_RGBA8 => Some(PixelFormat::Rgba8),
+        RSS_PIXEL_FORMAT_BGRA8 => Some(PixelFormat::Bgra8),
+        _ => None,
+    }
+}
+
+/// Borrow a C string argument; `None` for null or invalid UTF-8.
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// The ABI version the library was built with. Compare against `RSS_ABI_VERSION` from the
+/// header.
+#[no_mangle]
+pub extern "C" fn rss_abi_version() -> u32 {
+    RSS_ABI_VERSION
+}
+
+/// Static English description of `error`.
+#[no_mangle]
+pub extern "C" fn rss_error_message(error: rss_error) -> *const c_char {
+    let message: &'static CStr = match error {
+        RSS_OK => c"ok",
+        RSS_ERROR_NULL_ARGUMENT => c"a required pointer argument was null",
+        RSS_ERROR_INVALID_ARGUMENT => c"an argument was out of range or not valid UTF-8",
+        RSS_ERROR_NOT_FOUND => c"no such source",
+        RSS_ERROR_UNAVAILABLE => c"backend unavailable on this platform",
+        RSS_ERROR_NO_FRAME => c"no new frame",
+        RSS_ERROR_BUFFER_TOO_SMALL => c"buffer too small for the frame",
+        RSS_ERROR_PUBLISH_FAILED => c"publishing the frame failed",
+        RSS_ERROR_DISCONNECTED => c"source disconnected",
+        _ => c"unknown error",
+    };
+    message.as_ptr()
+}
+
+/// Snapshot the visible sources into a list owned by the caller.
+///
+/// # Safety
+/// `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rss_discover(out: *mut *mut rss_source_list) -> rss_error {
+    if out.is_null() {
+        return RSS_ERROR_NULL_ARGUMENT;
+    }
+    let sources = discover_sources().iter().map(CSource::new).collect();
+    *out = Box::into_raw(Box::new(rss_source_list { sources }));
+    RSS_OK
+}
+
+/// Call `callback` once per visible source, with `user_data`.
+///
+/// # Safety
+/// `callback` must be safe to call with the info pointer (valid only during the call).
+#[no_mangle]
+pub unsafe extern "C" fn rss_discover_each(
+    callback: rss_source_callback,
+    user_data: *mut c_void,
+) -> rss_error {
+    let Some(callback) = callback else {
+        return RSS_ERROR_NULL_ARGUMENT;
+    };
+    for source in discover_sources() {
+        let source = CSource::new(&source);
+        let info = source.info();
+        callback(&info, user_data);
+    }
+    RSS_OK
+}
+
+/// Number of sources in `list` (0 for null).
+///
+/// # Safety
+/// `list` must be null or from `rss_discover`.
+#[no_mangle]
+pub unsafe extern "C" fn rss_source_list_len(list: *const rss_source_list) -> usize {
+    list.as_ref().map_or(0, |list| list.sources.len())
+}
+
+/// Entry `index` of `list`. Its strings live until the list is freed.
+///
+/// # Safety
+/// `list` must be from `rss_discover`; `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rss_source_list_get(
+    list: *const rss_source_list,
+    index: usize,
+    out: *mut rss_source_info,
+) -> rss_error {
+    let (Some(list), false) = (list.as_ref(), out.is_null()) else {
+        return RSS_ERROR_NULL_ARGUMENT;
+    };
+    match list.sources.get(index) {
+        Some(source) => {
+            *out = source.info();
+            RSS_OK
+        }
+        None => RSS_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+/// Free a list from `rss_discover`. Null is ignored.
+///
+/// # Safety
+/// `list` must be null or from `rss_discover`, and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rss_source_list_free(list: *mut rss_source_list) {
+    if !list.is_null() {
+        drop(Box::from_raw(list));
+    }
+}
+
+/// Publish frames as `name` on `backend`.
+///
+/// # Safety
+/// `name` must be a NUL-terminated string; `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rss_sender_create(
+    backend: rss_backend,
+    name: *const c_char,
+    out: *mut *mut rss_sender,
+) -> rss_error {
+    if name.is_null() || out.is_null() {
+        return RSS_ERROR_NULL_ARGUMENT;
+    }
+    let (Some(backend), Some(name)) = (backend_from_c(backend), str_arg(name)) else {
+        return RSS_ERROR_INVALID_ARGUMENT;
+    };
+    match create_sender(backend, name) {
+        Some(sender) => {
+            *out = Box::into_raw(Box::new(rss_sender { sender }));
+            RSS_OK
+        }
+        None => RSS_ERROR_UNAVAILABLE,
+    }
+}
+
+/// Publish `height` rows of `width` pixels, `stride` bytes apart, starting at `pixels`.
+///
+/// # Safety
+/// `sender` must be from `rss_sender_create`; `pixels` must be readable for
+/// `stride * (height - 1) + width * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rss_sender_send(
+    sender: *mut rss_sender,
+    pixels: *const u8,
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: rss_pixel_format,
+) -> rss_error {
+    let (Some(sender), false) = (sender.as_mut(), pixels.is_null()) else {
+        return RSS_ERROR_NULL_ARGUMENT;
+    };
+    let Some(format) = format_from_c(format) else {
+        return RSS_ERROR_INVALID_ARGUMENT;
+    };
+    // Check the sizes before allocating: the frame and the caller's rows must both fit.
+    let row_bytes = (width as usize)
+        .checked_mul(format.bytes_per_pixel())
+        .filter(|&row_bytes| {
+            width != 0
+                && height != 0
+                && stride >= row_bytes
+                && row_bytes.checked_mul(height as usize).is_some()
+                && stride
+                    .checked_mul(height as usize - 1)
+                    .and_then(|span| span.checked_add(row_bytes))
+                    .is_some_and(|span| span <= isize::MAX as usize)
+        });
+    let Some(row_bytes) = row_bytes else {
+        return RSS_ERROR_INVALID_ARGUMENT;
+    };
+    let mut frame = Frame::new(width, height, format);
+    for y in 0..height {
+        let row = std::slice::from_raw_parts(pixels.add(y as usize * stride), row_bytes);
+        frame.row_mut(y).copy_from_slice(row);
+    }
+    match sender.sender.send_frame(&frame) {
+        Ok(()) => RSS_OK,
+        Err(_) => RSS_ERROR_PUBLISH_FAILED,
+    }
+}
+
+/// Stop publishing and free `sender`. Null is ignored.
+///
+/// # Safety
+/// `sender` must be null or from `rss_sender_create`, and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rss_sender_destroy(sender: *mut rss_sender) {
+    if !sender.is_null() {
+        drop(Box::from_raw(sender));
+    }
+}
+
+/// Connect to the source whose id (`rss_source_info::id`) or name is `source`.
+///
+/// # Safety
+/// `source` must be a NUL-terminated string; `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rss_receiver_connect(
+    source: *const c_char,
+    out: *mut *mut rss_receiver,
+) -> rss_error {
+    if source.is_null() || out.is_null() {
+        return RSS_ERROR_NULL_ARGUMENT;
+    }
+    let Some(wanted) = str_arg(source) else {
+        return RSS_ERROR_INVALID_ARGUMENT;
+    };
+    let sources = discover_sources();
+    let Some(info) = sources
+        .iter()
+        .find(|s| s.id() == wanted)
+        .or_else(|| sources.iter().find(|s| s.name == wanted))
+    else {
+        return RSS_ERROR_NOT_FOUND;
+    };
+    match connect_source(info) {
+        Some(receiver) => {
+            *out = Box::into_raw(Box::new(rss_receiver {
+                receiver,
+                pending: None,
+            }));
+            RSS_OK
+        }
+        None => RSS_ERROR_UNAVAILABLE,
+    }
+}
+
+/// Whether the source is still available (false for null).
+///
+/// # Safety
+/// `receiver` must be null or from `rss_receiver_connect`.
+#[no_mangle]
+pub unsafe extern "C" fn rss_receiver_is_connected(receiver: *const rss_receiver) -> bool {
+    receiver.as_ref().is_some_and(|r| r.receiver.is_connected())
+}
+
+/// The pending frame, or the next one from the source.
+fn next_frame(receiver: &mut rss_receiver) -> Result<Frame, rss_error> {
+    if let Some(frame) = receiver.pending.take() {
+        return Ok(frame);
+    }
+    match receiver.receiver.receive_frame() {
+        Some(frame) => Ok(frame),
+        None if receiver.receiver.is_connected() => Err(RSS_ERROR_NO_FRAME),
+        None => Err(RSS_ERROR_DISCONNECTED),
+    }
+}
+
+/// If a new frame arrived, pass it to `callback` with `user_data`. Returns `RSS_ERROR_NO_FRAME`
+/// when there is none yet.
+///
+/// # Safety
+/// `receiver` must be from `rss_receiver_connect`; `callback` must be safe to call with the
+/// frame pointer (valid only during the call).
+#[no_mangle]
+pub unsafe extern "C" fn rss_receiver_receive(
+    receiver: *mut rss_receiver,
+    callback: rss_frame_callback,
+    user_data: *mut c_void,
+) -> rss_error {
+    let (Some(receiver), Some(callback)) = (receiver.as_mut(), callback) else {
+        return RSS_ERROR_NULL_ARGUMENT;
+    };
+    match next_frame(receiver) {
+        Ok(frame) => {
+            let view = rss_frame {
+                pixels: frame.data().as_ptr(),
+                width: frame.width(),
+                height: frame.height(),
+                stride: frame.stride(),
+                format: format_to_c(frame.format()),
+            };
+            callback(&view, user_data);
+            RSS_OK
+        }
+        Err(error) => error,
+    }
+}
+
+/// If a new frame arrived, copy it tightly packed into `buffer` and describe it in `out`
+/// (`pixels` points into `buffer`). When `buffer_len` is too small, fills `out` with the size
+/// needed, keeps the frame for the next call and returns `RSS_ERROR_BUFFER_TOO_SMALL`.
+///
+/// # Safety
+/// `receiver` must be from `rss_receiver_connect`; `buffer` must be writable for `buffer_len`
+/// bytes (or null with length 0); `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn rss_receiver_receive_into(
+    receiver: *mut rss_receiver,
+    buffer: *mut u8,
+    buffer_len: usize,
+    out: *mut rss_frame,
+) -> rss_error {
+    let (Some(receiver), false) = (receiver.as_mut(), out.is_null()) else {
+        return RSS_ERROR_NULL_ARGUMENT;
+    };
+    if buffer.is_null() && buffer_len > 0 {
+        return RSS_ERROR_NULL_ARGUMENT;
+    }
+    let frame = match next_frame(receiver) {
+        Ok(frame) => frame,
+        Err(error) => return error,
+    };
+    let row_bytes = frame.row_bytes();
+    *out = rss_frame {
+        pixels: buffer,
+        width: frame.width(),
+        height: frame.height(),
+        stride: row_bytes,
+        format: format_to_c(frame.format()),
+    };
+    if buffer_len < row_bytes * frame.height() as usize {
+        (*out).pixels = ptr::null();
+        receiver.pending = Some(frame);
+        return RSS_ERROR_BUFFER_TOO_SMALL;
+    }
+    for y in 0..frame.height() {
+        ptr::copy_nonoverlapping(
+            frame.row(y).as_ptr(),
+            buffer.add(y as usize * row_bytes),
+            row_bytes,
+        );
+    }
+    RSS_OK
+}
+
+/// Disconnect and free `receiver`. Null is ignored.
+///
+/// # Safety
+/// `receiver` must be null or from `rss_receiver_connect`, and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rss_receiver_destroy(receiver: *mut rss_receiver) {
+    if !receiver.is_null() {
+        drop(Box::from_raw(receiver));
+    }
+}
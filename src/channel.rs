@@ -0,0 +1,457 @@
+//! Framed message channel over a shared memory buffer.
+//!
+//! `Spout::write_memory_buffer` / `read_memory_buffer` move raw bytes. `MessageSender` and
+//! `MessageReceiver` put a fixed header in front of each payload (magic, version, sender session,
+//! sequence number, length and CRC-32) so receivers can tell a new message from one they already
+//! read by looking at the header alone, and can reject torn or foreign data.
+//!
+//! The buffer is anything implementing `MemoryBuffer`: the Spout memory buffer on Windows
+//! (`Spout::memory_buffer`), or `FileMemoryBuffer`, a named buffer in the temp directory that
+//! works between processes on every platform (use it alongside Syphon, which has no memory buffer).
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::util::{file_safe_name, random_u64, shared_temp_dir};
+
+/// Identifies a framed message buffer.
+pub const MESSAGE_MAGIC: [u8; 4] = *b"RSSM";
+
+/// Wire format version written by `MessageSender`.
+pub const MESSAGE_VERSION: u16 = 1;
+
+/// Size of the header that precedes every payload.
+pub const MESSAGE_HEADER_LEN: usize = 32;
+
+/// Default maximum payload a `MessageReceiver` will accept.
+pub const DEFAULT_MAX_PAYLOAD: usize = 64 * 1024;
+
+/// A named byte buffer that is overwritten as a whole by writers and read as a prefix by readers.
+pub trait MemoryBuffer {
+    /// Replace the buffer contents with `data`. Returns false on failure.
+    fn write_bytes(&self, data: &[u8]) -> bool;
+
+    /// Copy up to `out.len()` bytes from the start of the buffer. Returns the number of bytes read (0 if empty or missing).
+    fn read_bytes(&self, out: &mut [u8]) -> usize;
+}
+
+impl<B: MemoryBuffer + ?Sized> MemoryBuffer for &B {
+    fn write_bytes(&self, data: &[u8]) -> bool {
+        (**self).write_bytes(data)
+    }
+
+    fn read_bytes(&self, out: &mut [u8]) -> usize {
+        (**self).read_bytes(out)
+    }
+}
+
+/// How a message payload is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadKind {
+    /// Opaque bytes.
+    Bytes,
+    /// UTF-8 JSON written by `MessageSender::send_value` (requires the `serde` feature to decode).
+    Json,
+}
+
+impl PayloadKind {
+    fn to_wire(self) -> u16 {
+        match self {
+            PayloadKind::Bytes => 0,
+            PayloadKind::Json => 1,
+        }
+    }
+
+    fn from_wire(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(PayloadKind::Bytes),
+            1 => Some(PayloadKind::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Errors from sending or receiving framed messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelError {
+    /// The underlying buffer write failed.
+    Write,
+    /// The payload is larger than the channel accepts.
+    TooLarge { len: usize, max: usize },
+    /// The buffer holds fewer bytes than the header promises (torn or partial write).
+    Truncated,
+    /// The buffer does not start with `MESSAGE_MAGIC`.
+    BadMagic,
+    /// The header was written by an incompatible version.
+    UnsupportedVersion(u16),
+    /// The header names a payload encoding this version does not know.
+    UnknownPayloadKind(u16),
+    /// The CRC-32 does not match (torn read or corruption).
+    ChecksumMismatch,
+    /// Serializing or deserializing a payload failed.
+    Serde(String),
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelError::Write => write!(f, "failed to write memory buffer"),
+            ChannelError::TooLarge { len, max } => {
+                write!(f, "payload of {} bytes exceeds limit of {} bytes", len, max)
+            }
+            ChannelError::Truncated => write!(f, "message is truncated"),
+            ChannelError::BadMagic => write!(f, "buffer does not contain a framed message"),
+            ChannelError::UnsupportedVersion(v) => write!(f, "unsupported message version {}", v),
+            ChannelError::UnknownPayloadKind(k) => write!(f, "unknown payload kind {}", k),
+            ChannelError::ChecksumMismatch => write!(f, "message checksum mismatch"),
+            ChannelError::Serde(e) => write!(f, "payload serialization failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ChannelError {}
+
+/// A decoded message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// Random id of the `MessageSender` that wrote it; changes when the sender restarts.
+    pub session: u32,
+    /// Per-session sequence number, starting at 1.
+    pub sequence: u64,
+    pub kind: PayloadKind,
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    /// Deserialize a JSON payload written by `MessageSender::send_value`.
+    #[cfg(feature = "serde")]
+    pub fn value<T: serde::de::DeserializeOwned>(&self) -> Result<T, ChannelError> {
+        serde_json::from_slice(&self.payload).map_err(|e| ChannelError::Serde(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    version: u16,
+    kind: u16,
+    session: u32,
+    len: u32,
+    sequence: u64,
+    checksum: u32,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; MESSAGE_HEADER_LEN] {
+        let mut out = [0u8; MESSAGE_HEADER_LEN];
+        out[0..4].copy_from_slice(&MESSAGE_MAGIC);
+        out[4..6].copy_from_slice(&self.version.to_le_bytes());
+        out[6..8].copy_from_slice(&self.kind.to_le_bytes());
+        out[8..12].copy_from_slice(&self.session.to_le_bytes());
+        out[12..16].copy_from_slice(&self.len.to_le_bytes());
+        out[16..24].copy_from_slice(&self.sequence.to_le_bytes());
+        out[24..28].copy_from_slice(&self.checksum.to_le_bytes());
+        out
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, ChannelError> {
+        if bytes.len() < MESSAGE_HEADER_LEN {
+            return Err(ChannelError::Truncated);
+        }
+        if bytes[0..4] != MESSAGE_MAGIC {
+            return Err(ChannelError::BadMagic);
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let version = u16_at(4);
+        if version != MESSAGE_VERSION {
+            return Err(ChannelError::UnsupportedVersion(version));
+        }
+        Ok(Self {
+            version,
+            kind: u16_at(6),
+            session: u32_at(8),
+            len: u32_at(12),
+            sequence: u64::from_le_bytes(bytes[16..24].try_into().unwrap()),
+            checksum: u32_at(24),
+        })
+    }
+
+    /// CRC-32 over the header fields (excluding the checksum itself) and the payload.
+    fn compute_checksum(&self, payload: &[u8]) -> u32 {
+        let mut header = self.encode();
+        header[24..28].fill(0);
+        crc32_update(crc32_update(!0, &header), payload) ^ !0
+    }
+}
+
+/// Writes framed messages to a `MemoryBuffer`. Each send replaces the previous message.
+pub struct MessageSender<B: MemoryBuffer> {
+    buffer: B,
+    session: u32,
+    sequence: u64,
+    max_payload: usize,
+}
+
+impl<B: MemoryBuffer> MessageSender<B> {
+    pub fn new(buffer: B) -> Self {
+        Self {
+            buffer,
+            session: random_u64() as u32,
+            sequence: 0,
+            max_payload: DEFAULT_MAX_PAYLOAD,
+        }
+    }
+
+    /// Reject payloads larger than `max` bytes (default `DEFAULT_MAX_PAYLOAD`).
+    pub fn with_max_payload(mut self, max: usize) -> Self {
+        self.max_payload = max.min(u32::MAX as usize);
+        self
+    }
+
+    /// This sender's session id (written into every header).
+    pub fn session(&self) -> u32 {
+        self.session
+    }
+
+    /// Sequence number of the last message sent (0 before the first send).
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+
+    /// Send opaque bytes. Returns the message's sequence number.
+    pub fn send(&mut self, payload: &[u8]) -> Result<u64, ChannelError> {
+        self.send_kind(PayloadKind::Bytes, payload)
+    }
+
+    /// Serialize `value` as JSON and send it.
+    #[cfg(feature = "serde")]
+    pub fn send_value<T: serde::Serialize + ?Sized>(&mut self, value: &T) -> Result<u64, ChannelError> {
+        let payload = serde_json::to_vec(value).map_err(|e| ChannelError::Serde(e.to_string()))?;
+        self.send_kind(PayloadKind::Json, &payload)
+    }
+
+    fn send_kind(&mut self, kind: PayloadKind, payload: &[u8]) -> Result<u64, ChannelError> {
+        if payload.len() > self.max_payload {
+            return Err(ChannelError::TooLarge {
+                len: payload.len(),
+                max: self.max_payload,
+            });
+        }
+        let sequence = self.sequence + 1;
+        let mut header = Header {
+            version: MESSAGE_VERSION,
+            kind: kind.to_wire(),
+            session: self.session,
+            len: payload.len() as u32,
+            sequence,
+            checksum: 0,
+        };
+        header.checksum = header.compute_checksum(payload);
+        let mut bytes = Vec::with_capacity(MESSAGE_HEADER_LEN + payload.len());
+        bytes.extend_from_slice(&header.encode());
+        bytes.extend_from_slice(payload);
+        if !self.buffer.write_bytes(&bytes) {
+            return Err(ChannelError::Write);
+        }
+        self.sequence = sequence;
+        Ok(sequence)
+    }
+}
+
+/// Reads framed messages from a `MemoryBuffer`, returning each message once.
+///
+/// `try_recv` first reads only the header; the payload is read only if the (session, sequence)
+/// pair differs from the last message returned.
+pub struct MessageReceiver<B: MemoryBuffer> {
+    buffer: B,
+    last: Option<(u32, u64)>,
+    max_payload: usize,
+    scratch: Vec<u8>,
+}
+
+impl<B: MemoryBuffer> MessageReceiver<B> {
+    pub fn new(buffer: B) -> Self {
+        Self {
+            buffer,
+            last: None,
+            max_payload: DEFAULT_MAX_PAYLOAD,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Reject messages whose header claims more than `max` payload bytes.
+    pub fn with_max_payload(mut self, max: usize) -> Self {
+        self.max_payload = max;
+        self
+    }
+
+    /// (session, sequence) of the last message returned.
+    pub fn last_seen(&self) -> Option<(u32, u64)> {
+        self.last
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+
+    /// True if the buffer holds a message that `try_recv` has not returned yet. Reads only the header.
+    pub fn has_new_message(&self) -> bool {
+        matches!(self.peek_header(), Ok(Some(h)) if self.last != Some((h.session, h.sequence)))
+    }
+
+    /// Next unseen message: `Ok(None)` if the buffer is empty or holds the last message already returned.
+    /// A torn or corrupt message returns an error and is retried on the next call.
+    pub fn try_recv(&mut self) -> Result<Option<Message>, ChannelError> {
+        let Some(header) = self.peek_header()? else {
+            return Ok(None);
+        };
+        if self.last == Some((header.session, header.sequence)) {
+            return Ok(None);
+        }
+        let kind = PayloadKind::from_wire(header.kind)
+            .ok_or(ChannelError::UnknownPayloadKind(header.kind))?;
+        let len = header.len as usize;
+        if len > self.max_payload {
+            return Err(ChannelError::TooLarge {
+                len,
+                max: self.max_payload,
+            });
+        }
+        self.scratch.resize(MESSAGE_HEADER_LEN + len, 0);
+        let n = self.buffer.read_bytes(&mut self.scratch);
+        if n < MESSAGE_HEADER_LEN + len {
+            return Err(ChannelError::Truncated);
+        }
+        // Re-decode: the writer may have replaced the message between the two reads.
+        let header = Header::decode(&self.scratch)?;
+        if header.len as usize != len {
+            return Err(ChannelError::Truncated);
+        }
+        let payload = &self.scratch[MESSAGE_HEADER_LEN..MESSAGE_HEADER_LEN + len];
+        if header.compute_checksum(payload) != header.checksum {
+            return Err(ChannelError::ChecksumMismatch);
+        }
+        self.last = Some((header.session, header.sequence));
+        Ok(Some(Message {
+            session: header.session,
+            sequence: header.sequence,
+            kind,
+            payload: payload.to_vec(),
+        }))
+    }
+
+    /// Next unseen message decoded from JSON. `Ok(None)` if there is no new message.
+    #[cfg(feature = "serde")]
+    pub fn try_recv_value<T: serde::de::DeserializeOwned>(&mut self) -> Result<Option<T>, ChannelError> {
+        match self.try_recv()? {
+            Some(message) => message.value().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn peek_header(&self) -> Result<Option<Header>, ChannelError> {
+        let mut bytes = [0u8; MESSAGE_HEADER_LEN];
+        let n = self.buffer.read_bytes(&mut bytes);
+        if n == 0 {
+            return Ok(None);
+        }
+        Header::decode(&bytes[..n]).map(Some)
+    }
+}
+
+/// A named memory buffer backed by a file in the system temp directory.
+///
+/// Writes go to a temporary file that is then renamed over the buffer, so readers in other
+/// processes always see a complete message. Works on every platform.
+#[derive(Debug, Clone)]
+pub struct FileMemoryBuffer {
+    path: PathBuf,
+}
+
+impl FileMemoryBuffer {
+    /// Buffer called `name` in the shared `rusty-syphon-spout` temp directory.
+    /// Names are sanitized so any server or sender name can be used.
+    pub fn new(name: &str) -> Self {
+        Self::at_path(shared_temp_dir().join(format!("{}.msgbuf", file_safe_name(name))))
+    }
+
+    /// Buffer stored at an explicit path.
+    pub fn at_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Delete the backing file.
+    pub fn remove(&self) -> std::io::Result<()> {
+        std::fs::remove_file(&self.path)
+    }
+}
+
+impl MemoryBuffer for FileMemoryBuffer {
+    fn write_bytes(&self, data: &[u8]) -> bool {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        if let Some(parent) = self.path.parent() {
+            if std::fs::create_dir_all(parent).is_err() {
+                return false;
+            }
+        }
+        let tmp = self.path.with_extension(format!(
+            "tmp{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        if std::fs::write(&tmp, data).is_err() {
+            let _ = std::fs::remove_file(&tmp);
+            return false;
+        }
+        std::fs::rename(&tmp, &self.path).is_ok()
+    }
+
+    fn read_bytes(&self, out: &mut [u8]) -> usize {
+        use std::io::Read;
+        let Ok(file) = std::fs::File::open(&self.path) else {
+            return 0;
+        };
+        let mut n = 0;
+        let mut reader = file.take(out.len() as u64);
+        while n < out.len() {
+            match reader.read(&mut out[n..]) {
+                Ok(0) | Err(_) => break,
+                Ok(k) => n += k,
+            }
+        }
+        n
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// Feed `data` into a running (pre-inverted) CRC-32.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc = CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
@@ -0,0 +1,419 @@
+//! Color space tags and CPU conversion between them.
+//!
+//! Every `Frame` carries a `ColorSpace` (default `Srgb`), and `LocalServer`s advertise one in
+//! their directory entry. Syphon and Spout have no place for color metadata, so
+//! `advertise_color_space` stores it next to the server name in the temp directory where
+//! `advertised_color_space` can look it up.
+//!
+//! Conversion decodes with the source transfer function to linear light (1.0 = SDR reference
+//! white, 203 cd/m² for PQ and HLG per ITU-R BT.2408), maps primaries through CIE XYZ (all
+//! supported spaces use a D65 white point), then encodes with the destination transfer function.
+//! Out-of-gamut and above-white values are clipped.
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::frame::{Frame, PixelFormat};
+use crate::util::{file_safe_name, shared_temp_dir};
+
+/// Luminance of SDR reference white in cd/m², used to place PQ and HLG relative to SDR.
+pub const SDR_REFERENCE_WHITE_NITS: f32 = 203.0;
+
+/// Nominal peak luminance assumed for HLG displays, in cd/m².
+pub const HLG_NOMINAL_PEAK_NITS: f32 = 1000.0;
+
+/// Color primaries (all with a D65 white point).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primaries {
+    /// ITU-R BT.709, shared by sRGB.
+    Bt709,
+    /// DCI-P3 primaries with D65 white (Display P3).
+    DisplayP3,
+    /// ITU-R BT.2020 / BT.2100.
+    Bt2020,
+}
+
+impl Primaries {
+    /// Red, green, blue chromaticities (x, y).
+    pub fn chromaticities(&self) -> [(f64, f64); 3] {
+        match self {
+            Primaries::Bt709 => [(0.640, 0.330), (0.300, 0.600), (0.150, 0.060)],
+            Primaries::DisplayP3 => [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)],
+            Primaries::Bt2020 => [(0.708, 0.292), (0.170, 0.797), (0.131, 0.046)],
+        }
+    }
+
+    /// Linear RGB to CIE XYZ (Y of white = 1).
+    pub fn rgb_to_xyz(&self) -> [[f64; 3]; 3] {
+        const D65: (f64, f64) = (0.3127, 0.3290);
+        let xyz = |(x, y): (f64, f64)| [x / y, 1.0, (1.0 - x - y) / y];
+        let [r, g, b] = self.chromaticities().map(xyz);
+        let m = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+        let s = mul_vec(&invert(&m), xyz(D65));
+        [
+            [m[0][0] * s[0], m[0][1] * s[1], m[0][2] * s[2]],
+            [m[1][0] * s[0], m[1][1] * s[1], m[1][2] * s[2]],
+            [m[2][0] * s[0], m[2][1] * s[1], m[2][2] * s[2]],
+        ]
+    }
+
+    /// Luminance weights (the Y row of `rgb_to_xyz`).
+    pub fn luma_coefficients(&self) -> [f64; 3] {
+        self.rgb_to_xyz()[1]
+    }
+}
+
+/// Transfer function (encoding between signal values and linear light).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferFunction {
+    /// IEC 61966-2-1 piecewise sRGB curve.
+    Srgb,
+    /// No encoding.
+    Linear,
+    /// ITU-R BT.709 OETF; decoding uses its exact inverse.
+    Bt709,
+    /// SMPTE ST 2084 perceptual quantizer (absolute, 1.0 = 10000 cd/m²).
+    Pq,
+    /// ITU-R BT.2100 hybrid log-gamma (relative, scene-referred).
+    Hlg,
+}
+
+impl TransferFunction {
+    /// Signal (0..1) to linear. For `Pq` the result is absolute (1.0 = 10000 cd/m²); for `Hlg`
+    /// it is scene light before the OOTF.
+    pub fn to_linear(&self, v: f32) -> f32 {
+        let v = v.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Srgb => {
+                if v <= 0.04045 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Linear => v,
+            TransferFunction::Bt709 => {
+                if v < 0.081 {
+                    v / 4.5
+                } else {
+                    ((v + 0.099) / 1.099).powf(1.0 / 0.45)
+                }
+            }
+            TransferFunction::Pq => {
+                let p = v.powf(1.0 / PQ_M2);
+                ((p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p)).powf(1.0 / PQ_M1)
+            }
+            TransferFunction::Hlg => {
+                if v <= 0.5 {
+                    v * v / 3.0
+                } else {
+                    (((v - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+                }
+            }
+        }
+    }
+
+    /// Linear to signal (0..1), the inverse of `to_linear`.
+    pub fn from_linear(&self, l: f32) -> f32 {
+        let l = l.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Srgb => {
+                if l <= 0.0031308 {
+                    l * 12.92
+                } else {
+                    1.055 * l.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Linear => l,
+            TransferFunction::Bt709 => {
+                if l < 0.018 {
+                    l * 4.5
+                } else {
+                    1.099 * l.powf(0.45) - 0.099
+                }
+            }
+            TransferFunction::Pq => {
+                let p = l.powf(PQ_M1);
+                ((PQ_C1 + PQ_C2 * p) / (1.0 + PQ_C3 * p)).powf(PQ_M2)
+            }
+            TransferFunction::Hlg => {
+                if l <= 1.0 / 12.0 {
+                    (3.0 * l).sqrt()
+                } else {
+                    HLG_A * (12.0 * l - HLG_B).ln() + HLG_C
+                }
+            }
+        }
+    }
+}
+
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 0.284_668_92;
+const HLG_C: f32 = 0.559_910_7;
+
+/// What the pixel values of a frame or source mean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorSpace {
+    /// sRGB: BT.709 primaries, sRGB transfer. What Syphon and Spout senders usually publish.
+    #[default]
+    Srgb,
+    /// BT.709 primaries, linear light.
+    LinearSrgb,
+    /// ITU-R BT.709 video.
+    Rec709,
+    /// Display P3: P3 primaries, sRGB transfer.
+    DisplayP3,
+    /// BT.2100 PQ (HDR10).
+    Rec2020Pq,
+    /// BT.2100 HLG.
+    Rec2020Hlg,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 6] = [
+        ColorSpace::Srgb,
+        ColorSpace::LinearSrgb,
+        ColorSpace::Rec709,
+        ColorSpace::DisplayP3,
+        ColorSpace::Rec2020Pq,
+        ColorSpace::Rec2020Hlg,
+    ];
+
+    pub fn primaries(&self) -> Primaries {
+        match self {
+            ColorSpace::Srgb | ColorSpace::LinearSrgb | ColorSpace::Rec709 => Primaries::Bt709,
+            ColorSpace::DisplayP3 => Primaries::DisplayP3,
+            ColorSpace::Rec2020Pq | ColorSpace::Rec2020Hlg => Primaries::Bt2020,
+        }
+    }
+
+    pub fn transfer(&self) -> TransferFunction {
+        match self {
+            ColorSpace::Srgb | ColorSpace::DisplayP3 => TransferFunction::Srgb,
+            ColorSpace::LinearSrgb => TransferFunction::Linear,
+            ColorSpace::Rec709 => TransferFunction::Bt709,
+            ColorSpace::Rec2020Pq => TransferFunction::Pq,
+            ColorSpace::Rec2020Hlg => TransferFunction::Hlg,
+        }
+    }
+
+    /// True for the HDR (PQ and HLG) spaces.
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, ColorSpace::Rec2020Pq | ColorSpace::Rec2020Hlg)
+    }
+
+    /// Stable identifier used when advertising the color space (e.g. `"srgb"`, `"rec2020-pq"`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "srgb",
+            ColorSpace::LinearSrgb => "linear-srgb",
+            ColorSpace::Rec709 => "rec709",
+            ColorSpace::DisplayP3 => "display-p3",
+            ColorSpace::Rec2020Pq => "rec2020-pq",
+            ColorSpace::Rec2020Hlg => "rec2020-hlg",
+        }
+    }
+
+    /// Parse a name produced by `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// Converts RGB values between two color spaces. Build once and reuse for every frame.
+#[derive(Debug, Clone)]
+pub struct ColorConverter {
+    from: ColorSpace,
+    to: ColorSpace,
+    matrix: [[f32; 3]; 3],
+    decode_lut: [f32; 256],
+}
+
+impl ColorConverter {
+    pub fn new(from: ColorSpace, to: ColorSpace) -> Self {
+        let to_xyz = from.primaries().rgb_to_xyz();
+        let from_xyz = invert(&to.primaries().rgb_to_xyz());
+        let m = mul(&from_xyz, &to_xyz);
+        let matrix = m.map(|row| row.map(|v| v as f32));
+        let transfer = from.transfer();
+        let decode_lut = std::array::from_fn(|i| transfer.to_linear(i as f32 / 255.0));
+        Self {
+            from,
+            to,
+            matrix,
+            decode_lut,
+        }
+    }
+
+    pub fn from_space(&self) -> ColorSpace {
+        self.from
+    }
+
+    pub fn to_space(&self) -> ColorSpace {
+        self.to
+    }
+
+    /// True if conversion leaves values unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.from == self.to
+    }
+
+    /// Convert one encoded RGB triple (0..1) in the source space to the destination space.
+    pub fn convert_rgb(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let linear = rgb.map(|v| self.from.transfer().to_linear(v));
+        self.encode(self.decode_relative(linear))
+    }
+
+    /// Convert `frame`'s pixels in place and retag it. Alpha is unchanged.
+    pub fn convert_in_place(&self, frame: &mut Frame) {
+        if !self.is_identity() {
+            let (r, b) = match frame.format() {
+                PixelFormat::Rgba8 => (0, 2),
+                PixelFormat::Bgra8 => (2, 0),
+            };
+            for y in 0..frame.height() {
+                for px in frame.row_mut(y).chunks_exact_mut(4) {
+                    let linear = [
+                        self.decode_lut[px[r] as usize],
+                        self.decode_lut[px[1] as usize],
+                        self.decode_lut[px[b] as usize],
+                    ];
+                    let out = self.encode(self.decode_relative(linear));
+                    px[r] = to_u8(out[0]);
+                    px[1] = to_u8(out[1]);
+                    px[b] = to_u8(out[2]);
+                }
+            }
+        }
+        frame.set_color_space(self.to);
+    }
+
+    /// A converted copy of `frame`.
+    pub fn convert(&self, frame: &Frame) -> Frame {
+        let mut out = frame.clone();
+        self.convert_in_place(&mut out);
+        out
+    }
+
+    /// Transfer-decoded values to linear light relative to SDR white, in the destination primaries.
+    fn decode_relative(&self, linear: [f32; 3]) -> [f32; 3] {
+        let relative = match self.from {
+            ColorSpace::Rec2020Pq => linear.map(|v| v * 10000.0 / SDR_REFERENCE_WHITE_NITS),
+            ColorSpace::Rec2020Hlg => {
+                // BT.2100 OOTF with system gamma 1.2 for a 1000 cd/m² display.
+                let ys = luma(Primaries::Bt2020, linear).max(0.0);
+                let gain = HLG_NOMINAL_PEAK_NITS / SDR_REFERENCE_WHITE_NITS * ys.powf(0.2);
+                linear.map(|v| v * gain)
+            }
+            _ => linear,
+        };
+        mul_vec_f32(&self.matrix, relative)
+    }
+
+    /// Linear light relative to SDR white (destination primaries) to encoded values.
+    fn encode(&self, relative: [f32; 3]) -> [f32; 3] {
+        let transfer = self.to.transfer();
+        let linear = match self.to {
+            ColorSpace::Rec2020Pq => {
+                relative.map(|v| v.max(0.0) * SDR_REFERENCE_WHITE_NITS / 10000.0)
+            }
+            ColorSpace::Rec2020Hlg => {
+                // Inverse OOTF: display light back to scene light.
+                let display =
+                    relative.map(|v| v.max(0.0) * SDR_REFERENCE_WHITE_NITS / HLG_NOMINAL_PEAK_NITS);
+                let yd = luma(Primaries::Bt2020, display);
+                if yd <= 0.0 {
+                    [0.0; 3]
+                } else {
+                    let scale = yd.powf(-0.2 / 1.2);
+                    display.map(|v| v * scale)
+                }
+            }
+            _ => relative,
+        };
+        linear.map(|v| transfer.from_linear(v))
+    }
+}
+
+/// Convert `frame` from its tagged color space to `to`.
+pub fn convert_color_space(frame: &Frame, to: ColorSpace) -> Frame {
+    ColorConverter::new(frame.color_space(), to).convert(frame)
+}
+
+/// Record the color space of a Syphon server or Spout sender called `server_name` so other
+/// processes can find it with `advertised_color_space`. Call `withdraw_color_space` when the server stops.
+pub fn advertise_color_space(server_name: &str, color_space: ColorSpace) -> io::Result<()> {
+    let path = advertisement_path(server_name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, color_space.name())
+}
+
+/// The color space advertised for `server_name`, if any.
+pub fn advertised_color_space(server_name: &str) -> Option<ColorSpace> {
+    let name = std::fs::read_to_string(advertisement_path(server_name)).ok()?;
+    ColorSpace::from_name(name.trim())
+}
+
+/// Remove the advertisement for `server_name`.
+pub fn withdraw_color_space(server_name: &str) {
+    let _ = std::fs::remove_file(advertisement_path(server_name));
+}
+
+fn advertisement_path(server_name: &str) -> PathBuf {
+    shared_temp_dir()
+        .join("color")
+        .join(format!("{}.colorspace", file_safe_name(server_name)))
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn luma(primaries: Primaries, rgb: [f32; 3]) -> f32 {
+    let k = primaries.luma_coefficients();
+    k[0] as f32 * rgb[0] + k[1] as f32 * rgb[1] + k[2] as f32 * rgb[2]
+}
+
+fn mul(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+fn mul_vec(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    std::array::from_fn(|i| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2])
+}
+
+fn mul_vec_f32(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2])
+}
+
+fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    let c =
+        |r0: usize, c0: usize, r1: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    [
+        [
+            c(1, 1, 2, 2) / det,
+            -c(0, 1, 2, 2) / det,
+            c(0, 1, 1, 2) / det,
+        ],
+        [
+            -c(1, 0, 2, 2) / det,
+            c(0, 0, 2, 2) / det,
+            -c(0, 0, 1, 2) / det,
+        ],
+        [
+            c(1, 0, 2, 1) / det,
+            -c(0, 0, 2, 1) / det,
+            c(0, 0, 1, 1) / det,
+        ],
+    ]
+}
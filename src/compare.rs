@@ -0,0 +1,393 @@
+//! Frame comparison with tolerance: max/mean error, PSNR, SSIM, diff images and test assertions.
+//!
+//! Frames of different `PixelFormat`s compare by channel meaning (R with R), and `flipped`
+//! compares against the vertically mirrored actual frame, so GPU round trips can be checked
+//! without normalizing them first.
+//!
+//! ```ignore
+//! FrameCompare::new().tolerance(1).min_psnr(45.0).assert(&sent, &received);
+//! ```
+
+use std::fmt;
+
+use crate::frame::{Frame, PixelFormat};
+use crate::geometry::Size;
+
+/// One pixel that differs, as RGBA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelDifference {
+    pub x: u32,
+    pub y: u32,
+    pub expected: [u8; 4],
+    pub actual: [u8; 4],
+}
+
+impl PixelDifference {
+    /// Largest per-channel difference.
+    pub fn max_channel_diff(&self) -> u8 {
+        (0..4)
+            .map(|c| self.expected[c].abs_diff(self.actual[c]))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for PixelDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}): expected {:?}, got {:?}",
+            self.x, self.y, self.expected, self.actual
+        )
+    }
+}
+
+/// Result of comparing two frames of the same size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameComparison {
+    /// Largest difference of any channel of any pixel.
+    pub max_abs_diff: u8,
+    /// Mean absolute channel difference.
+    pub mean_abs_error: f64,
+    /// Mean squared channel difference.
+    pub mse: f64,
+    /// Peak signal-to-noise ratio in dB; infinite for identical frames.
+    pub psnr: f64,
+    /// Structural similarity of luma, -1..1 (1 = identical), over 8x8 windows.
+    pub ssim: f64,
+    /// Pixels with any channel differing by more than the tolerance.
+    pub differing_pixels: u64,
+    /// First differing pixel in row order (beyond the tolerance).
+    pub first_difference: Option<PixelDifference>,
+    /// The pixel with the largest channel difference, if any differ.
+    pub worst_difference: Option<PixelDifference>,
+}
+
+impl FrameComparison {
+    pub fn is_identical(&self) -> bool {
+        self.max_abs_diff == 0
+    }
+}
+
+/// Why `FrameCompare::check` rejected a pair of frames.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompareError {
+    SizeMismatch {
+        expected: Size,
+        actual: Size,
+    },
+    /// Some channel differs by more than `tolerance`.
+    ExceedsTolerance {
+        tolerance: u8,
+        comparison: Box<FrameComparison>,
+    },
+    PsnrTooLow {
+        min: f64,
+        comparison: Box<FrameComparison>,
+    },
+    SsimTooLow {
+        min: f64,
+        comparison: Box<FrameComparison>,
+    },
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (reason, comparison) = match self {
+            CompareError::SizeMismatch { expected, actual } => {
+                return write!(
+                    f,
+                    "frame size mismatch: expected {}x{}, got {}x{}",
+                    expected.width, expected.height, actual.width, actual.height
+                );
+            }
+            CompareError::ExceedsTolerance {
+                tolerance,
+                comparison,
+            } => (
+                format!(
+                    "{} pixels differ by more than {} (max {})",
+                    comparison.differing_pixels, tolerance, comparison.max_abs_diff
+                ),
+                comparison,
+            ),
+            CompareError::PsnrTooLow { min, comparison } => (
+                format!("PSNR {:.2} dB below {:.2} dB", comparison.psnr, min),
+                comparison,
+            ),
+            CompareError::SsimTooLow { min, comparison } => (
+                format!("SSIM {:.4} below {:.4}", comparison.ssim, min),
+                comparison,
+            ),
+        };
+        write!(f, "frames diverge: {reason}")?;
+        if let Some(first) = comparison.first_difference {
+            write!(f, "\n  first difference at {first}")?;
+        }
+        if let Some(worst) = comparison.worst_difference {
+            write!(f, "\n  worst difference at {worst}")?;
+        }
+        write!(
+            f,
+            "\n  max abs diff {}, mean error {:.4}, PSNR {:.2} dB, SSIM {:.4}",
+            comparison.max_abs_diff, comparison.mean_abs_error, comparison.psnr, comparison.ssim
+        )
+    }
+}
+
+impl std::error::Error for CompareError {}
+
+/// Comparison settings and pass criteria. The default is an exact match.
+#[derive(Debug, Clone, Default)]
+pub struct FrameCompare {
+    tolerance: u8,
+    min_psnr: Option<f64>,
+    min_ssim: Option<f64>,
+    ignore_alpha: bool,
+    flipped: bool,
+}
+
+impl FrameCompare {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Largest per-channel difference that still counts as equal.
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Also require at least this PSNR (dB).
+    pub fn min_psnr(mut self, db: f64) -> Self {
+        self.min_psnr = Some(db);
+        self
+    }
+
+    /// Also require at least this SSIM.
+    pub fn min_ssim(mut self, ssim: f64) -> Self {
+        self.min_ssim = Some(ssim);
+        self
+    }
+
+    /// Skip the alpha channel (e.g. when a backend forces opaque output).
+    pub fn ignore_alpha(mut self) -> Self {
+        self.ignore_alpha = true;
+        self
+    }
+
+    /// Compare against the actual frame mirrored vertically (bottom-up GL readbacks).
+    pub fn flipped(mut self) -> Self {
+        self.flipped = true;
+        self
+    }
+
+    /// Metrics for `actual` against `expected`. `None` if the sizes differ.
+    pub fn compare(&self, expected: &Frame, actual: &Frame) -> Option<FrameComparison> {
+        if expected.size() != actual.size() {
+            return None;
+        }
+        let (width, height) = (expected.width(), expected.height());
+        let channels = if self.ignore_alpha { 3 } else { 4 };
+        let mut sum_abs = 0u64;
+        let mut sum_sq = 0u64;
+        let mut max_abs_diff = 0u8;
+        let mut differing_pixels = 0u64;
+        let mut first_difference = None;
+        let mut worst_difference = None;
+        for y in 0..height {
+            for x in 0..width {
+                let e = rgba_at(expected, x, y);
+                let a = rgba_at(actual, x, self.actual_row(y, height));
+                let mut pixel_max = 0u8;
+                for c in 0..channels {
+                    let d = e[c].abs_diff(a[c]);
+                    sum_abs += d as u64;
+                    sum_sq += d as u64 * d as u64;
+                    pixel_max = pixel_max.max(d);
+                }
+                let diff = PixelDifference {
+                    x,
+                    y,
+                    expected: e,
+                    actual: a,
+                };
+                if pixel_max > self.tolerance {
+                    differing_pixels += 1;
+                    first_difference.get_or_insert(diff);
+                }
+                if pixel_max > max_abs_diff {
+                    max_abs_diff = pixel_max;
+                    worst_difference = Some(diff);
+                }
+            }
+        }
+        let samples = (width as u64 * height as u64 * channels as u64).max(1) as f64;
+        let mse = sum_sq as f64 / samples;
+        let psnr = if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (255.0 * 255.0 / mse).log10()
+        };
+        Some(FrameComparison {
+            max_abs_diff,
+            mean_abs_error: sum_abs as f64 / samples,
+            mse,
+            psnr,
+            ssim: self.ssim(expected, actual),
+            differing_pixels,
+            first_difference,
+            worst_difference,
+        })
+    }
+
+    /// Compare and apply the pass criteria.
+    pub fn check(&self, expected: &Frame, actual: &Frame) -> Result<FrameComparison, CompareError> {
+        let comparison = self
+            .compare(expected, actual)
+            .ok_or(CompareError::SizeMismatch {
+                expected: expected.size(),
+                actual: actual.size(),
+            })?;
+        if comparison.max_abs_diff > self.tolerance {
+            return Err(CompareError::ExceedsTolerance {
+                tolerance: self.tolerance,
+                comparison: Box::new(comparison),
+            });
+        }
+        if let Some(min) = self.min_psnr.filter(|min| comparison.psnr < *min) {
+            return Err(CompareError::PsnrTooLow {
+                min,
+                comparison: Box::new(comparison),
+            });
+        }
+        if let Some(min) = self.min_ssim.filter(|min| comparison.ssim < *min) {
+            return Err(CompareError::SsimTooLow {
+                min,
+                comparison: Box::new(comparison),
+            });
+        }
+        Ok(comparison)
+    }
+
+    /// Panic with where and how much the frames diverge unless `check` passes.
+    #[track_caller]
+    pub fn assert(&self, expected: &Frame, actual: &Frame) -> FrameComparison {
+        match self.check(expected, actual) {
+            Ok(comparison) => comparison,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Per-channel absolute differences as an opaque RGBA frame (black = equal). `None` if the
+    /// sizes differ.
+    pub fn diff_image(&self, expected: &Frame, actual: &Frame) -> Option<Frame> {
+        if expected.size() != actual.size() {
+            return None;
+        }
+        let (width, height) = (expected.width(), expected.height());
+        let mut out = Frame::new(width, height, PixelFormat::Rgba8);
+        for y in 0..height {
+            for x in 0..width {
+                let e = rgba_at(expected, x, y);
+                let a = rgba_at(actual, x, self.actual_row(y, height));
+                let px = &mut out.row_mut(y)[x as usize * 4..x as usize * 4 + 4];
+                for c in 0..3 {
+                    px[c] = e[c].abs_diff(a[c]);
+                }
+                px[3] = 255;
+            }
+        }
+        Some(out)
+    }
+
+    fn actual_row(&self, y: u32, height: u32) -> u32 {
+        if self.flipped {
+            height - 1 - y
+        } else {
+            y
+        }
+    }
+
+    /// Mean SSIM of BT.601 luma over 8x8 windows stepped by 4 (one window for small frames).
+    fn ssim(&self, expected: &Frame, actual: &Frame) -> f64 {
+        let (width, height) = (expected.width(), expected.height());
+        if width == 0 || height == 0 {
+            return 1.0;
+        }
+        let luma = |frame: &Frame, flip: bool| -> Vec<f64> {
+            let mut out = Vec::with_capacity(width as usize * height as usize);
+            for y in 0..height {
+                let row = if flip { self.actual_row(y, height) } else { y };
+                for x in 0..width {
+                    let [r, g, b, _] = rgba_at(frame, x, row);
+                    out.push(0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64);
+                }
+            }
+            out
+        };
+        let (ea, aa) = (luma(expected, false), luma(actual, true));
+        let (win_w, win_h) = (width.min(8), height.min(8));
+        let (mut total, mut windows) = (0.0, 0u32);
+        let mut y0 = 0;
+        while y0 + win_h <= height {
+            let mut x0 = 0;
+            while x0 + win_w <= width {
+                total += window_ssim(&ea, &aa, width, x0, y0, win_w, win_h);
+                windows += 1;
+                x0 += 4;
+            }
+            y0 += 4;
+        }
+        total / windows as f64
+    }
+}
+
+/// Exact comparison of `actual` against `expected`. `None` if the sizes differ.
+pub fn compare_frames(expected: &Frame, actual: &Frame) -> Option<FrameComparison> {
+    FrameCompare::new().compare(expected, actual)
+}
+
+/// Absolute-difference image of two frames of the same size.
+pub fn diff_image(expected: &Frame, actual: &Frame) -> Option<Frame> {
+    FrameCompare::new().diff_image(expected, actual)
+}
+
+/// Panic unless no channel differs by more than `tolerance`, describing where frames diverge.
+#[track_caller]
+pub fn assert_frames_match(expected: &Frame, actual: &Frame, tolerance: u8) {
+    FrameCompare::new()
+        .tolerance(tolerance)
+        .assert(expected, actual);
+}
+
+fn rgba_at(frame: &Frame, x: u32, y: u32) -> [u8; 4] {
+    let p = &frame.row(y)[x as usize * 4..x as usize * 4 + 4];
+    match frame.format() {
+        PixelFormat::Rgba8 => [p[0], p[1], p[2], p[3]],
+        PixelFormat::Bgra8 => [p[2], p[1], p[0], p[3]],
+    }
+}
+
+fn window_ssim(a: &[f64], b: &[f64], stride: u32, x0: u32, y0: u32, w: u32, h: u32) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    let n = (w * h) as f64;
+    let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            let i = (y * stride + x) as usize;
+            let (va, vb) = (a[i], b[i]);
+            sa += va;
+            sb += vb;
+            saa += va * va;
+            sbb += vb * vb;
+            sab += va * vb;
+        }
+    }
+    let (ma, mb) = (sa / n, sb / n);
+    let var_a = saa / n - ma * ma;
+    let var_b = sbb / n - mb * mb;
+    let cov = sab / n - ma * mb;
+    ((2.0 * ma * mb + C1) * (2.0 * cov + C2)) / ((ma * ma + mb * mb + C1) * (var_a + var_b + C2))
+}
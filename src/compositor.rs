@@ -0,0 +1,329 @@
+//! Multi-source compositor: tile or layer several receivers into one output frame.
+//!
+//! A `Compositor` owns N `FrameReceiver`s (Syphon/Spout clients or `LocalClient`s). Each call to
+//! `compose` takes the newest frame from every source (keeping the previous one if nothing new
+//! arrived), scales it into the rectangle chosen by the `Layout`, and blends it over the layers
+//! below with the source's opacity and `BlendMode`. `publish` composes and sends the result to
+//! any `FrameSender`, e.g. a `LocalServer`, an `OpenGLServer` or a Spout sender.
+
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::{PublishError, Rect, Size};
+
+/// How colors of a layer combine with what is already in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// Source over destination.
+    #[default]
+    Normal,
+    /// Sum, clamped to white.
+    Add,
+    /// Product; darkens.
+    Multiply,
+    /// Inverse product of inverses; lightens.
+    Screen,
+}
+
+/// Corner used by `Layout::PictureInPicture`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// Where each source is drawn in the output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+    /// Equal cells, left to right then top to bottom. `columns: None` picks a near-square grid.
+    Grid { columns: Option<u32> },
+    /// Source 0 fills the output; the others are stacked as insets of `scale` (0..1) times the
+    /// output size in `corner`, `margin` pixels from the edges.
+    PictureInPicture {
+        corner: Corner,
+        scale: f32,
+        margin: u32,
+    },
+    /// One rectangle per source; sources without a rectangle are not drawn.
+    Custom(Vec<Rect>),
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::Grid { columns: None }
+    }
+}
+
+impl Layout {
+    /// Destination rectangle of each of `count` sources in an output of `size`.
+    pub fn rects(&self, size: Size, count: usize) -> Vec<Option<Rect>> {
+        match self {
+            Layout::Grid { columns } => {
+                if count == 0 {
+                    return Vec::new();
+                }
+                let columns = columns
+                    .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32)
+                    .clamp(1, count as u32);
+                let rows = (count as u32).div_ceil(columns);
+                (0..count as u32)
+                    .map(|i| {
+                        let (col, row) = (i % columns, i / columns);
+                        let x0 = size.width * col / columns;
+                        let x1 = size.width * (col + 1) / columns;
+                        let y0 = size.height * row / rows;
+                        let y1 = size.height * (row + 1) / rows;
+                        Some(Rect::new(x0, y0, x1 - x0, y1 - y0))
+                    })
+                    .collect()
+            }
+            Layout::PictureInPicture {
+                corner,
+                scale,
+                margin,
+            } => {
+                let scale = scale.clamp(0.0, 1.0);
+                let w = (size.width as f32 * scale).round() as u32;
+                let h = (size.height as f32 * scale).round() as u32;
+                (0..count)
+                    .map(|i| {
+                        if i == 0 {
+                            return Some(Rect::from_size(size));
+                        }
+                        // Stack insets away from the corner, one inset height (plus margin) apart.
+                        let step = (i as u32 - 1) * (h + margin);
+                        let x = match corner {
+                            Corner::TopLeft | Corner::BottomLeft => *margin,
+                            Corner::TopRight | Corner::BottomRight => {
+                                size.width.saturating_sub(w + margin)
+                            }
+                        };
+                        let y = match corner {
+                            Corner::TopLeft | Corner::TopRight => margin + step,
+                            Corner::BottomLeft | Corner::BottomRight => {
+                                size.height.saturating_sub(h + margin + step)
+                            }
+                        };
+                        Some(Rect::new(x, y, w, h))
+                    })
+                    .collect()
+            }
+            Layout::Custom(rects) => (0..count).map(|i| rects.get(i).copied()).collect(),
+        }
+    }
+}
+
+/// Per-source drawing options.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerStyle {
+    /// 0 (invisible) to 1 (opaque); multiplied with the frame's own alpha.
+    pub opacity: f32,
+    pub blend: BlendMode,
+    /// Hide the layer without removing the source.
+    pub visible: bool,
+}
+
+impl Default for LayerStyle {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            blend: BlendMode::Normal,
+            visible: true,
+        }
+    }
+}
+
+struct Source {
+    receiver: Box<dyn FrameReceiver>,
+    style: LayerStyle,
+    last_frame: Option<Frame>,
+}
+
+/// Combines N receivers into one output frame. Sources are drawn in the order they were added,
+/// so later sources are on top.
+pub struct Compositor {
+    size: Size,
+    format: PixelFormat,
+    layout: Layout,
+    background: [u8; 4],
+    sources: Vec<Source>,
+}
+
+impl Compositor {
+    /// Output of `size` in RGBA, opaque black background, grid layout.
+    pub fn new(size: Size) -> Self {
+        Self {
+            size,
+            format: PixelFormat::Rgba8,
+            layout: Layout::default(),
+            background: [0, 0, 0, 255],
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    pub fn set_format(&mut self, format: PixelFormat) {
+        self.format = format;
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    /// Background color as RGBA, regardless of the output format.
+    pub fn set_background(&mut self, rgba: [u8; 4]) {
+        self.background = rgba;
+    }
+
+    /// Add a source on top of the existing ones. Returns its index.
+    pub fn add_source(&mut self, receiver: impl FrameReceiver + 'static) -> usize {
+        self.sources.push(Source {
+            receiver: Box::new(receiver),
+            style: LayerStyle::default(),
+            last_frame: None,
+        });
+        self.sources.len() - 1
+    }
+
+    /// Remove the source at `index` and return its receiver.
+    pub fn remove_source(&mut self, index: usize) -> Option<Box<dyn FrameReceiver>> {
+        (index < self.sources.len()).then(|| self.sources.remove(index).receiver)
+    }
+
+    pub fn source_count(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn style(&self, index: usize) -> Option<LayerStyle> {
+        self.sources.get(index).map(|s| s.style)
+    }
+
+    pub fn set_style(&mut self, index: usize, style: LayerStyle) {
+        if let Some(source) = self.sources.get_mut(index) {
+            source.style = style;
+        }
+    }
+
+    pub fn set_opacity(&mut self, index: usize, opacity: f32) {
+        if let Some(source) = self.sources.get_mut(index) {
+            source.style.opacity = opacity;
+        }
+    }
+
+    pub fn set_blend_mode(&mut self, index: usize, blend: BlendMode) {
+        if let Some(source) = self.sources.get_mut(index) {
+            source.style.blend = blend;
+        }
+    }
+
+    /// Pull new frames from every source and draw the output.
+    pub fn compose(&mut self) -> Frame {
+        for source in &mut self.sources {
+            if let Some(frame) = source.receiver.receive_frame() {
+                source.last_frame = Some(frame);
+            }
+        }
+        let layers: Vec<_> = self
+            .sources
+            .iter()
+            .map(|s| (s.last_frame.as_ref(), s.style))
+            .collect();
+        let mut out = compose_layers(self.size, &self.layout, self.background, &layers);
+        if self.format != PixelFormat::Rgba8 {
+            out = out.to_format(self.format);
+        }
+        out
+    }
+
+    /// Compose and send the result to `sender`.
+    pub fn publish(&mut self, sender: &mut dyn FrameSender) -> Result<(), PublishError> {
+        let frame = self.compose();
+        sender.send_frame(&frame)
+    }
+}
+
+/// Draw `layers` (bottom first) into a new RGBA frame of `size`. Layers with no frame are skipped.
+pub fn compose_layers(
+    size: Size,
+    layout: &Layout,
+    background: [u8; 4],
+    layers: &[(Option<&Frame>, LayerStyle)],
+) -> Frame {
+    let mut out = Frame::new(size.width, size.height, PixelFormat::Rgba8);
+    for y in 0..size.height {
+        for px in out.row_mut(y).chunks_exact_mut(4) {
+            px.copy_from_slice(&background);
+        }
+    }
+    let rects = layout.rects(size, layers.len());
+    for ((frame, style), rect) in layers.iter().zip(rects) {
+        let (Some(frame), Some(rect)) = (frame, rect) else {
+            continue;
+        };
+        if !style.visible || style.opacity <= 0.0 {
+            continue;
+        }
+        draw_layer(&mut out, frame, rect, style);
+    }
+    out
+}
+
+/// Scale `frame` (nearest neighbour) into `rect` of `out`, clipped to the output, and blend it.
+fn draw_layer(out: &mut Frame, frame: &Frame, rect: Rect, style: &LayerStyle) {
+    if rect.is_empty() || frame.width() == 0 || frame.height() == 0 {
+        return;
+    }
+    let opacity = (style.opacity.clamp(0.0, 1.0) * 255.0).round() as u32;
+    let swap = frame.format() == PixelFormat::Bgra8;
+    let x_end = (rect.right().min(out.width() as u64)) as u32;
+    let y_end = (rect.bottom().min(out.height() as u64)) as u32;
+    for oy in rect.y..y_end {
+        let sy = ((oy - rect.y) as u64 * frame.height() as u64 / rect.height as u64) as u32;
+        let src_row = frame.row(sy);
+        let dst_row = out.row_mut(oy);
+        for ox in rect.x..x_end {
+            let sx = ((ox - rect.x) as u64 * frame.width() as u64 / rect.width as u64) as usize;
+            let s = &src_row[sx * 4..sx * 4 + 4];
+            let src = if swap {
+                [s[2], s[1], s[0], s[3]]
+            } else {
+                [s[0], s[1], s[2], s[3]]
+            };
+            let d = &mut dst_row[ox as usize * 4..ox as usize * 4 + 4];
+            blend_pixel(d, src, opacity, style.blend);
+        }
+    }
+}
+
+/// Blend one RGBA pixel. `opacity` is 0..=255.
+fn blend_pixel(dst: &mut [u8], src: [u8; 4], opacity: u32, mode: BlendMode) {
+    let alpha = src[3] as u32 * opacity / 255;
+    if alpha == 0 {
+        return;
+    }
+    for c in 0..3 {
+        let d = dst[c] as u32;
+        let s = src[c] as u32;
+        let target = match mode {
+            BlendMode::Normal => s,
+            BlendMode::Add => (d + s).min(255),
+            BlendMode::Multiply => d * s / 255,
+            BlendMode::Screen => 255 - (255 - d) * (255 - s) / 255,
+        };
+        dst[c] = ((d * (255 - alpha) + target * alpha + 127) / 255) as u8;
+    }
+    let da = dst[3] as u32;
+    dst[3] = (alpha + da * (255 - alpha) / 255) as u8;
+}
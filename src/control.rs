@@ -0,0 +1,384 @@
+//! Back-channel from clients to a named server.
+//!
+//! Syphon and Spout only move frames from server to client. `ControlServer` lets a server
+//! (usually created with the same name as its Syphon server or Spout sender) receive small
+//! `ControlMessage`s from clients, e.g. "please switch to 1920x1080" or "I'm dropping frames".
+//!
+//! The transport is plain Rust: the server listens on a loopback TCP port and registers it under
+//! its name in the temp directory; `ControlClient::connect` looks the name up and connects. Each
+//! message is a little-endian `u32` length followed by a tagged payload.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::geometry::Size;
+use crate::util::{file_safe_name, shared_temp_dir};
+
+/// Control protocol version sent in the client handshake.
+pub const CONTROL_PROTOCOL_VERSION: u16 = 1;
+
+/// Largest encoded control message accepted by either side.
+pub const MAX_CONTROL_MESSAGE: usize = 64 * 1024;
+
+/// How long a new connection may take to send its hello.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TAG_HELLO: u8 = 0;
+const TAG_REQUEST_SIZE: u8 = 1;
+const TAG_REQUEST_FRAME_RATE: u8 = 2;
+const TAG_FRAMES_DROPPED: u8 = 3;
+const TAG_TEXT: u8 = 4;
+const TAG_CUSTOM: u8 = 5;
+
+/// A message from a client to a server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlMessage {
+    /// Ask the server to publish at this size.
+    RequestSize(Size),
+    /// Ask the server to publish at this many frames per second.
+    RequestFrameRate(f64),
+    /// The client dropped this many frames since its last report.
+    FramesDropped(u64),
+    /// Free-form text.
+    Text(String),
+    /// Application-defined bytes.
+    Custom(Vec<u8>),
+}
+
+impl ControlMessage {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            ControlMessage::RequestSize(size) => {
+                out.push(TAG_REQUEST_SIZE);
+                out.extend_from_slice(&size.width.to_le_bytes());
+                out.extend_from_slice(&size.height.to_le_bytes());
+            }
+            ControlMessage::RequestFrameRate(fps) => {
+                out.push(TAG_REQUEST_FRAME_RATE);
+                out.extend_from_slice(&fps.to_le_bytes());
+            }
+            ControlMessage::FramesDropped(n) => {
+                out.push(TAG_FRAMES_DROPPED);
+                out.extend_from_slice(&n.to_le_bytes());
+            }
+            ControlMessage::Text(text) => {
+                out.push(TAG_TEXT);
+                out.extend_from_slice(text.as_bytes());
+            }
+            ControlMessage::Custom(bytes) => {
+                out.push(TAG_CUSTOM);
+                out.extend_from_slice(bytes);
+            }
+        }
+        out
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (&tag, body) = bytes.split_first()?;
+        match tag {
+            TAG_REQUEST_SIZE if body.len() == 8 => Some(ControlMessage::RequestSize(Size::new(
+                u32::from_le_bytes(body[0..4].try_into().ok()?),
+                u32::from_le_bytes(body[4..8].try_into().ok()?),
+            ))),
+            TAG_REQUEST_FRAME_RATE if body.len() == 8 => Some(ControlMessage::RequestFrameRate(
+                f64::from_le_bytes(body.try_into().ok()?),
+            )),
+            TAG_FRAMES_DROPPED if body.len() == 8 => Some(ControlMessage::FramesDropped(
+                u64::from_le_bytes(body.try_into().ok()?),
+            )),
+            TAG_TEXT => String::from_utf8(body.to_vec()).ok().map(ControlMessage::Text),
+            TAG_CUSTOM => Some(ControlMessage::Custom(body.to_vec())),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies one client connection for the lifetime of a `ControlServer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(pub u64);
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "client-{}", self.0)
+    }
+}
+
+/// Something that happened on a `ControlServer`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlEvent {
+    /// A client completed the handshake. `name` is what it passed to `ControlClient::connect`.
+    Connected { client: ClientId, name: String },
+    /// A client sent a message.
+    Message {
+        client: ClientId,
+        message: ControlMessage,
+    },
+    /// A client that was `Connected` disconnected or sent something malformed and was dropped.
+    /// Clients that fail the handshake are dropped without any event.
+    Disconnected { client: ClientId },
+}
+
+/// Receives `ControlMessage`s from clients of a named server. Unregisters and disconnects all
+/// clients on drop.
+pub struct ControlServer {
+    name: String,
+    addr: SocketAddr,
+    registration: PathBuf,
+    events: Receiver<ControlEvent>,
+    shutdown: Arc<AtomicBool>,
+    connections: Arc<Mutex<HashMap<ClientId, TcpStream>>>,
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl ControlServer {
+    /// Listen for clients of the server called `name`. If another control server is registered
+    /// under the same name, this one replaces it.
+    pub fn bind(name: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let addr = listener.local_addr()?;
+        let registration = registration_path(name);
+        if let Some(parent) = registration.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&registration, addr.port().to_string())?;
+
+        let (tx, events) = mpsc::channel();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(HashMap::new()));
+        let accept_thread = {
+            let shutdown = shutdown.clone();
+            let connections = connections.clone();
+            std::thread::Builder::new()
+                .name(format!("control-accept-{}", name))
+                .spawn(move || accept_loop(listener, tx, shutdown, connections))?
+        };
+        Ok(Self {
+            name: name.to_string(),
+            addr,
+            registration,
+            events,
+            shutdown,
+            connections,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Loopback address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Currently connected clients.
+    pub fn clients(&self) -> Vec<ClientId> {
+        let mut ids: Vec<_> = self.connections.lock().unwrap().keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Drop a client's connection. A `Disconnected` event follows.
+    pub fn disconnect(&self, client: ClientId) {
+        if let Some(stream) = self.connections.lock().unwrap().get(&client) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Next pending event, without blocking.
+    pub fn try_recv(&self) -> Option<ControlEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Wait up to `timeout` for the next event.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<ControlEvent> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// All events pending right now.
+    pub fn drain(&self) -> Vec<ControlEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Only remove the registration if a newer server has not replaced it.
+        if std::fs::read_to_string(&self.registration).ok().as_deref()
+            == Some(self.addr.port().to_string().as_str())
+        {
+            let _ = std::fs::remove_file(&self.registration);
+        }
+        for stream in self.connections.lock().unwrap().values() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        // Wake the blocking accept so the thread sees the shutdown flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.accept_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Sends `ControlMessage`s to a named `ControlServer`.
+pub struct ControlClient {
+    server_name: String,
+    stream: TcpStream,
+}
+
+impl ControlClient {
+    /// Connect to the control server registered as `server_name`, identifying as `client_name`.
+    /// Fails with `NotFound` if no server is registered under that name.
+    pub fn connect(server_name: &str, client_name: &str) -> io::Result<Self> {
+        let port: u16 = std::fs::read_to_string(registration_path(server_name))?
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad control registration"))?;
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+        stream.set_nodelay(true)?;
+        let mut hello = vec![TAG_HELLO];
+        hello.extend_from_slice(&CONTROL_PROTOCOL_VERSION.to_le_bytes());
+        hello.extend_from_slice(client_name.as_bytes());
+        let mut client = Self {
+            server_name: server_name.to_string(),
+            stream,
+        };
+        client.write_frame(&hello)?;
+        Ok(client)
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// Send a message. Fails if the server has gone away.
+    pub fn send(&mut self, message: &ControlMessage) -> io::Result<()> {
+        self.write_frame(&message.encode())
+    }
+
+    fn write_frame(&mut self, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > MAX_CONTROL_MESSAGE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "control message too large",
+            ));
+        }
+        let mut frame = Vec::with_capacity(4 + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame)
+    }
+}
+
+fn registration_path(name: &str) -> PathBuf {
+    shared_temp_dir()
+        .join("control")
+        .join(format!("{}.port", file_safe_name(name)))
+}
+
+fn accept_loop(
+    listener: TcpListener,
+    events: Sender<ControlEvent>,
+    shutdown: Arc<AtomicBool>,
+    connections: Arc<Mutex<HashMap<ClientId, TcpStream>>>,
+) {
+    let next_id = AtomicU64::new(1);
+    for stream in listener.incoming() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let client = ClientId(next_id.fetch_add(1, Ordering::Relaxed));
+        let events = events.clone();
+        let connections = connections.clone();
+        let shutdown = shutdown.clone();
+        // If the thread cannot be spawned the closure, and with it the stream, is dropped: the
+        // peer sees the connection close and nothing was registered for it yet.
+        let _ = std::thread::Builder::new()
+            .name(format!("control-{}", client))
+            .spawn(move || {
+                let announced = connection_loop(client, stream, &events, &connections, &shutdown);
+                connections.lock().unwrap().remove(&client);
+                if announced {
+                    let _ = events.send(ControlEvent::Disconnected { client });
+                }
+            });
+    }
+}
+
+/// Serve one client until it goes away. Returns whether it got as far as `Connected`; only then
+/// is it listed in `connections`.
+fn connection_loop(
+    client: ClientId,
+    mut stream: TcpStream,
+    events: &Sender<ControlEvent>,
+    connections: &Mutex<HashMap<ClientId, TcpStream>>,
+    shutdown: &AtomicBool,
+) -> bool {
+    // A peer that never says hello must not hold its thread forever.
+    if stream.set_read_timeout(Some(HELLO_TIMEOUT)).is_err() {
+        return false;
+    }
+    let Some(hello) = read_frame(&mut stream) else {
+        return false;
+    };
+    if hello.len() < 3 || hello[0] != TAG_HELLO {
+        return false;
+    }
+    if u16::from_le_bytes([hello[1], hello[2]]) != CONTROL_PROTOCOL_VERSION {
+        return false;
+    }
+    if stream.set_read_timeout(None).is_err() {
+        return false;
+    }
+    let Ok(registered) = stream.try_clone() else {
+        return false;
+    };
+    connections.lock().unwrap().insert(client, registered);
+    // The server sets the flag before shutting down what is registered, so a client registered
+    // after that is caught here.
+    if shutdown.load(Ordering::SeqCst) {
+        return false;
+    }
+    let name = String::from_utf8_lossy(&hello[3..]).into_owned();
+    if events.send(ControlEvent::Connected { client, name }).is_err() {
+        return false;
+    }
+    while let Some(frame) = read_frame(&mut stream) {
+        let Some(message) = ControlMessage::decode(&frame) else {
+            break;
+        };
+        if events.send(ControlEvent::Message { client, message }).is_err() {
+            break;
+        }
+    }
+    true
+}
+
+fn read_frame(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).ok()?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_CONTROL_MESSAGE {
+        return None;
+    }
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).ok()?;
+    Some(payload)
+}
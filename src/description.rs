@@ -0,0 +1,115 @@
+//! Plain-data source descriptions that outlive the source and the process.
+//!
+//! `ServerDescription` is a retained Objective-C object and `SpoutSenderInfo` carries a DirectX
+//! share handle; neither can be stored. `SourceDescription` holds what identifies and describes a
+//! source (with the `serde` feature it is `Serialize` / `Deserialize`), and `find` / `connect`
+//! look the live source back up by backend and UUID while it is still running.
+
+use std::time::SystemTime;
+
+use crate::discovery::{connect_source, discover_sources, find_source, SourceBackend, SourceInfo};
+use crate::frame::{FrameReceiver, PixelFormat};
+use crate::geometry::Size;
+use crate::safe::ServerDescription;
+#[cfg(target_os = "windows")]
+use crate::safe::SpoutSenderInfo;
+
+/// A source as seen at `discovered_at`. Size and format are `None` when the backend did not
+/// report them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceDescription {
+    pub backend: SourceBackend,
+    /// Syphon server UUID, `LocalServer` UUID, or the Spout sender name.
+    pub uuid: String,
+    pub name: String,
+    pub app_name: String,
+    pub size: Option<Size>,
+    pub format: Option<PixelFormat>,
+    pub discovered_at: SystemTime,
+}
+
+impl SourceDescription {
+    /// Describe a discovered source, as seen at `discovered_at`.
+    pub fn from_source(info: &SourceInfo, discovered_at: SystemTime) -> Self {
+        Self {
+            backend: info.backend,
+            uuid: info.uuid.clone(),
+            name: info.name.clone(),
+            app_name: info.app_name.clone(),
+            size: info.size,
+            format: info.format,
+            discovered_at,
+        }
+    }
+
+    /// Describe a Syphon server from the directory. Returns `None` if it has no UUID.
+    pub fn from_server_description(desc: &ServerDescription) -> Option<Self> {
+        Some(Self {
+            backend: SourceBackend::Syphon,
+            uuid: desc.uuid()?,
+            name: desc.name().unwrap_or_default(),
+            app_name: desc.app_name().unwrap_or_default(),
+            size: None,
+            format: None,
+            discovered_at: SystemTime::now(),
+        })
+    }
+
+    /// Describe the Spout sender `name` from its sender info; the share handle is dropped.
+    #[cfg(target_os = "windows")]
+    pub fn from_spout_sender(name: &str, info: &SpoutSenderInfo) -> Self {
+        Self {
+            backend: SourceBackend::Spout,
+            uuid: name.to_string(),
+            name: name.to_string(),
+            app_name: String::new(),
+            size: Some(Size::new(info.width, info.height)),
+            format: PixelFormat::from_dxgi_format(info.format),
+            discovered_at: SystemTime::now(),
+        }
+    }
+
+    /// `backend:uuid`, as `SourceInfo::id`.
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.backend.name(), self.uuid)
+    }
+
+    /// The description as a `SourceInfo`, without checking the source still exists.
+    pub fn to_source_info(&self) -> SourceInfo {
+        SourceInfo {
+            backend: self.backend,
+            uuid: self.uuid.clone(),
+            name: self.name.clone(),
+            app_name: self.app_name.clone(),
+            size: self.size,
+            format: self.format,
+        }
+    }
+
+    /// The live source with the same backend and UUID, with its current size and format.
+    pub fn find(&self) -> Option<SourceInfo> {
+        find_source(&self.id())
+    }
+
+    /// Open the live source for receiving. Returns `None` if it is gone.
+    pub fn connect(&self) -> Option<Box<dyn FrameReceiver>> {
+        connect_source(&self.find()?)
+    }
+}
+
+/// Stamped with the current time.
+impl From<&SourceInfo> for SourceDescription {
+    fn from(info: &SourceInfo) -> Self {
+        Self::from_source(info, SystemTime::now())
+    }
+}
+
+/// Descriptions of every source currently visible, all stamped with the same time.
+pub fn snapshot_sources() -> Vec<SourceDescription> {
+    let now = SystemTime::now();
+    discover_sources()
+        .iter()
+        .map(|info| SourceDescription::from_source(info, now))
+        .collect()
+}
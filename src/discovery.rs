@@ -0,0 +1,299 @@
+//! One list of every frame source this process can see, whatever the backend.
+//!
+//! `discover_sources` merges the pure-Rust `local_servers`, the Syphon `ServerDirectory` (macOS)
+//! and the Spout sender list (Windows) into `SourceInfo`s, and `connect_source` opens any of them
+//! as a boxed `FrameReceiver`; `create_sender` publishes on any backend as a `FrameSender`.
+//! Syphon sources and servers get their own headless CGL context, so they can be used from any
+//! thread; Spout needs an OpenGL context current on the calling thread, as `Spout` itself does.
+
+use crate::frame::{FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::Size;
+use crate::local::{local_servers, LocalClient, LocalServer};
+use crate::safe::ServerDirectory;
+#[cfg(target_os = "macos")]
+use crate::{
+    frame::Frame,
+    geometry::PublishError,
+    safe::{
+        cgl_create_headless_context, cgl_destroy_context, cgl_make_current, CGLContextObj,
+        OpenGLClient, OpenGLServer,
+    },
+};
+
+/// Where a source lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum SourceBackend {
+    /// A `LocalServer` in this process.
+    Local,
+    /// A Syphon server (macOS).
+    Syphon,
+    /// A Spout sender (Windows).
+    Spout,
+}
+
+impl SourceBackend {
+    /// Lowercase name, as used in `SourceInfo::id`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceBackend::Local => "local",
+            SourceBackend::Syphon => "syphon",
+            SourceBackend::Spout => "spout",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "local" => Some(SourceBackend::Local),
+            "syphon" => Some(SourceBackend::Syphon),
+            "spout" => Some(SourceBackend::Spout),
+            _ => None,
+        }
+    }
+}
+
+/// A discovered source. Size and format are filled in when the backend reports them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceInfo {
+    pub backend: SourceBackend,
+    /// Unique while the source runs. Spout senders have no UUID and use their name.
+    pub uuid: String,
+    pub name: String,
+    pub app_name: String,
+    pub size: Option<Size>,
+    pub format: Option<PixelFormat>,
+}
+
+impl SourceInfo {
+    /// `backend:uuid`, unique across backends.
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.backend.name(), self.uuid)
+    }
+
+    /// `name`, followed by the app name in brackets when there is one.
+    pub fn display_name(&self) -> String {
+        match (self.name.is_empty(), self.app_name.is_empty()) {
+            (_, true) => self.name.clone(),
+            (true, false) => self.app_name.clone(),
+            (false, false) => format!("{} ({})", self.name, self.app_name),
+        }
+    }
+}
+
+/// All sources currently visible: local servers, then Syphon servers, then Spout senders.
+pub fn discover_sources() -> Vec<SourceInfo> {
+    let mut sources = local_sources();
+    if let Some(directory) = ServerDirectory::shared() {
+        sources.extend(syphon_sources(&directory));
+    }
+    #[cfg(target_os = "windows")]
+    {
+        if let Some(spout) = crate::safe::Spout::new() {
+            sources.extend(spout_sources(&spout));
+        }
+    }
+    sources
+}
+
+/// The `LocalServer`s in this process.
+pub fn local_sources() -> Vec<SourceInfo> {
+    local_servers()
+        .into_iter()
+        .map(|info| SourceInfo {
+            backend: SourceBackend::Local,
+            uuid: info.uuid,
+            name: info.name,
+            app_name: info.app_name,
+            size: info.size,
+            format: info.format,
+        })
+        .collect()
+}
+
+/// The servers currently in a Syphon `directory`; those without a UUID are skipped.
+pub fn syphon_sources(directory: &ServerDirectory) -> Vec<SourceInfo> {
+    directory
+        .servers()
+        .iter()
+        .filter_map(|desc| {
+            Some(SourceInfo {
+                backend: SourceBackend::Syphon,
+                uuid: desc.uuid()?,
+                name: desc.name().unwrap_or_default(),
+                app_name: desc.app_name().unwrap_or_default(),
+                size: None,
+                format: None,
+            })
+        })
+        .collect()
+}
+
+/// The senders in `spout`'s sender list.
+#[cfg(target_os = "windows")]
+pub fn spout_sources(spout: &crate::safe::Spout) -> Vec<SourceInfo> {
+    (0..spout.sender_count())
+        .filter_map(|index| spout.sender_name_at(index))
+        .map(|name| {
+            let info = spout.sender_info(&name);
+            SourceInfo {
+                backend: SourceBackend::Spout,
+                uuid: name.clone(),
+                name,
+                app_name: String::new(),
+                size: info.as_ref().map(|info| Size::new(info.width, info.height)),
+                format: info.and_then(|info| PixelFormat::from_dxgi_format(info.format)),
+            }
+        })
+        .collect()
+}
+
+/// The source with this `SourceInfo::id`, if it is still visible.
+pub fn find_source(id: &str) -> Option<SourceInfo> {
+    discover_sources()
+        .into_iter()
+        .find(|source| source.id() == id)
+}
+
+/// Open `source` for receiving. Returns `None` if it has gone or its backend is unavailable.
+pub fn connect_source(source: &SourceInfo) -> Option<Box<dyn FrameReceiver>> {
+    match source.backend {
+        SourceBackend::Local => {
+            LocalClient::connect_uuid(&source.uuid).map(|c| Box::new(c) as Box<dyn FrameReceiver>)
+        }
+        #[cfg(target_os = "macos")]
+        SourceBackend::Syphon => {
+            SyphonSource::connect(&source.uuid).map(|c| Box::new(c) as Box<dyn FrameReceiver>)
+        }
+        #[cfg(not(target_os = "macos"))]
+        SourceBackend::Syphon => None,
+        #[cfg(target_os = "windows")]
+        SourceBackend::Spout => {
+            let spout = crate::safe::Spout::new()?;
+            spout.receiver_set_name(Some(&source.name));
+            Some(Box::new(spout))
+        }
+        #[cfg(not(target_os = "windows"))]
+        SourceBackend::Spout => None,
+    }
+}
+
+/// Publish frames as `name` on `backend`. Returns `None` if the backend is unavailable here.
+pub fn create_sender(backend: SourceBackend, name: &str) -> Option<Box<dyn FrameSender>> {
+    match backend {
+        SourceBackend::Local => Some(Box::new(LocalServer::new(name))),
+        #[cfg(target_os = "macos")]
+        SourceBackend::Syphon => {
+            SyphonSender::new(name).map(|s| Box::new(s) as Box<dyn FrameSender>)
+        }
+        #[cfg(not(target_os = "macos"))]
+        SourceBackend::Syphon => None,
+        #[cfg(target_os = "windows")]
+        SourceBackend::Spout => {
+            let spout = crate::safe::Spout::new()?;
+            spout.sender_set_name(Some(name));
+            Some(Box::new(spout))
+        }
+        #[cfg(not(target_os = "windows"))]
+        SourceBackend::Spout => None,
+    }
+}
+
+/// A Syphon OpenGL client with its own headless context, made current around each read.
+#[cfg(target_os = "macos")]
+struct SyphonSource {
+    client: Option<OpenGLClient>,
+    context: CGLContextObj,
+}
+
+#[cfg(target_os = "macos")]
+impl SyphonSource {
+    fn connect(uuid: &str) -> Option<Self> {
+        let directory = ServerDirectory::shared()?;
+        let description = directory
+            .servers()
+            .into_iter()
+            .find(|desc| desc.uuid().as_deref() == Some(uuid))?;
+        let context = cgl_create_headless_context()?;
+        cgl_make_current(context);
+        match OpenGLClient::new(&description, context, None, None) {
+            Some(client) => Some(Self {
+                client: Some(client),
+                context,
+            }),
+            None => {
+                cgl_destroy_context(context);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl FrameReceiver for SyphonSource {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        cgl_make_current(self.context);
+        self.client.as_mut()?.receive_frame()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.client.as_ref().is_some_and(|c| c.is_connected())
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for SyphonSource {
+    fn drop(&mut self) {
+        cgl_make_current(self.context);
+        self.client = None;
+        cgl_destroy_context(self.context);
+    }
+}
+
+/// A Syphon OpenGL server with its own headless context, made current around each publish.
+#[cfg(target_os = "macos")]
+struct SyphonSender {
+    server: Option<OpenGLServer>,
+    context: CGLContextObj,
+}
+
+#[cfg(target_os = "macos")]
+impl SyphonSender {
+    fn new(name: &str) -> Option<Self> {
+        let context = cgl_create_headless_context()?;
+        cgl_make_current(context);
+        match OpenGLServer::new(Some(name), context, None) {
+            Some(server) => Some(Self {
+                server: Some(server),
+                context,
+            }),
+            None => {
+                cgl_destroy_context(context);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl FrameSender for SyphonSender {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        cgl_make_current(self.context);
+        match self.server.as_mut() {
+            Some(server) => server.send_frame(frame),
+            None => Err(PublishError::Backend),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for SyphonSender {
+    fn drop(&mut self) {
+        cgl_make_current(self.context);
+        self.server = None;
+        cgl_destroy_context(self.context);
+    }
+}
@@ -0,0 +1,285 @@
+//! Headless OpenGL on Linux through EGL, the counterpart of the macOS `cgl_*` helpers.
+//!
+//! libEGL is loaded at runtime, so the crate builds and runs without it; `egl_create_headless_context`
+//! just returns `None` then. Contexts are desktop OpenGL on Mesa's surfaceless platform when
+//! available (works with the llvmpipe software renderer, no GPU or display server needed),
+//! falling back to a 1x1 pbuffer on the default display. The `gl_*` texture helpers use
+//! whichever context is current.
+
+use std::ffi::c_void;
+use std::sync::OnceLock;
+
+use khronos_egl as egl;
+
+/// `EGL_PLATFORM_SURFACELESS_MESA` from `EGL_MESA_platform_surfaceless`.
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+type Egl = egl::DynamicInstance<egl::EGL1_4>;
+
+fn instance() -> Option<&'static Egl> {
+    static EGL: OnceLock<Option<Egl>> = OnceLock::new();
+    EGL.get_or_init(|| unsafe { Egl::load_required() }.ok())
+        .as_ref()
+}
+
+/// True if libEGL could be loaded.
+pub fn egl_available() -> bool {
+    instance().is_some()
+}
+
+/// A headless EGL OpenGL context. Destroy with `egl_destroy_context`.
+#[derive(Debug)]
+pub struct EglContext {
+    display: egl::Display,
+    context: egl::Context,
+    surface: Option<egl::Surface>,
+}
+
+impl EglContext {
+    /// True if the context runs on the surfaceless platform (no pbuffer).
+    pub fn is_surfaceless(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Raw `EGLContext` handle.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.context.as_ptr()
+    }
+}
+
+/// Create a headless desktop OpenGL context for offscreen rendering (e.g. tests and CI).
+/// Caller must destroy with `egl_destroy_context`. Returns `None` if EGL or OpenGL is unavailable.
+pub fn egl_create_headless_context() -> Option<EglContext> {
+    let egl = instance()?;
+    surfaceless_context(egl).or_else(|| pbuffer_context(egl))
+}
+
+/// Destroy a context created with `egl_create_headless_context`, releasing it if current.
+///
+/// The display stays initialized: it is shared by every context in the process, and
+/// `eglTerminate` would pull it out from under the others.
+pub fn egl_destroy_context(ctx: EglContext) {
+    let Some(egl) = instance() else { return };
+    if egl.get_current_context() == Some(ctx.context) {
+        let _ = egl.make_current(ctx.display, None, None, None);
+    }
+    if let Some(surface) = ctx.surface {
+        let _ = egl.destroy_surface(ctx.display, surface);
+    }
+    let _ = egl.destroy_context(ctx.display, ctx.context);
+}
+
+/// Make the given context current on this thread. Returns false on failure.
+pub fn egl_make_current(ctx: &EglContext) -> bool {
+    instance().is_some_and(|egl| {
+        egl.make_current(ctx.display, ctx.surface, ctx.surface, Some(ctx.context))
+            .is_ok()
+    })
+}
+
+fn surfaceless_context(egl: &Egl) -> Option<EglContext> {
+    let egl15 = egl.upcast::<egl::EGL1_5>()?;
+    let display = unsafe {
+        egl15.get_platform_display(
+            PLATFORM_SURFACELESS_MESA,
+            egl::DEFAULT_DISPLAY,
+            &[egl::ATTRIB_NONE],
+        )
+    }
+    .ok()?;
+    let context = create_context(egl, display, 0);
+    let has_surfaceless = context.is_some()
+        && egl
+            .query_string(Some(display), egl::EXTENSIONS)
+            .is_ok_and(|ext| {
+                ext.to_string_lossy()
+                    .split(' ')
+                    .any(|e| e == "EGL_KHR_surfaceless_context")
+            });
+    match context {
+        Some((_, context)) if has_surfaceless => Some(EglContext {
+            display,
+            context,
+            surface: None,
+        }),
+        Some((_, context)) => {
+            let _ = egl.destroy_context(display, context);
+            None
+        }
+        None => None,
+    }
+}
+
+fn pbuffer_context(egl: &Egl) -> Option<EglContext> {
+    let display = unsafe { egl.get_display(egl::DEFAULT_DISPLAY) }?;
+    let (config, context) = create_context(egl, display, egl::PBUFFER_BIT)?;
+    let attribs = [egl::WIDTH, 1, egl::HEIGHT, 1, egl::NONE];
+    match egl.create_pbuffer_surface(display, config, &attribs) {
+        Ok(surface) => Some(EglContext {
+            display,
+            context,
+            surface: Some(surface),
+        }),
+        Err(_) => {
+            let _ = egl.destroy_context(display, context);
+            None
+        }
+    }
+}
+
+/// Initialize `display` and create a desktop GL context whose config supports `surface_type`.
+/// Initializing an already initialized display is a no-op; displays are never terminated.
+fn create_context(
+    egl: &Egl,
+    display: egl::Display,
+    surface_type: egl::Int,
+) -> Option<(egl::Config, egl::Context)> {
+    egl.initialize(display).ok()?;
+    egl.bind_api(egl::OPENGL_API).ok()?;
+    let attribs = [
+        egl::SURFACE_TYPE,
+        surface_type,
+        egl::RENDERABLE_TYPE,
+        egl::OPENGL_BIT,
+        egl::RED_SIZE,
+        8,
+        egl::GREEN_SIZE,
+        8,
+        egl::BLUE_SIZE,
+        8,
+        egl::ALPHA_SIZE,
+        8,
+        egl::NONE,
+    ];
+    let config = egl.choose_first_config(display, &attribs).ok()??;
+    let context = egl
+        .create_context(display, config, None, &[egl::NONE])
+        .ok()?;
+    Some((config, context))
+}
+
+const GL_TEXTURE_RECTANGLE: u32 = 0x84F5;
+const GL_TEXTURE_MIN_FILTER: u32 = 0x2801;
+const GL_TEXTURE_MAG_FILTER: u32 = 0x2800;
+const GL_NEAREST: i32 = 0x2600;
+const GL_RGBA: u32 = 0x1908;
+const GL_RGBA8: i32 = 0x8058;
+const GL_UNSIGNED_BYTE: u32 = 0x1401;
+const GL_UNPACK_ALIGNMENT: u32 = 0x0CF5;
+const GL_PACK_ALIGNMENT: u32 = 0x0D05;
+const GL_TEXTURE_WIDTH: u32 = 0x1000;
+const GL_TEXTURE_HEIGHT: u32 = 0x1001;
+
+/// Entry points used by the texture helpers, resolved through `eglGetProcAddress`.
+struct Gl {
+    gen_textures: unsafe extern "system" fn(i32, *mut u32),
+    delete_textures: unsafe extern "system" fn(i32, *const u32),
+    bind_texture: unsafe extern "system" fn(u32, u32),
+    tex_parameteri: unsafe extern "system" fn(u32, u32, i32),
+    tex_image_2d: unsafe extern "system" fn(u32, i32, i32, i32, i32, i32, u32, u32, *const c_void),
+    get_tex_image: unsafe extern "system" fn(u32, i32, u32, u32, *mut c_void),
+    pixel_storei: unsafe extern "system" fn(u32, i32),
+    get_tex_level_parameteriv: unsafe extern "system" fn(u32, i32, u32, *mut i32),
+}
+
+fn gl() -> Option<&'static Gl> {
+    static GL: OnceLock<Option<Gl>> = OnceLock::new();
+    GL.get_or_init(|| {
+        let egl = instance()?;
+        let load = |name: &str| egl.get_proc_address(name);
+        // SAFETY: each pointer comes from the GL library for the named function, whose C
+        // signature matches the field it is stored in.
+        unsafe {
+            Some(Gl {
+                gen_textures: cast(load("glGenTextures")?),
+                delete_textures: cast(load("glDeleteTextures")?),
+                bind_texture: cast(load("glBindTexture")?),
+                tex_parameteri: cast(load("glTexParameteri")?),
+                tex_image_2d: cast(load("glTexImage2D")?),
+                get_tex_image: cast(load("glGetTexImage")?),
+                pixel_storei: cast(load("glPixelStorei")?),
+                get_tex_level_parameteriv: cast(load("glGetTexLevelParameteriv")?),
+            })
+        }
+    })
+    .as_ref()
+}
+
+/// Reinterpret a proc address as the typed function pointer `F`.
+unsafe fn cast<F: Copy>(f: extern "system" fn()) -> F {
+    assert_eq!(std::mem::size_of::<F>(), std::mem::size_of_val(&f));
+    std::mem::transmute_copy(&f)
+}
+
+fn has_current_context() -> bool {
+    instance().is_some_and(|egl| egl.get_current_context().is_some())
+}
+
+pub(crate) fn create_texture_rectangle_rgba8(width: usize, height: usize, rgba: &[u8]) -> u32 {
+    if rgba.len() < width * height * 4 || !has_current_context() {
+        return 0;
+    }
+    let Some(gl) = gl() else { return 0 };
+    let mut tex = 0;
+    unsafe {
+        (gl.gen_textures)(1, &mut tex);
+        (gl.bind_texture)(GL_TEXTURE_RECTANGLE, tex);
+        (gl.tex_parameteri)(GL_TEXTURE_RECTANGLE, GL_TEXTURE_MIN_FILTER, GL_NEAREST);
+        (gl.tex_parameteri)(GL_TEXTURE_RECTANGLE, GL_TEXTURE_MAG_FILTER, GL_NEAREST);
+        (gl.pixel_storei)(GL_UNPACK_ALIGNMENT, 1);
+        (gl.tex_image_2d)(
+            GL_TEXTURE_RECTANGLE,
+            0,
+            GL_RGBA8,
+            width as i32,
+            height as i32,
+            0,
+            GL_RGBA,
+            GL_UNSIGNED_BYTE,
+            rgba.as_ptr().cast(),
+        );
+        (gl.bind_texture)(GL_TEXTURE_RECTANGLE, 0);
+    }
+    tex
+}
+
+pub(crate) fn read_texture_rectangle_rgba8(
+    tex_id: u32,
+    width: usize,
+    height: usize,
+    out_rgba: &mut [u8],
+) {
+    if out_rgba.len() < width * height * 4 || !has_current_context() {
+        return;
+    }
+    let Some(gl) = gl() else { return };
+    unsafe {
+        (gl.bind_texture)(GL_TEXTURE_RECTANGLE, tex_id);
+        // glGetTexImage writes the whole level, so refuse textures larger than the caller's buffer.
+        let (mut tex_width, mut tex_height) = (0, 0);
+        (gl.get_tex_level_parameteriv)(GL_TEXTURE_RECTANGLE, 0, GL_TEXTURE_WIDTH, &mut tex_width);
+        (gl.get_tex_level_parameteriv)(GL_TEXTURE_RECTANGLE, 0, GL_TEXTURE_HEIGHT, &mut tex_height);
+        if tex_width as usize * tex_height as usize * 4 > out_rgba.len() {
+            (gl.bind_texture)(GL_TEXTURE_RECTANGLE, 0);
+            return;
+        }
+        (gl.pixel_storei)(GL_PACK_ALIGNMENT, 1);
+        (gl.get_tex_image)(
+            GL_TEXTURE_RECTANGLE,
+            0,
+            GL_RGBA,
+            GL_UNSIGNED_BYTE,
+            out_rgba.as_mut_ptr().cast(),
+        );
+        (gl.bind_texture)(GL_TEXTURE_RECTANGLE, 0);
+    }
+}
+
+pub(crate) fn delete_texture(tex_id: u32) {
+    if tex_id == 0 || !has_current_context() {
+        return;
+    }
+    if let Some(gl) = gl() {
+        unsafe { (gl.delete_textures)(1, &tex_id) };
+    }
+}
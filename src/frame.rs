@@ -0,0 +1,319 @@
+//! CPU frames: pixel buffers with an explicit row stride, used by the CPU send/receive paths.
+
+use std::borrow::Cow;
+
+use crate::color::ColorSpace;
+use crate::geometry::{PublishError, PublishRequest, Rect, Size};
+use crate::stats::ReceiverStats;
+
+/// OpenGL pixel format for `Spout::sender_send_image` / `receiver_receive_image` with RGBA data.
+pub const GL_RGBA: u32 = 0x1908;
+
+/// OpenGL pixel format for BGRA data.
+pub const GL_BGRA: u32 = 0x80E1;
+
+/// `DXGI_FORMAT_R8G8B8A8_UNORM`, the Spout sender format for RGBA textures.
+pub const DXGI_FORMAT_R8G8B8A8_UNORM: u32 = 28;
+
+/// `DXGI_FORMAT_R8G8B8A8_UNORM_SRGB`.
+pub const DXGI_FORMAT_R8G8B8A8_UNORM_SRGB: u32 = 29;
+
+/// `DXGI_FORMAT_B8G8R8A8_UNORM`, Spout's default sender format.
+pub const DXGI_FORMAT_B8G8R8A8_UNORM: u32 = 87;
+
+/// `DXGI_FORMAT_B8G8R8A8_UNORM_SRGB`.
+pub const DXGI_FORMAT_B8G8R8A8_UNORM_SRGB: u32 = 91;
+
+/// Packed 8-bit-per-channel pixel layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum PixelFormat {
+    #[default]
+    Rgba8,
+    Bgra8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => 4,
+        }
+    }
+
+    /// The matching OpenGL format enum (`GL_RGBA` / `GL_BGRA`).
+    pub fn gl_format(&self) -> u32 {
+        match self {
+            PixelFormat::Rgba8 => GL_RGBA,
+            PixelFormat::Bgra8 => GL_BGRA,
+        }
+    }
+
+    /// Format for an OpenGL format enum, if supported.
+    pub fn from_gl_format(gl_format: u32) -> Option<Self> {
+        match gl_format {
+            GL_RGBA => Some(PixelFormat::Rgba8),
+            GL_BGRA => Some(PixelFormat::Bgra8),
+            _ => None,
+        }
+    }
+
+    /// The matching GStreamer video format name (`RGBA` / `BGRA`).
+    pub fn gst_format(&self) -> &'static str {
+        match self {
+            PixelFormat::Rgba8 => "RGBA",
+            PixelFormat::Bgra8 => "BGRA",
+        }
+    }
+
+    /// Format for a GStreamer video format name, if supported.
+    pub fn from_gst_format(name: &str) -> Option<Self> {
+        match name {
+            "RGBA" => Some(PixelFormat::Rgba8),
+            "BGRA" => Some(PixelFormat::Bgra8),
+            _ => None,
+        }
+    }
+
+    /// Format for a DXGI format (as in `SpoutSenderInfo::format`), if supported. The sRGB
+    /// variants map to the same layouts.
+    pub fn from_dxgi_format(dxgi_format: u32) -> Option<Self> {
+        match dxgi_format {
+            DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => {
+                Some(PixelFormat::Rgba8)
+            }
+            DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => {
+                Some(PixelFormat::Bgra8)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An owned CPU image. Rows are `stride` bytes apart; only the first `width * bytes_per_pixel`
+/// bytes of each row are pixels. Frames are tagged `ColorSpace::Srgb` unless set otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: PixelFormat,
+    color_space: ColorSpace,
+    data: Vec<u8>,
+}
+
+impl Frame {
+    /// A zeroed, tightly packed frame.
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        let stride = width as usize * format.bytes_per_pixel();
+        Self {
+            width,
+            height,
+            stride,
+            format,
+            color_space: ColorSpace::default(),
+            data: vec![0; stride * height as usize],
+        }
+    }
+
+    /// Wrap tightly packed pixels. Returns `None` if `data` is too short.
+    pub fn from_vec(width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> Option<Self> {
+        let stride = width as usize * format.bytes_per_pixel();
+        Self::from_vec_with_stride(width, height, stride, format, data)
+    }
+
+    /// Wrap pixels whose rows are `stride` bytes apart (e.g. a sub-view of a larger buffer's rows).
+    /// Returns `None` if the stride is shorter than a row or `data` is too short.
+    pub fn from_vec_with_stride(
+        width: u32,
+        height: u32,
+        stride: usize,
+        format: PixelFormat,
+        data: Vec<u8>,
+    ) -> Option<Self> {
+        let row_bytes = width as usize * format.bytes_per_pixel();
+        if stride < row_bytes {
+            return None;
+        }
+        if data.len() < required_len(row_bytes, stride, height as usize) {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            stride,
+            format,
+            color_space: ColorSpace::default(),
+            data,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Bytes between the starts of consecutive rows.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Retag the frame without touching its pixels (see `ColorConverter` to convert them).
+    pub fn set_color_space(&mut self, color_space: ColorSpace) {
+        self.color_space = color_space;
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Bytes of pixel data in one row (`width * bytes_per_pixel`).
+    pub fn row_bytes(&self) -> usize {
+        self.width as usize * self.format.bytes_per_pixel()
+    }
+
+    /// True if there is no padding between rows.
+    pub fn is_packed(&self) -> bool {
+        self.stride == self.row_bytes()
+    }
+
+    /// Raw buffer including any row padding.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Pixels of row `y` (without padding).
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.stride;
+        &self.data[start..start + self.row_bytes()]
+    }
+
+    pub fn row_mut(&mut self, y: u32) -> &mut [u8] {
+        let start = y as usize * self.stride;
+        let len = self.row_bytes();
+        &mut self.data[start..start + len]
+    }
+
+    /// Copy `rect` into a new tightly packed frame. Returns `None` if `rect` is empty or out of bounds.
+    pub fn crop(&self, rect: Rect) -> Option<Frame> {
+        if rect.is_empty() || !rect.fits_within(self.size()) {
+            return None;
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let out_row = rect.width as usize * bpp;
+        let mut data = Vec::with_capacity(out_row * rect.height as usize);
+        for y in rect.y..rect.y + rect.height {
+            let start = y as usize * self.stride + rect.x as usize * bpp;
+            data.extend_from_slice(&self.data[start..start + out_row]);
+        }
+        Frame::from_vec(rect.width, rect.height, self.format, data)
+            .map(|f| f.with_color_space(self.color_space))
+    }
+
+    /// This frame in `format`, swapping channels if needed. Clones when the format already matches.
+    pub fn to_format(&self, format: PixelFormat) -> Frame {
+        let mut out = self.clone();
+        if format != self.format {
+            for y in 0..self.height {
+                for px in out.row_mut(y).chunks_exact_mut(4) {
+                    px.swap(0, 2);
+                }
+            }
+            out.format = format;
+        }
+        out
+    }
+
+    /// Tightly packed pixels for the region of `request`, borrowing when no copy is needed.
+    /// The request must have been built for this frame's size.
+    pub fn extract(&self, request: &PublishRequest) -> Result<Cow<'_, [u8]>, PublishError> {
+        request.validate_for(self.size())?;
+        if request.is_full_texture() && self.is_packed() {
+            let len = self.row_bytes() * self.height as usize;
+            return Ok(Cow::Borrowed(&self.data[..len]));
+        }
+        let cropped = self
+            .crop(request.publish_region())
+            .ok_or(PublishError::RegionOutOfBounds {
+                region: request.publish_region(),
+                texture: self.size(),
+            })?;
+        Ok(Cow::Owned(cropped.into_vec()))
+    }
+}
+
+/// Minimum buffer length for `rows` rows of `row_bytes`, `stride` apart (last row needs no padding).
+fn required_len(row_bytes: usize, stride: usize, rows: usize) -> usize {
+    if rows == 0 {
+        0
+    } else {
+        stride * (rows - 1) + row_bytes
+    }
+}
+
+/// Anything CPU frames can be pulled from: Syphon and Spout clients, the pure-Rust `LocalClient`.
+pub trait FrameReceiver {
+    /// The next frame, if a new one arrived since the last call.
+    fn receive_frame(&mut self) -> Option<Frame>;
+
+    /// True while the source is still available.
+    fn is_connected(&self) -> bool;
+
+    /// Receive counters, for receivers that keep them.
+    fn stats(&self) -> Option<ReceiverStats> {
+        None
+    }
+}
+
+/// Anything CPU frames can be published to: Syphon and Spout servers, the pure-Rust `LocalServer`.
+pub trait FrameSender {
+    /// Publish a whole frame.
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError>;
+}
+
+impl<R: FrameReceiver + ?Sized> FrameReceiver for Box<R> {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        (**self).receive_frame()
+    }
+
+    fn is_connected(&self) -> bool {
+        (**self).is_connected()
+    }
+
+    fn stats(&self) -> Option<ReceiverStats> {
+        (**self).stats()
+    }
+}
+
+impl<S: FrameSender + ?Sized> FrameSender for Box<S> {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        (**self).send_frame(frame)
+    }
+}
@@ -0,0 +1,205 @@
+//! Pixel geometry for publishing: `Size`, `Rect` and a validated `PublishRequest`.
+//!
+//! Regions are in whole pixels with the origin at the top-left of the texture or buffer.
+//! Syphon takes the region as floating point; it is converted when the request is published.
+
+use std::fmt;
+
+/// Width and height in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Size {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// True if either dimension is zero.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Number of pixels (`width * height`).
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+/// Axis-aligned rectangle in pixels: origin `(x, y)` and extent `width` x `height`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Rectangle at the origin covering all of `size`.
+    pub const fn from_size(size: Size) -> Self {
+        Self::new(0, 0, size.width, size.height)
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// True if either dimension is zero.
+    pub fn is_empty(&self) -> bool {
+        self.size().is_empty()
+    }
+
+    /// One past the right-most column (`x + width`), widened so it cannot overflow.
+    pub fn right(&self) -> u64 {
+        self.x as u64 + self.width as u64
+    }
+
+    /// One past the bottom-most row (`y + height`), widened so it cannot overflow.
+    pub fn bottom(&self) -> u64 {
+        self.y as u64 + self.height as u64
+    }
+
+    /// True if the rectangle lies entirely inside a buffer of `size`.
+    pub fn fits_within(&self, size: Size) -> bool {
+        self.right() <= size.width as u64 && self.bottom() <= size.height as u64
+    }
+}
+
+/// Why a publish was rejected or failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishError {
+    /// The texture or buffer has a zero dimension.
+    EmptyTexture,
+    /// The region has a zero dimension.
+    EmptyRegion,
+    /// The region extends past the texture or buffer.
+    RegionOutOfBounds { region: Rect, texture: Size },
+    /// The request was built for a different size than the buffer being published.
+    SizeMismatch { expected: Size, actual: Size },
+    /// A texture or command buffer pointer was null.
+    NullTexture,
+    /// The backend can only publish regions anchored at the origin.
+    UnsupportedRegion(Rect),
+    /// The backend call returned failure.
+    Backend,
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishError::EmptyTexture => write!(f, "texture has a zero dimension"),
+            PublishError::EmptyRegion => write!(f, "publish region has a zero dimension"),
+            PublishError::RegionOutOfBounds { region, texture } => write!(
+                f,
+                "region {}x{} at ({}, {}) exceeds texture {}x{}",
+                region.width, region.height, region.x, region.y, texture.width, texture.height
+            ),
+            PublishError::SizeMismatch { expected, actual } => write!(
+                f,
+                "request is for {}x{} but buffer is {}x{}",
+                expected.width, expected.height, actual.width, actual.height
+            ),
+            PublishError::NullTexture => write!(f, "texture or command buffer is null"),
+            PublishError::UnsupportedRegion(region) => write!(
+                f,
+                "backend cannot publish a region offset to ({}, {})",
+                region.x, region.y
+            ),
+            PublishError::Backend => write!(f, "backend failed to publish the frame"),
+        }
+    }
+}
+
+impl std::error::Error for PublishError {}
+
+/// What to publish: the full texture size, the region inside it, and whether the image is flipped.
+///
+/// Build with `PublishRequest::new(size)` (whole texture), then narrow with `region`. Every
+/// backend's `publish` method calls `validate` before touching the texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublishRequest {
+    texture_size: Size,
+    region: Rect,
+    flipped: bool,
+}
+
+impl PublishRequest {
+    /// Publish all of a texture of `texture_size`, not flipped.
+    pub fn new(texture_size: Size) -> Self {
+        Self {
+            texture_size,
+            region: Rect::from_size(texture_size),
+            flipped: false,
+        }
+    }
+
+    /// Publish only `region` of the texture.
+    pub fn region(mut self, region: Rect) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Mark the texture contents as vertically flipped.
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    pub fn texture_size(&self) -> Size {
+        self.texture_size
+    }
+
+    pub fn publish_region(&self) -> Rect {
+        self.region
+    }
+
+    pub fn is_flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// True if the region is the whole texture.
+    pub fn is_full_texture(&self) -> bool {
+        self.region == Rect::from_size(self.texture_size)
+    }
+
+    /// Check that the texture is non-empty and the region is non-empty and inside it.
+    pub fn validate(&self) -> Result<(), PublishError> {
+        if self.texture_size.is_empty() {
+            return Err(PublishError::EmptyTexture);
+        }
+        if self.region.is_empty() {
+            return Err(PublishError::EmptyRegion);
+        }
+        if !self.region.fits_within(self.texture_size) {
+            return Err(PublishError::RegionOutOfBounds {
+                region: self.region,
+                texture: self.texture_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Validate, and also check that the request was built for a buffer of `actual` size.
+    pub fn validate_for(&self, actual: Size) -> Result<(), PublishError> {
+        if self.texture_size != actual {
+            return Err(PublishError::SizeMismatch {
+                expected: self.texture_size,
+                actual,
+            });
+        }
+        self.validate()
+    }
+}
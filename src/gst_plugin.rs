@@ -0,0 +1,656 @@
+//! GStreamer elements (feature `gstreamer`).
+//!
+//! - `syphonspoutsrc` receives from any source `discover_sources` can see (property `source`: a
+//!   `SourceInfo::id`, a source name, or empty for the first one found). It is a live source
+//!   producing `video/x-raw` RGBA or BGRA, whichever downstream accepts, and renegotiates when the
+//!   source changes size. It sends EOS when the source goes away.
+//! - `syphonspoutsink` publishes `video/x-raw` RGBA or BGRA buffers as a server named
+//!   `server-name` on `backend` (`local` or `syphon`; see `create_sender`).
+//!
+//! Register them in-process with `gst_plugin_register_static` after `gstreamer::init`. Built as a
+//! `cdylib`, the library is also a loadable plugin named `rusty_syphon_spout`. Receivers and
+//! senders run on a worker thread of each element, since backend handles are not `Send`.
+//!
+//! Spout is not supported by either element: it needs an OpenGL context current on the thread
+//! that connects and receives (see `discovery`), and the worker threads have none. `start` fails
+//! with a settings error for Spout sources and the `spout` backend.
+
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use gstreamer::subclass::prelude::*;
+use gstreamer_base as gst_base;
+use gstreamer_base::prelude::*;
+use gstreamer_base::subclass::prelude::*;
+
+use crate::discovery::{connect_source, create_sender, discover_sources, SourceBackend};
+use crate::frame::{Frame, PixelFormat};
+use crate::geometry::{PublishError, PublishRequest, Size};
+
+glib::wrapper! {
+    /// The `syphonspoutsrc` element.
+    pub struct SyphonSpoutSrc(ObjectSubclass<src_imp::SyphonSpoutSrc>)
+        @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+glib::wrapper! {
+    /// The `syphonspoutsink` element.
+    pub struct SyphonSpoutSink(ObjectSubclass<sink_imp::SyphonSpoutSink>)
+        @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+/// Register the `rusty_syphon_spout` plugin and its elements with GStreamer in this process.
+/// Call after `gstreamer::init`.
+pub fn gst_plugin_register_static() -> Result<(), glib::BoolError> {
+    plugin::plugin_register_static()
+}
+
+mod plugin {
+    use super::*;
+
+    gst::plugin_define!(
+        rusty_syphon_spout,
+        env!("CARGO_PKG_DESCRIPTION"),
+        plugin_init,
+        env!("CARGO_PKG_VERSION"),
+        "MIT/X11",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_REPOSITORY")
+    );
+
+    fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+        gst::Element::register(
+            Some(plugin),
+            "syphonspoutsrc",
+            gst::Rank::NONE,
+            SyphonSpoutSrc::static_type(),
+        )?;
+        gst::Element::register(
+            Some(plugin),
+            "syphonspoutsink",
+            gst::Rank::NONE,
+            SyphonSpoutSink::static_type(),
+        )
+    }
+}
+
+/// Template caps: RGBA or BGRA at any size and frame rate.
+fn template_caps() -> gst::Caps {
+    gst::Caps::builder("video/x-raw")
+        .field(
+            "format",
+            gst::List::new([
+                PixelFormat::Rgba8.gst_format(),
+                PixelFormat::Bgra8.gst_format(),
+            ]),
+        )
+        .field("width", gst::IntRange::new(1, i32::MAX))
+        .field("height", gst::IntRange::new(1, i32::MAX))
+        .field(
+            "framerate",
+            gst::FractionRange::new(gst::Fraction::new(0, 1), gst::Fraction::new(i32::MAX, 1)),
+        )
+        .build()
+}
+
+/// Fixed caps for frames of `size` in `format`, at a variable frame rate.
+fn frame_caps(size: Size, format: PixelFormat) -> gst::Caps {
+    gst::Caps::builder("video/x-raw")
+        .field("format", format.gst_format())
+        .field("width", size.width as i32)
+        .field("height", size.height as i32)
+        .field("framerate", gst::Fraction::new(0, 1))
+        .field("pixel-aspect-ratio", gst::Fraction::new(1, 1))
+        .build()
+}
+
+fn metadata(
+    cell: &'static OnceLock<gst::subclass::ElementMetadata>,
+    long_name: &str,
+    classification: &str,
+    description: &str,
+) -> Option<&'static gst::subclass::ElementMetadata> {
+    Some(cell.get_or_init(|| {
+        gst::subclass::ElementMetadata::new(
+            long_name,
+            classification,
+            description,
+            env!("CARGO_PKG_REPOSITORY"),
+        )
+    }))
+}
+
+/// Reject backends the worker threads cannot drive. See the module doc.
+fn check_backend(backend: SourceBackend) -> Result<(), gst::ErrorMessage> {
+    if backend == SourceBackend::Spout {
+        return Err(gst::error_msg!(
+            gst::LibraryError::Settings,
+            ["Spout needs an OpenGL context on the calling thread, which these elements lack"]
+        ));
+    }
+    Ok(())
+}
+
+/// Newest frame from the source's worker thread, plus flags the streaming thread waits on.
+#[derive(Default)]
+struct LatestFrame {
+    slot: Mutex<LatestSlot>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct LatestSlot {
+    frame: Option<Frame>,
+    disconnected: bool,
+    flushing: bool,
+    stop: bool,
+}
+
+impl LatestFrame {
+    fn update(&self, f: impl FnOnce(&mut LatestSlot)) {
+        f(&mut self.slot.lock().unwrap());
+        self.changed.notify_all();
+    }
+}
+
+mod src_imp {
+    use super::*;
+
+    #[derive(Default)]
+    struct Settings {
+        source: Option<String>,
+    }
+
+    struct State {
+        latest: Arc<LatestFrame>,
+        worker: Option<JoinHandle<()>>,
+    }
+
+    #[derive(Default)]
+    pub struct SyphonSpoutSrc {
+        settings: Mutex<Settings>,
+        state: Mutex<Option<State>>,
+        /// Size and format of the caps last set; `None` until the first frame or after a
+        /// reconfigure.
+        negotiated: Mutex<Option<(Size, PixelFormat)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SyphonSpoutSrc {
+        const NAME: &'static str = "GstSyphonSpoutSrc";
+        type Type = super::SyphonSpoutSrc;
+        type ParentType = gst_base::PushSrc;
+    }
+
+    impl ObjectImpl for SyphonSpoutSrc {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: OnceLock<Vec<glib::ParamSpec>> = OnceLock::new();
+            PROPERTIES.get_or_init(|| {
+                vec![glib::ParamSpecString::builder("source")
+                    .nick("Source")
+                    .blurb("Source id or name to receive from; empty for the first source found")
+                    .mutable_ready()
+                    .build()]
+            })
+        }
+
+        fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            if pspec.name() == "source" {
+                self.settings.lock().unwrap().source = value.get().unwrap();
+            }
+        }
+
+        fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "source" => self.settings.lock().unwrap().source.to_value(),
+                _ => unreachable!(),
+            }
+        }
+
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.set_live(true);
+            obj.set_format(gst::Format::Time);
+            obj.set_do_timestamp(true);
+        }
+    }
+
+    impl GstObjectImpl for SyphonSpoutSrc {}
+
+    impl ElementImpl for SyphonSpoutSrc {
+        fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+            static METADATA: OnceLock<gst::subclass::ElementMetadata> = OnceLock::new();
+            metadata(
+                &METADATA,
+                "Syphon/Spout source",
+                "Source/Video",
+                "Receives frames from a Syphon server, Spout sender or local server",
+            )
+        }
+
+        fn pad_templates() -> &'static [gst::PadTemplate] {
+            static TEMPLATES: OnceLock<Vec<gst::PadTemplate>> = OnceLock::new();
+            TEMPLATES.get_or_init(|| {
+                vec![gst::PadTemplate::new(
+                    "src",
+                    gst::PadDirection::Src,
+                    gst::PadPresence::Always,
+                    &template_caps(),
+                )
+                .unwrap()]
+            })
+        }
+    }
+
+    impl BaseSrcImpl for SyphonSpoutSrc {
+        fn start(&self) -> Result<(), gst::ErrorMessage> {
+            let wanted = self
+                .settings
+                .lock()
+                .unwrap()
+                .source
+                .clone()
+                .unwrap_or_default();
+            let source = discover_sources()
+                .into_iter()
+                .find(|s| wanted.is_empty() || s.id() == wanted || s.name == wanted)
+                .ok_or_else(|| {
+                    gst::error_msg!(
+                        gst::ResourceError::NotFound,
+                        ["No source matching '{}'", wanted]
+                    )
+                })?;
+            check_backend(source.backend)?;
+
+            let latest = Arc::new(LatestFrame::default());
+            let (connected_tx, connected_rx) = mpsc::channel();
+            let worker = {
+                let latest = latest.clone();
+                std::thread::Builder::new()
+                    .name("syphonspoutsrc".to_string())
+                    .spawn(move || {
+                        let Some(mut receiver) = connect_source(&source) else {
+                            let _ = connected_tx.send(false);
+                            return;
+                        };
+                        let _ = connected_tx.send(true);
+                        loop {
+                            if latest.slot.lock().unwrap().stop {
+                                return;
+                            }
+                            match receiver.receive_frame() {
+                                Some(frame) => latest.update(|slot| slot.frame = Some(frame)),
+                                None if !receiver.is_connected() => {
+                                    latest.update(|slot| slot.disconnected = true);
+                                    return;
+                                }
+                                None => std::thread::sleep(Duration::from_millis(2)),
+                            }
+                        }
+                    })
+                    .map_err(|err| {
+                        gst::error_msg!(gst::ResourceError::Failed, ["Thread: {}", err])
+                    })?
+            };
+            if connected_rx.recv() != Ok(true) {
+                let _ = worker.join();
+                return Err(gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Could not connect to '{}'", wanted]
+                ));
+            }
+            *self.negotiated.lock().unwrap() = None;
+            *self.state.lock().unwrap() = Some(State {
+                latest,
+                worker: Some(worker),
+            });
+            Ok(())
+        }
+
+        fn stop(&self) -> Result<(), gst::ErrorMessage> {
+            if let Some(mut state) = self.state.lock().unwrap().take() {
+                state.latest.update(|slot| slot.stop = true);
+                if let Some(worker) = state.worker.take() {
+                    let _ = worker.join();
+                }
+            }
+            Ok(())
+        }
+
+        fn is_seekable(&self) -> bool {
+            false
+        }
+
+        fn negotiate(&self) -> Result<(), gst::LoggableError> {
+            // Caps depend on the frames, so they are set in `create` once one arrives.
+            *self.negotiated.lock().unwrap() = None;
+            Ok(())
+        }
+
+        fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+            if let Some(state) = self.state.lock().unwrap().as_ref() {
+                state.latest.update(|slot| slot.flushing = true);
+            }
+            Ok(())
+        }
+
+        fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+            if let Some(state) = self.state.lock().unwrap().as_ref() {
+                state.latest.update(|slot| slot.flushing = false);
+            }
+            Ok(())
+        }
+    }
+
+    impl PushSrcImpl for SyphonSpoutSrc {
+        fn create(
+            &self,
+            _buffer: Option<&mut gst::BufferRef>,
+        ) -> Result<gst_base::subclass::base_src::CreateSuccess, gst::FlowError> {
+            let latest = self
+                .state
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|state| state.latest.clone())
+                .ok_or(gst::FlowError::Flushing)?;
+            let frame = {
+                let mut slot = latest.slot.lock().unwrap();
+                loop {
+                    if slot.flushing {
+                        return Err(gst::FlowError::Flushing);
+                    }
+                    if let Some(frame) = slot.frame.take() {
+                        break frame;
+                    }
+                    if slot.disconnected {
+                        return Err(gst::FlowError::Eos);
+                    }
+                    slot = latest
+                        .changed
+                        .wait_timeout(slot, Duration::from_millis(100))
+                        .unwrap()
+                        .0;
+                }
+            };
+            let format = self.ensure_caps(&frame)?;
+            let frame = if frame.format() == format {
+                frame
+            } else {
+                frame.to_format(format)
+            };
+            let data = frame
+                .extract(&PublishRequest::new(frame.size()))
+                .map_err(|_| gst::FlowError::Error)?
+                .into_owned();
+            Ok(gst_base::subclass::base_src::CreateSuccess::NewBuffer(
+                gst::Buffer::from_mut_slice(data),
+            ))
+        }
+    }
+
+    impl SyphonSpoutSrc {
+        /// Set caps for `frame`'s size if they changed. Keeps the frame's own format when
+        /// downstream accepts it, otherwise the other one. Returns the format to send.
+        fn ensure_caps(&self, frame: &Frame) -> Result<PixelFormat, gst::FlowError> {
+            let mut negotiated = self.negotiated.lock().unwrap();
+            if let Some((size, format)) = *negotiated {
+                if size == frame.size() {
+                    return Ok(format);
+                }
+            }
+            let obj = self.obj();
+            let peer = obj.src_pad().peer_query_caps(None);
+            let other = match frame.format() {
+                PixelFormat::Rgba8 => PixelFormat::Bgra8,
+                PixelFormat::Bgra8 => PixelFormat::Rgba8,
+            };
+            for format in [frame.format(), other] {
+                let caps = frame_caps(frame.size(), format);
+                if peer.can_intersect(&caps) {
+                    obj.set_caps(&caps)
+                        .map_err(|_| gst::FlowError::NotNegotiated)?;
+                    *negotiated = Some((frame.size(), format));
+                    return Ok(format);
+                }
+            }
+            Err(gst::FlowError::NotNegotiated)
+        }
+    }
+}
+
+mod sink_imp {
+    use super::*;
+
+    struct Settings {
+        server_name: String,
+        backend: String,
+    }
+
+    impl Default for Settings {
+        fn default() -> Self {
+            Self {
+                server_name: "GStreamer".to_string(),
+                backend: SourceBackend::Local.name().to_string(),
+            }
+        }
+    }
+
+    struct State {
+        frames: Option<mpsc::SyncSender<Frame>>,
+        /// The error that stopped the worker, if sending failed.
+        failed: mpsc::Receiver<PublishError>,
+        worker: Option<JoinHandle<()>>,
+        info: Option<(Size, PixelFormat)>,
+    }
+
+    #[derive(Default)]
+    pub struct SyphonSpoutSink {
+        settings: Mutex<Settings>,
+        state: Mutex<Option<State>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SyphonSpoutSink {
+        const NAME: &'static str = "GstSyphonSpoutSink";
+        type Type = super::SyphonSpoutSink;
+        type ParentType = gst_base::BaseSink;
+    }
+
+    impl ObjectImpl for SyphonSpoutSink {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: OnceLock<Vec<glib::ParamSpec>> = OnceLock::new();
+            PROPERTIES.get_or_init(|| {
+                vec![
+                    glib::ParamSpecString::builder("server-name")
+                        .nick("Server name")
+                        .blurb("Name to publish under")
+                        .default_value(Some("GStreamer"))
+                        .mutable_ready()
+                        .build(),
+                    glib::ParamSpecString::builder("backend")
+                        .nick("Backend")
+                        .blurb("local or syphon")
+                        .default_value(Some(SourceBackend::Local.name()))
+                        .mutable_ready()
+                        .build(),
+                ]
+            })
+        }
+
+        fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            let mut settings = self.settings.lock().unwrap();
+            let value: Option<String> = value.get().unwrap();
+            match pspec.name() {
+                "server-name" => settings.server_name = value.unwrap_or_default(),
+                "backend" => settings.backend = value.unwrap_or_default(),
+                _ => unreachable!(),
+            }
+        }
+
+        fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            let settings = self.settings.lock().unwrap();
+            match pspec.name() {
+                "server-name" => settings.server_name.to_value(),
+                "backend" => settings.backend.to_value(),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    impl GstObjectImpl for SyphonSpoutSink {}
+
+    impl ElementImpl for SyphonSpoutSink {
+        fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+            static METADATA: OnceLock<gst::subclass::ElementMetadata> = OnceLock::new();
+            metadata(
+                &METADATA,
+                "Syphon/Spout sink",
+                "Sink/Video",
+                "Publishes frames as a Syphon server, Spout sender or local server",
+            )
+        }
+
+        fn pad_templates() -> &'static [gst::PadTemplate] {
+            static TEMPLATES: OnceLock<Vec<gst::PadTemplate>> = OnceLock::new();
+            TEMPLATES.get_or_init(|| {
+                vec![gst::PadTemplate::new(
+                    "sink",
+                    gst::PadDirection::Sink,
+                    gst::PadPresence::Always,
+                    &template_caps(),
+                )
+                .unwrap()]
+            })
+        }
+    }
+
+    impl BaseSinkImpl for SyphonSpoutSink {
+        fn start(&self) -> Result<(), gst::ErrorMessage> {
+            let (name, backend_name) = {
+                let settings = self.settings.lock().unwrap();
+                (settings.server_name.clone(), settings.backend.clone())
+            };
+            let backend = SourceBackend::from_name(&backend_name).ok_or_else(|| {
+                gst::error_msg!(
+                    gst::LibraryError::Settings,
+                    ["Unknown backend '{}'", backend_name]
+                )
+            })?;
+            check_backend(backend)?;
+            let (frames, frames_rx) = mpsc::sync_channel::<Frame>(1);
+            let (created_tx, created_rx) = mpsc::channel();
+            let (failed_tx, failed) = mpsc::channel();
+            let worker = std::thread::Builder::new()
+                .name("syphonspoutsink".to_string())
+                .spawn(move || {
+                    let Some(mut sender) = create_sender(backend, &name) else {
+                        let _ = created_tx.send(false);
+                        return;
+                    };
+                    let _ = created_tx.send(true);
+                    for frame in frames_rx {
+                        if let Err(err) = sender.send_frame(&frame) {
+                            let _ = failed_tx.send(err);
+                            return;
+                        }
+                    }
+                })
+                .map_err(|err| gst::error_msg!(gst::ResourceError::Failed, ["Thread: {}", err]))?;
+            if created_rx.recv() != Ok(true) {
+                let _ = worker.join();
+                return Err(gst::error_msg!(
+                    gst::ResourceError::OpenWrite,
+                    ["Backend '{}' is not available", backend_name]
+                ));
+            }
+            *self.state.lock().unwrap() = Some(State {
+                frames: Some(frames),
+                failed,
+                worker: Some(worker),
+                info: None,
+            });
+            Ok(())
+        }
+
+        fn stop(&self) -> Result<(), gst::ErrorMessage> {
+            if let Some(mut state) = self.state.lock().unwrap().take() {
+                state.frames = None; // ends the worker's loop
+                if let Some(worker) = state.worker.take() {
+                    let _ = worker.join();
+                }
+            }
+            Ok(())
+        }
+
+        fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+            let s = caps
+                .structure(0)
+                .ok_or_else(|| gst::loggable_error!(gst::CAT_RUST, "Empty caps"))?;
+            let format = s
+                .get::<&str>("format")
+                .ok()
+                .and_then(PixelFormat::from_gst_format)
+                .ok_or_else(|| gst::loggable_error!(gst::CAT_RUST, "Unsupported format"))?;
+            let width = s.get::<i32>("width").unwrap_or(0);
+            let height = s.get::<i32>("height").unwrap_or(0);
+            if width <= 0 || height <= 0 {
+                return Err(gst::loggable_error!(gst::CAT_RUST, "Invalid size"));
+            }
+            if let Some(state) = self.state.lock().unwrap().as_mut() {
+                state.info = Some((Size::new(width as u32, height as u32), format));
+            }
+            Ok(())
+        }
+
+        fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+            // Frames are sent on the worker, so a failure shows up on the next buffer.
+            self.check_worker()?;
+            let (frames, (size, format)) = {
+                let state = self.state.lock().unwrap();
+                let state = state.as_ref().ok_or(gst::FlowError::Flushing)?;
+                (
+                    state.frames.clone().ok_or(gst::FlowError::Flushing)?,
+                    state.info.ok_or(gst::FlowError::NotNegotiated)?,
+                )
+            };
+            let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+            // RGBA/BGRA rows have no padding with GStreamer's default 4-byte stride alignment.
+            let len = size.width as usize * size.height as usize * format.bytes_per_pixel();
+            let data = map.get(..len).ok_or(gst::FlowError::Error)?.to_vec();
+            let frame = Frame::from_vec(size.width, size.height, format, data)
+                .ok_or(gst::FlowError::Error)?;
+            if frames.send(frame).is_err() {
+                // The worker stopped since the check above.
+                self.check_worker()?;
+                return Err(gst::FlowError::Error);
+            }
+            Ok(gst::FlowSuccess::Ok)
+        }
+    }
+
+    impl SyphonSpoutSink {
+        /// Posts an element error and fails if the worker could not send a frame.
+        fn check_worker(&self) -> Result<(), gst::FlowError> {
+            let state = self.state.lock().unwrap();
+            let failed = state.as_ref().map(|state| state.failed.try_recv());
+            drop(state);
+            if let Some(Ok(err)) = failed {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Write,
+                    ["Sending frame failed: {}", err]
+                );
+                return Err(gst::FlowError::Error);
+            }
+            Ok(())
+        }
+    }
+}
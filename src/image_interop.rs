@@ -0,0 +1,128 @@
+//! Conversions between `Frame` and the `image` crate's buffers (feature `image`).
+//!
+//! `ImageSender::publish_image` and `ImageReceiver::receive_image` work on every `FrameSender` /
+//! `FrameReceiver`, including `Box<dyn ...>`: images are published as RGBA and received frames
+//! come back as tightly packed `RgbaImage`s whatever their format and stride. Images carry no
+//! color space; frames made from them are tagged sRGB, and a frame's tag is dropped on the way out.
+
+use std::ops::Deref;
+
+use image::{DynamicImage, ImageBuffer, Rgb, Rgba, RgbaImage};
+
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::PublishError;
+
+/// Images that can be copied into an RGBA `Frame`.
+pub trait ToFrame {
+    fn to_frame(&self) -> Frame;
+}
+
+impl<C: Deref<Target = [u8]>> ToFrame for ImageBuffer<Rgba<u8>, C> {
+    fn to_frame(&self) -> Frame {
+        let pixels: &[u8] = self;
+        Frame::from_vec(
+            self.width(),
+            self.height(),
+            PixelFormat::Rgba8,
+            pixels.to_vec(),
+        )
+        .expect("image buffer holds width * height pixels")
+    }
+}
+
+/// RGB images are published opaque.
+impl<C: Deref<Target = [u8]>> ToFrame for ImageBuffer<Rgb<u8>, C> {
+    fn to_frame(&self) -> Frame {
+        let mut frame = Frame::new(self.width(), self.height(), PixelFormat::Rgba8);
+        for (dst, src) in frame.data_mut().chunks_exact_mut(4).zip(self.pixels()) {
+            dst.copy_from_slice(&[src[0], src[1], src[2], 255]);
+        }
+        frame
+    }
+}
+
+/// Other pixel types go through `DynamicImage::to_rgba8`, so 16-bit and float images are
+/// quantized to 8 bits per channel.
+impl ToFrame for DynamicImage {
+    fn to_frame(&self) -> Frame {
+        match self {
+            DynamicImage::ImageRgba8(image) => image.to_frame(),
+            DynamicImage::ImageRgb8(image) => image.to_frame(),
+            other => Frame::from(other.to_rgba8()),
+        }
+    }
+}
+
+/// Takes over the image's buffer without copying.
+impl From<RgbaImage> for Frame {
+    fn from(image: RgbaImage) -> Self {
+        let (width, height) = image.dimensions();
+        Frame::from_vec(width, height, PixelFormat::Rgba8, image.into_raw())
+            .expect("image buffer holds width * height pixels")
+    }
+}
+
+impl From<DynamicImage> for Frame {
+    fn from(image: DynamicImage) -> Self {
+        Frame::from(image.into_rgba8())
+    }
+}
+
+/// Reuses the frame's buffer when it is already packed RGBA.
+impl From<Frame> for RgbaImage {
+    fn from(frame: Frame) -> Self {
+        if frame.format() != PixelFormat::Rgba8 || !frame.is_packed() {
+            return frame.to_rgba_image();
+        }
+        let (width, height) = (frame.width(), frame.height());
+        let mut data = frame.into_vec();
+        data.truncate(width as usize * height as usize * 4);
+        RgbaImage::from_raw(width, height, data).expect("frame holds width * height pixels")
+    }
+}
+
+impl From<Frame> for DynamicImage {
+    fn from(frame: Frame) -> Self {
+        DynamicImage::ImageRgba8(frame.into())
+    }
+}
+
+impl Frame {
+    /// Copy into a tightly packed `RgbaImage`, swapping channels of BGRA frames.
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.width(), self.height());
+        let row_bytes = self.row_bytes();
+        if row_bytes == 0 {
+            return image;
+        }
+        for (y, dst) in image.chunks_exact_mut(row_bytes).enumerate() {
+            dst.copy_from_slice(self.row(y as u32));
+            if self.format() == PixelFormat::Bgra8 {
+                for px in dst.chunks_exact_mut(4) {
+                    px.swap(0, 2);
+                }
+            }
+        }
+        image
+    }
+}
+
+/// `publish_image` for every `FrameSender`.
+pub trait ImageSender: FrameSender {
+    /// Publish `image` as an RGBA frame.
+    fn publish_image<I: ToFrame + ?Sized>(&mut self, image: &I) -> Result<(), PublishError> {
+        self.send_frame(&image.to_frame())
+    }
+}
+
+impl<S: FrameSender + ?Sized> ImageSender for S {}
+
+/// `receive_image` for every `FrameReceiver`.
+pub trait ImageReceiver: FrameReceiver {
+    /// The next frame as an `RgbaImage`, if a new one arrived since the last call.
+    fn receive_image(&mut self) -> Option<RgbaImage> {
+        self.receive_frame().map(RgbaImage::from)
+    }
+}
+
+impl<R: FrameReceiver + ?Sized> ImageReceiver for R {}
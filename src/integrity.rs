@@ -0,0 +1,164 @@
+//! Integrity mode: per-frame XXH3 checksums sent alongside frames and verified on receipt.
+//!
+//! `IntegritySender` wraps any `FrameSender` and, before each frame, publishes the checksums of
+//! its most recent frames on a `MessageSender` (e.g. over a `FileMemoryBuffer` or a Spout memory
+//! buffer named after the server). `IntegrityReceiver` wraps the matching `FrameReceiver`, hashes
+//! every frame it gets and counts it as verified, corrupted or unverified in its `ReceiverStats`.
+//! Sending a window of checksums instead of one means a receiver that polls the side channel
+//! slightly after the sender has moved on still finds the checksum of the frame it read.
+
+use std::collections::VecDeque;
+
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::channel::{ChannelError, MemoryBuffer, MessageReceiver, MessageSender};
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::PublishError;
+use crate::stats::ReceiverStats;
+
+/// Checksums carried by each integrity message.
+pub const INTEGRITY_WINDOW: usize = 8;
+
+/// XXH3-64 of the frame's size, format and pixels (row padding excluded).
+pub fn frame_checksum(frame: &Frame) -> u64 {
+    let mut hasher = Xxh3::new();
+    hasher.update(&frame.width().to_le_bytes());
+    hasher.update(&frame.height().to_le_bytes());
+    hasher.update(&[match frame.format() {
+        PixelFormat::Rgba8 => 0,
+        PixelFormat::Bgra8 => 1,
+    }]);
+    for y in 0..frame.height() {
+        hasher.update(frame.row(y));
+    }
+    hasher.digest()
+}
+
+/// Sends frames to `S` and their checksums to a side channel on `B`.
+pub struct IntegritySender<S: FrameSender, B: MemoryBuffer> {
+    sender: S,
+    channel: MessageSender<B>,
+    recent: VecDeque<u64>,
+}
+
+impl<S: FrameSender, B: MemoryBuffer> IntegritySender<S, B> {
+    pub fn new(sender: S, checksum_buffer: B) -> Self {
+        Self {
+            sender,
+            channel: MessageSender::new(checksum_buffer),
+            recent: VecDeque::with_capacity(INTEGRITY_WINDOW),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.sender
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.sender
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sender
+    }
+
+    /// Publish the checksum of `frame` without sending it, for frames that reach the receiver
+    /// some other way (e.g. a GPU texture published with `OpenGLServer::publish`).
+    pub fn announce(&mut self, frame: &Frame) -> Result<(), ChannelError> {
+        if self.recent.len() == INTEGRITY_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(frame_checksum(frame));
+        let payload: Vec<u8> = self.recent.iter().flat_map(|c| c.to_le_bytes()).collect();
+        self.channel.send(&payload).map(|_| ())
+    }
+}
+
+impl<S: FrameSender, B: MemoryBuffer> FrameSender for IntegritySender<S, B> {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        self.announce(frame).map_err(|_| PublishError::Backend)?;
+        self.sender.send_frame(frame)
+    }
+}
+
+/// Receives frames from `R` and verifies them against checksums read from `B`.
+pub struct IntegrityReceiver<R: FrameReceiver, B: MemoryBuffer> {
+    receiver: R,
+    channel: MessageReceiver<B>,
+    known: Vec<u64>,
+    drop_corrupted: bool,
+    stats: ReceiverStats,
+}
+
+impl<R: FrameReceiver, B: MemoryBuffer> IntegrityReceiver<R, B> {
+    /// Corrupted frames are dropped (not returned) unless `drop_corrupted(false)` is set.
+    pub fn new(receiver: R, checksum_buffer: B) -> Self {
+        Self {
+            receiver,
+            channel: MessageReceiver::new(checksum_buffer),
+            known: Vec::new(),
+            drop_corrupted: true,
+            stats: ReceiverStats::default(),
+        }
+    }
+
+    /// Whether frames that fail verification are withheld from `receive_frame`.
+    pub fn drop_corrupted(mut self, drop: bool) -> Self {
+        self.drop_corrupted = drop;
+        self
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.receiver
+    }
+
+    pub fn into_inner(self) -> R {
+        self.receiver
+    }
+
+    /// Check `frame` against the latest checksums from the sender. `None` if none have arrived.
+    pub fn verify(&mut self, frame: &Frame) -> Option<bool> {
+        while let Ok(Some(message)) = self.channel.try_recv() {
+            self.known = message
+                .payload
+                .chunks_exact(8)
+                .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+                .collect();
+        }
+        if self.known.is_empty() {
+            return None;
+        }
+        Some(self.known.contains(&frame_checksum(frame)))
+    }
+}
+
+impl<R: FrameReceiver, B: MemoryBuffer> FrameReceiver for IntegrityReceiver<R, B> {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        let frame = self.receiver.receive_frame()?;
+        let skipped = self.receiver.stats().map_or(0, |inner| {
+            inner
+                .frames_dropped
+                .saturating_sub(self.stats.frames_dropped)
+        });
+        self.stats.record_frame(skipped);
+        match self.verify(&frame) {
+            Some(true) => self.stats.frames_verified += 1,
+            Some(false) => {
+                self.stats.frames_corrupted += 1;
+                if self.drop_corrupted {
+                    return None;
+                }
+            }
+            None => self.stats.frames_unverified += 1,
+        }
+        Some(frame)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.receiver.is_connected()
+    }
+
+    fn stats(&self) -> Option<ReceiverStats> {
+        Some(self.stats)
+    }
+}
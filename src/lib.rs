@@ -4,8 +4,109 @@
 //!
 //! - **macOS**: Server directory, `SyphonOptions`, OpenGL and Metal servers/clients, CGL/GL helpers.
 //! - **Windows**: `Spout` type for sender and receiver (OpenGL textures), sender list discovery.
+//! - **Linux**: headless EGL contexts (`egl_create_headless_context`) for the `gl_*` texture helpers;
+//!   `MemfdServer` / `MemfdClient` pass sealed memfd frames between processes over a Unix socket.
+//! - **All platforms**:
+//!   - `PublishRequest`: validated publish regions, and CPU `Frame`s for the publish paths.
+//!   - `MessageSender` / `MessageReceiver`: framed messages over shared memory buffers.
+//!   - `ControlServer` / `ControlClient`: back-channel from clients to servers.
+//!   - `IntegritySender` / `IntegrityReceiver`: checksummed frames.
+//!   - `RtpSender` / `RtpReceiver`: uncompressed video over RTP (RFC 4175).
+//!   - `discover_sources`: sources from every backend.
+//!   - `SourceDescription`: serializable source details to look up again later.
+//!   - `SourceSelector`: finds a source again after its app restarts.
+//!   - `ReconnectingReceiver`: follows a selector across restarts.
+//!   - `PreviewServer`: MJPEG previews in a browser.
+//!   - `gstreamer` feature: `syphonspoutsrc` / `syphonspoutsink` elements.
+//!   - `python` feature: `python_module` with NumPy frames.
+//!   - `wgpu` feature: `WgpuSender` / `WgpuReceiver` for `wgpu::Texture`s.
+//!   - `image` feature: `publish_image` / `receive_image`.
+//! - **Pure Rust**:
+//!   - `LocalServer` / `LocalClient`: CPU frames shared inside a process.
+//!   - `FrameSender` / `FrameReceiver`: one interface over every backend.
+//!   - `Compositor`: tiles or layers several receivers into one output.
+//!   - `SourceSwitcher`: cuts or crossfades between sources.
+//!   - `SyncSender` / `SyncReceiver`: frame-by-frame sync between sender and receivers.
+//!   - `FramePool`: reused frame buffers for `receive_pooled`.
+//!   - `LockingReceiver`: `lock_frame` borrows frames without copying.
+//!   - `Resampler`: scales frames to a fixed size.
+//!   - `ColorConverter`: conversion between `ColorSpace`s.
+//!   - `YuvFrame`: NV12, I420, UYVY and P010.
+//!   - `FrameCompare`: frame comparison for tests.
+//! - **C ABI**: built as a `cdylib`, the library exports the `rss_*` functions declared in
+//!   `include/rusty_syphon_spout.h` for discovery, sending and receiving from C, C++ or C#.
 
+mod capi;
+mod channel;
+mod color;
+mod compare;
+mod compositor;
+mod control;
+mod description;
+mod discovery;
+#[cfg(target_os = "linux")]
+mod egl;
 mod ffi;
+mod frame;
+mod geometry;
+#[cfg(feature = "gstreamer")]
+mod gst_plugin;
+#[cfg(feature = "image")]
+mod image_interop;
+mod integrity;
+mod local;
+mod lock;
+#[cfg(target_os = "linux")]
+mod memfd;
+mod pool;
+mod preview;
+mod reconnect;
+#[cfg(feature = "python")]
+mod python;
+mod resample;
+mod rtp;
 mod safe;
+mod selector;
+mod stats;
+mod switcher;
+mod sync;
+mod util;
+#[cfg(feature = "wgpu")]
+mod wgpu_interop;
+mod yuv;
 
+pub use channel::*;
+pub use color::*;
+pub use compare::*;
+pub use compositor::*;
+pub use control::*;
+pub use description::*;
+pub use discovery::*;
+#[cfg(target_os = "linux")]
+pub use egl::*;
+pub use frame::*;
+pub use geometry::*;
+#[cfg(feature = "gstreamer")]
+pub use gst_plugin::*;
+#[cfg(feature = "image")]
+pub use image_interop::*;
+pub use integrity::*;
+pub use local::*;
+pub use lock::*;
+#[cfg(target_os = "linux")]
+pub use memfd::*;
+pub use pool::*;
+pub use preview::*;
+pub use reconnect::*;
+#[cfg(feature = "python")]
+pub use python::*;
+pub use resample::*;
+pub use rtp::*;
 pub use safe::*;
+pub use selector::*;
+pub use stats::*;
+pub use switcher::*;
+pub use sync::*;
+#[cfg(feature = "wgpu")]
+pub use wgpu_interop::*;
+pub use yuv::*;
@@ -0,0 +1,371 @@
+//! Pure-Rust in-process backend: `LocalServer` publishes CPU frames, `LocalClient` receives them.
+//!
+//! Servers register in a process-wide directory (`local_servers`) under a name and UUID, like
+//! Syphon servers. Frames are shared as `Arc<Frame>`, so publishing never copies pixels and any
+//! number of clients can read the latest frame. Use it to route frames inside one application,
+//! and to exercise sender/receiver code on platforms without Syphon or Spout.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::color::ColorSpace;
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::{PublishError, Size};
+use crate::lock::{FrameLock, LockingReceiver};
+use crate::pool::{FramePool, PooledFrame, PooledReceiver};
+use crate::stats::ReceiverStats;
+use crate::sync::{SyncMode, SyncReceiver, SyncSender};
+use crate::util::{app_name, new_uuid};
+
+static DIRECTORY: Mutex<Vec<Arc<Shared>>> = Mutex::new(Vec::new());
+
+struct Slot {
+    frame: Option<Arc<Frame>>,
+    /// Number of frames published so far; 0 before the first.
+    frame_number: u64,
+    /// Attached clients that have taken frame `frame_number`.
+    taken: usize,
+}
+
+impl Slot {
+    /// Mark the current frame as taken by a client that last saw `last_seen`. Returns false if
+    /// it had taken it already.
+    fn take(&mut self, last_seen: &mut u64, stats: &mut ReceiverStats) -> bool {
+        if self.frame_number == *last_seen {
+            return false;
+        }
+        stats.record_frame(self.frame_number - *last_seen - 1);
+        *last_seen = self.frame_number;
+        self.taken += 1;
+        true
+    }
+}
+
+struct Shared {
+    uuid: String,
+    name: Mutex<String>,
+    app_name: String,
+    color_space: Mutex<ColorSpace>,
+    slot: Mutex<Slot>,
+    new_frame: Condvar,
+    /// Notified when a client takes a frame or detaches.
+    consumed: Condvar,
+    alive: AtomicBool,
+    clients: AtomicUsize,
+}
+
+/// Directory entry for a `LocalServer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalServerInfo {
+    pub uuid: String,
+    pub name: String,
+    pub app_name: String,
+    /// Size and format of the latest frame, if one has been published.
+    pub size: Option<Size>,
+    pub format: Option<PixelFormat>,
+    /// Color space the server advertises for its frames.
+    pub color_space: ColorSpace,
+}
+
+/// Servers currently registered in this process, oldest first.
+pub fn local_servers() -> Vec<LocalServerInfo> {
+    DIRECTORY.lock().unwrap().iter().map(|s| s.info()).collect()
+}
+
+impl Shared {
+    fn info(&self) -> LocalServerInfo {
+        let slot = self.slot.lock().unwrap();
+        LocalServerInfo {
+            uuid: self.uuid.clone(),
+            name: self.name.lock().unwrap().clone(),
+            app_name: self.app_name.clone(),
+            size: slot.frame.as_ref().map(|f| f.size()),
+            format: slot.frame.as_ref().map(|f| f.format()),
+            color_space: *self.color_space.lock().unwrap(),
+        }
+    }
+}
+
+/// Publishes CPU frames to `LocalClient`s in the same process. Unregisters on drop.
+pub struct LocalServer {
+    shared: Arc<Shared>,
+}
+
+impl LocalServer {
+    /// Register a server called `name`. Names need not be unique; the UUID is.
+    pub fn new(name: &str) -> Self {
+        let shared = Arc::new(Shared {
+            uuid: new_uuid(),
+            name: Mutex::new(name.to_string()),
+            app_name: app_name(),
+            color_space: Mutex::new(ColorSpace::default()),
+            slot: Mutex::new(Slot {
+                frame: None,
+                frame_number: 0,
+                taken: 0,
+            }),
+            new_frame: Condvar::new(),
+            consumed: Condvar::new(),
+            alive: AtomicBool::new(true),
+            clients: AtomicUsize::new(0),
+        });
+        DIRECTORY.lock().unwrap().push(shared.clone());
+        Self { shared }
+    }
+
+    pub fn uuid(&self) -> &str {
+        &self.shared.uuid
+    }
+
+    pub fn name(&self) -> String {
+        self.shared.name.lock().unwrap().clone()
+    }
+
+    pub fn set_name(&self, name: &str) {
+        *self.shared.name.lock().unwrap() = name.to_string();
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        *self.shared.color_space.lock().unwrap()
+    }
+
+    /// Advertise `color_space` in the directory entry. Frames keep their own tags; publish frames
+    /// tagged with the advertised space.
+    pub fn set_color_space(&self, color_space: ColorSpace) {
+        *self.shared.color_space.lock().unwrap() = color_space;
+    }
+
+    pub fn info(&self) -> LocalServerInfo {
+        self.shared.info()
+    }
+
+    /// True if any `LocalClient` is attached.
+    pub fn has_clients(&self) -> bool {
+        self.shared.clients.load(Ordering::SeqCst) > 0
+    }
+
+    /// Number of frames published so far.
+    pub fn frame_number(&self) -> u64 {
+        self.shared.slot.lock().unwrap().frame_number
+    }
+
+    /// Publish `frame` without copying it. Returns the new frame number.
+    pub fn publish(&self, frame: impl Into<Arc<Frame>>) -> u64 {
+        let mut slot = self.shared.slot.lock().unwrap();
+        slot.frame = Some(frame.into());
+        slot.frame_number += 1;
+        slot.taken = 0;
+        self.shared.new_frame.notify_all();
+        slot.frame_number
+    }
+
+    /// Number of attached clients that have taken the latest frame.
+    pub fn consumed_count(&self) -> usize {
+        self.shared.slot.lock().unwrap().taken
+    }
+
+    /// Block until clients have taken the latest frame as `mode` requires, or `timeout` passes.
+    /// Clients that attached after it was published count too, since they can still take it.
+    /// True at once before the first frame and while no client is attached.
+    pub fn wait_consumed(&self, mode: SyncMode, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut slot = self.shared.slot.lock().unwrap();
+        loop {
+            let clients = self.shared.clients.load(Ordering::SeqCst);
+            if slot.frame_number == 0 || mode.satisfied(slot.taken, clients) {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            slot = self
+                .shared
+                .consumed
+                .wait_timeout(slot, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Remove the server from the directory and disconnect clients. Called on drop.
+    pub fn stop(&self) {
+        if self.shared.alive.swap(false, Ordering::SeqCst) {
+            DIRECTORY
+                .lock()
+                .unwrap()
+                .retain(|s| !Arc::ptr_eq(s, &self.shared));
+            let _guard = self.shared.slot.lock().unwrap();
+            self.shared.new_frame.notify_all();
+        }
+    }
+}
+
+impl Drop for LocalServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl FrameSender for LocalServer {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        self.publish(frame.clone());
+        Ok(())
+    }
+}
+
+impl SyncSender for LocalServer {
+    fn wait_consumed(&mut self, mode: SyncMode, timeout: Duration) -> bool {
+        LocalServer::wait_consumed(self, mode, timeout)
+    }
+}
+
+/// Receives frames from a `LocalServer`. Keeps the server's latest frame alive while attached.
+pub struct LocalClient {
+    shared: Arc<Shared>,
+    last_seen: u64,
+    stats: ReceiverStats,
+}
+
+impl LocalClient {
+    /// Attach to the oldest registered server called `name`.
+    pub fn connect(name: &str) -> Option<Self> {
+        let directory = DIRECTORY.lock().unwrap();
+        let shared = directory
+            .iter()
+            .find(|s| *s.name.lock().unwrap() == name)?
+            .clone();
+        Some(Self::attach(shared))
+    }
+
+    /// Attach to the server with this UUID.
+    pub fn connect_uuid(uuid: &str) -> Option<Self> {
+        let directory = DIRECTORY.lock().unwrap();
+        let shared = directory.iter().find(|s| s.uuid == uuid)?.clone();
+        Some(Self::attach(shared))
+    }
+
+    fn attach(shared: Arc<Shared>) -> Self {
+        shared.clients.fetch_add(1, Ordering::SeqCst);
+        // The current frame is still new to this client; earlier ones were never meant for it
+        // and don't count as dropped.
+        let last_seen = shared.slot.lock().unwrap().frame_number.saturating_sub(1);
+        Self {
+            shared,
+            last_seen,
+            stats: ReceiverStats::default(),
+        }
+    }
+
+    /// Current directory entry for the server.
+    pub fn server_info(&self) -> LocalServerInfo {
+        self.shared.info()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.shared.alive.load(Ordering::SeqCst)
+    }
+
+    /// True if the server published a frame this client has not taken yet.
+    pub fn has_new_frame(&self) -> bool {
+        self.shared.slot.lock().unwrap().frame_number != self.last_seen
+    }
+
+    /// Frame number of the last frame taken by this client. Before the first, the number of
+    /// the frame before the one current when it attached.
+    pub fn last_frame_number(&self) -> u64 {
+        self.last_seen
+    }
+
+    /// The latest frame (shared, not copied), marking it as seen. Returns the frame even if it was seen before.
+    pub fn latest_frame(&mut self) -> Option<Arc<Frame>> {
+        let mut slot = self.shared.slot.lock().unwrap();
+        if slot.take(&mut self.last_seen, &mut self.stats) {
+            self.shared.consumed.notify_all();
+        }
+        slot.frame.clone()
+    }
+
+    /// The latest frame if it is new since the last call, without copying.
+    pub fn new_frame(&mut self) -> Option<Arc<Frame>> {
+        let mut slot = self.shared.slot.lock().unwrap();
+        if !slot.take(&mut self.last_seen, &mut self.stats) {
+            return None;
+        }
+        self.shared.consumed.notify_all();
+        slot.frame.clone()
+    }
+
+    /// The latest frame if it is new since the last call, copied into a frame from `pool`.
+    pub fn new_frame_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        self.new_frame().map(|frame| pool.copy_of(&frame))
+    }
+
+    /// Receive counters; frames replaced before this client took them count as dropped.
+    pub fn stats(&self) -> ReceiverStats {
+        self.stats
+    }
+
+    /// Block until a new frame is published, the server stops, or `timeout` passes.
+    pub fn wait_new_frame(&mut self, timeout: Duration) -> Option<Arc<Frame>> {
+        let deadline = Instant::now() + timeout;
+        let mut slot = self.shared.slot.lock().unwrap();
+        while slot.frame_number == self.last_seen && self.is_valid() {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            slot = self.shared.new_frame.wait_timeout(slot, deadline - now).unwrap().0;
+        }
+        if !slot.take(&mut self.last_seen, &mut self.stats) {
+            return None;
+        }
+        self.shared.consumed.notify_all();
+        slot.frame.clone()
+    }
+}
+
+impl Drop for LocalClient {
+    fn drop(&mut self) {
+        let mut slot = self.shared.slot.lock().unwrap();
+        if slot.frame_number != 0 && slot.frame_number == self.last_seen {
+            slot.taken -= 1;
+        }
+        self.shared.clients.fetch_sub(1, Ordering::SeqCst);
+        self.shared.consumed.notify_all();
+    }
+}
+
+impl FrameReceiver for LocalClient {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        self.new_frame().map(|f| (*f).clone())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_valid()
+    }
+
+    fn stats(&self) -> Option<ReceiverStats> {
+        Some(self.stats)
+    }
+}
+
+/// Shares the server's frame: publishing again swaps in a new frame rather than writing over it.
+impl LockingReceiver for LocalClient {
+    fn lock_frame(&mut self) -> Option<FrameLock<'_>> {
+        self.new_frame().map(FrameLock::from)
+    }
+}
+
+impl PooledReceiver for LocalClient {
+    fn receive_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        self.new_frame_pooled(pool)
+    }
+}
+
+impl SyncReceiver for LocalClient {
+    fn wait_frame(&mut self, timeout: Duration) -> Option<Frame> {
+        self.wait_new_frame(timeout).map(|f| (*f).clone())
+    }
+}
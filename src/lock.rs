@@ -0,0 +1,213 @@
+//! Borrowing a received frame instead of copying it.
+//!
+//! `LockingReceiver::lock_frame` is `receive_frame` without the copy where the backend allows it:
+//! the returned `FrameLock` points straight at the sender's pixels and keeps them from being
+//! replaced or unmapped until it drops. `LocalClient` shares the server's `Arc<Frame>`, and
+//! `MemfdClient` maps the sealed memfd read-only (sealing already rules out writes). Backends
+//! whose pixels live in GPU textures or in memory the sender reuses (Syphon, Spout) copy into
+//! the lock instead; `is_zero_copy` tells the two apart.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::color::ColorSpace;
+use crate::frame::{Frame, FrameReceiver, PixelFormat};
+use crate::geometry::Size;
+
+enum Pixels {
+    /// The sender's own frame.
+    Shared(Arc<Frame>),
+    /// A copy, for backends that cannot lend their memory.
+    Copied(Frame),
+    /// Memory owned by the transport, e.g. a read-only mapping.
+    Borrowed(Box<dyn AsRef<[u8]> + Send>),
+}
+
+/// Everything about a frame but its pixels.
+#[derive(Clone, Copy)]
+struct Shape {
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: PixelFormat,
+    color_space: ColorSpace,
+}
+
+impl Shape {
+    fn of(frame: &Frame) -> Self {
+        Self {
+            width: frame.width(),
+            height: frame.height(),
+            stride: frame.stride(),
+            format: frame.format(),
+            color_space: frame.color_space(),
+        }
+    }
+}
+
+/// A read-only view of a received frame; see the module docs. Borrows the receiver, so it
+/// cannot receive again until the lock drops.
+pub struct FrameLock<'a> {
+    shape: Shape,
+    pixels: Pixels,
+    _receiver: PhantomData<&'a mut ()>,
+}
+
+impl FrameLock<'_> {
+    fn new(shape: Shape, pixels: Pixels) -> Self {
+        Self {
+            shape,
+            pixels,
+            _receiver: PhantomData,
+        }
+    }
+
+    /// Lend `bytes`, whose rows are `stride` bytes apart. Returns `None` if they are too short.
+    pub(crate) fn borrowed(
+        width: u32,
+        height: u32,
+        stride: usize,
+        format: PixelFormat,
+        color_space: ColorSpace,
+        bytes: impl AsRef<[u8]> + Send + 'static,
+    ) -> Option<Self> {
+        let row_bytes = width as usize * format.bytes_per_pixel();
+        let needed = (height as usize)
+            .checked_sub(1)
+            .map_or(0, |last| stride * last + row_bytes);
+        if stride < row_bytes || bytes.as_ref().len() < needed {
+            return None;
+        }
+        let shape = Shape {
+            width,
+            height,
+            stride,
+            format,
+            color_space,
+        };
+        Some(Self::new(shape, Pixels::Borrowed(Box::new(bytes))))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.shape.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.shape.height
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.shape.width, self.shape.height)
+    }
+
+    pub fn stride(&self) -> usize {
+        self.shape.stride
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.shape.format
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.shape.color_space
+    }
+
+    /// Bytes of pixels per row (without padding).
+    pub fn row_bytes(&self) -> usize {
+        self.shape.width as usize * self.shape.format.bytes_per_pixel()
+    }
+
+    /// All the bytes, rows `stride` apart.
+    pub fn data(&self) -> &[u8] {
+        match &self.pixels {
+            Pixels::Shared(frame) => frame.data(),
+            Pixels::Copied(frame) => frame.data(),
+            Pixels::Borrowed(bytes) => (**bytes).as_ref(),
+        }
+    }
+
+    /// Pixels of row `y` (without padding).
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.shape.stride;
+        &self.data()[start..start + self.row_bytes()]
+    }
+
+    /// False if the pixels were copied out of the backend.
+    pub fn is_zero_copy(&self) -> bool {
+        !matches!(self.pixels, Pixels::Copied(_))
+    }
+
+    /// An owned copy of the frame.
+    pub fn to_frame(&self) -> Frame {
+        match &self.pixels {
+            Pixels::Shared(frame) => (**frame).clone(),
+            Pixels::Copied(frame) => frame.clone(),
+            Pixels::Borrowed(_) => self.copy_rows(),
+        }
+    }
+
+    /// The frame, copying only if it is not already owned here.
+    pub fn into_frame(self) -> Frame {
+        match self.pixels {
+            Pixels::Shared(frame) => Arc::try_unwrap(frame).unwrap_or_else(|f| (*f).clone()),
+            Pixels::Copied(frame) => frame,
+            Pixels::Borrowed(_) => self.copy_rows(),
+        }
+    }
+
+    fn copy_rows(&self) -> Frame {
+        let Shape {
+            width,
+            height,
+            format,
+            color_space,
+            ..
+        } = self.shape;
+        let mut frame = Frame::new(width, height, format);
+        for y in 0..height {
+            frame.row_mut(y).copy_from_slice(self.row(y));
+        }
+        frame.with_color_space(color_space)
+    }
+}
+
+impl From<Arc<Frame>> for FrameLock<'_> {
+    fn from(frame: Arc<Frame>) -> Self {
+        Self::new(Shape::of(&frame), Pixels::Shared(frame))
+    }
+}
+
+impl From<Frame> for FrameLock<'_> {
+    fn from(frame: Frame) -> Self {
+        Self::new(Shape::of(&frame), Pixels::Copied(frame))
+    }
+}
+
+impl fmt::Debug for FrameLock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameLock")
+            .field("width", &self.shape.width)
+            .field("height", &self.shape.height)
+            .field("stride", &self.shape.stride)
+            .field("format", &self.shape.format)
+            .field("color_space", &self.shape.color_space)
+            .field("zero_copy", &self.is_zero_copy())
+            .finish()
+    }
+}
+
+/// A `FrameReceiver` that can lend new frames instead of copying them.
+pub trait LockingReceiver: FrameReceiver {
+    /// The next frame, if a new one arrived since the last call, as a lock on the sender's
+    /// pixels. The default copies it from `receive_frame`.
+    fn lock_frame(&mut self) -> Option<FrameLock<'_>> {
+        self.receive_frame().map(FrameLock::from)
+    }
+}
+
+impl<R: LockingReceiver + ?Sized> LockingReceiver for Box<R> {
+    fn lock_frame(&mut self) -> Option<FrameLock<'_>> {
+        (**self).lock_frame()
+    }
+}
@@ -0,0 +1,786 @@
+//! Linux transport that hands frames to other processes as sealed memfds over a Unix socket.
+//!
+//! `MemfdServer` listens on a `SOCK_SEQPACKET` Unix socket (under the shared temp directory, or
+//! any path, e.g. one bind-mounted into a container). Each published frame is written into a new
+//! memfd, sealed against shrinking, growing and writing, and passed to every client with
+//! `SCM_RIGHTS` alongside a small header. Because sealed frames are immutable, clients can never
+//! see a torn frame or have a buffer resized under them, and they need no access to `/dev/shm`.
+//! `MemfdClient` drains the socket, keeps the newest frame and verifies its seals before reading,
+//! then acknowledges it with a small packet back to the server, which `wait_consumed` waits on.
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::color::ColorSpace;
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::PublishError;
+use crate::lock::{FrameLock, LockingReceiver};
+use crate::pool::{FramePool, PooledFrame, PooledReceiver};
+use crate::stats::ReceiverStats;
+use crate::sync::{SyncMode, SyncReceiver, SyncSender};
+use crate::util::{file_safe_name, shared_temp_dir};
+
+const MAGIC: [u8; 4] = *b"RSMF";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 40;
+/// Client-to-server acknowledgement: `ACK_MAGIC` and the frame number taken.
+const ACK_MAGIC: [u8; 4] = *b"RSMA";
+const ACK_LEN: usize = 12;
+const REQUIRED_SEALS: libc::c_int = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE;
+
+/// Socket path used by `MemfdServer::bind(name)` and `MemfdClient::connect(name)`.
+pub fn memfd_socket_path(name: &str) -> PathBuf {
+    shared_temp_dir()
+        .join("memfd")
+        .join(format!("{}.sock", file_safe_name(name)))
+}
+
+/// Frame description sent with each memfd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    format: PixelFormat,
+    color_space: ColorSpace,
+    width: u32,
+    height: u32,
+    stride: u32,
+    frame_number: u64,
+    data_len: u64,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut out = [0u8; HEADER_LEN];
+        out[0..4].copy_from_slice(&MAGIC);
+        out[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        out[6] = match self.format {
+            PixelFormat::Rgba8 => 0,
+            PixelFormat::Bgra8 => 1,
+        };
+        out[7] = ColorSpace::ALL
+            .iter()
+            .position(|c| *c == self.color_space)
+            .unwrap_or(0) as u8;
+        out[8..12].copy_from_slice(&self.width.to_le_bytes());
+        out[12..16].copy_from_slice(&self.height.to_le_bytes());
+        out[16..20].copy_from_slice(&self.stride.to_le_bytes());
+        out[24..32].copy_from_slice(&self.frame_number.to_le_bytes());
+        out[32..40].copy_from_slice(&self.data_len.to_le_bytes());
+        out
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != HEADER_LEN || bytes[0..4] != MAGIC {
+            return None;
+        }
+        if u16::from_le_bytes([bytes[4], bytes[5]]) != VERSION {
+            return None;
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Some(Self {
+            format: match bytes[6] {
+                0 => PixelFormat::Rgba8,
+                1 => PixelFormat::Bgra8,
+                _ => return None,
+            },
+            color_space: *ColorSpace::ALL.get(bytes[7] as usize)?,
+            width: u32_at(8),
+            height: u32_at(12),
+            stride: u32_at(16),
+            frame_number: u64_at(24),
+            data_len: u64_at(32),
+        })
+    }
+}
+
+/// A connected client, as the server sees it.
+struct Peer {
+    socket: OwnedFd,
+    /// Last frame number sent to this client; 0 if none.
+    sent: u64,
+    /// Last frame number the client acknowledged.
+    acked: u64,
+}
+
+/// Publishes frames as sealed memfds to clients connected over a Unix socket. Removes the
+/// socket on drop.
+pub struct MemfdServer {
+    listener: OwnedFd,
+    path: PathBuf,
+    clients: Vec<Peer>,
+    frame_number: u64,
+}
+
+impl MemfdServer {
+    /// Listen at `memfd_socket_path(name)`, replacing a stale socket left by a previous run.
+    pub fn bind(name: &str) -> io::Result<Self> {
+        let path = memfd_socket_path(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::bind_path(path)
+    }
+
+    /// Listen at `path`, replacing a stale socket file there. Fails with `AddrInUse` if a
+    /// server is still listening on it.
+    pub fn bind_path(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if connect_socket(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "a memfd server is already listening there",
+            ));
+        }
+        let _ = std::fs::remove_file(&path);
+        let listener = seqpacket_socket()?;
+        let addr = sockaddr(&path)?;
+        unsafe {
+            if libc::bind(
+                listener.as_raw_fd(),
+                &addr as *const _ as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_un>() as libc::socklen_t,
+            ) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::listen(listener.as_raw_fd(), 16) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        set_nonblocking(listener.as_raw_fd())?;
+        Ok(Self {
+            listener,
+            path,
+            clients: Vec::new(),
+            frame_number: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of frames published so far.
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    /// Accept pending connections and return how many clients are attached.
+    pub fn client_count(&mut self) -> usize {
+        self.accept_pending();
+        self.clients.len()
+    }
+
+    /// Number of clients that received the latest frame and acknowledged taking it.
+    pub fn consumed_count(&mut self) -> usize {
+        self.accept_pending();
+        self.read_acks();
+        let frame_number = self.frame_number;
+        self.clients
+            .iter()
+            .filter(|peer| frame_number != 0 && peer.acked >= frame_number)
+            .count()
+    }
+
+    /// Block until clients have acknowledged the latest frame as `mode` requires, or `timeout`
+    /// passes. Only clients the frame was sent to count: ones that connected later, or whose
+    /// socket was full, never see it. True at once before the first frame and when no client
+    /// received it.
+    pub fn wait_consumed(&mut self, mode: SyncMode, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.accept_pending();
+            self.read_acks();
+            let frame_number = self.frame_number;
+            let receivers = self.clients.iter().filter(|p| p.sent == frame_number);
+            let taken = receivers
+                .clone()
+                .filter(|p| p.acked >= frame_number)
+                .count();
+            if frame_number == 0 || mode.satisfied(taken, receivers.count()) {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            let mut polls: Vec<libc::pollfd> = self
+                .clients
+                .iter()
+                .map(|peer| libc::pollfd {
+                    fd: peer.socket.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+            let ms = (deadline - now).as_millis().clamp(1, i32::MAX as u128) as i32;
+            unsafe { libc::poll(polls.as_mut_ptr(), polls.len() as libc::nfds_t, ms) };
+        }
+    }
+
+    /// Write `frame` into a sealed memfd and send it to every client. Clients whose socket is
+    /// full miss this frame; disconnected clients are removed. Returns the frame number.
+    pub fn publish(&mut self, frame: &Frame) -> io::Result<u64> {
+        self.accept_pending();
+        self.read_acks();
+        let memfd = sealed_memfd(frame)?;
+        self.frame_number += 1;
+        let header = Header {
+            format: frame.format(),
+            color_space: frame.color_space(),
+            width: frame.width(),
+            height: frame.height(),
+            stride: frame.row_bytes() as u32,
+            frame_number: self.frame_number,
+            data_len: (frame.row_bytes() * frame.height() as usize) as u64,
+        }
+        .encode();
+        let frame_number = self.frame_number;
+        self.clients.retain_mut(|peer| {
+            match send_with_fd(peer.socket.as_raw_fd(), &header, memfd.as_raw_fd()) {
+                Ok(()) => {
+                    peer.sent = frame_number;
+                    true
+                }
+                Err(err) => err.kind() == io::ErrorKind::WouldBlock,
+            }
+        });
+        Ok(self.frame_number)
+    }
+
+    /// Drain acknowledgements from every client, removing ones that disconnected.
+    fn read_acks(&mut self) {
+        self.clients.retain_mut(|peer| loop {
+            let mut buf = [0u8; ACK_LEN];
+            let len = unsafe {
+                libc::recv(
+                    peer.socket.as_raw_fd(),
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                    libc::MSG_DONTWAIT,
+                )
+            };
+            if len == 0 {
+                return false;
+            }
+            if len < 0 {
+                let err = io::Error::last_os_error();
+                match err.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    io::ErrorKind::WouldBlock => return true,
+                    _ => return false,
+                }
+            }
+            if len as usize == ACK_LEN && buf[0..4] == ACK_MAGIC {
+                let frame_number = u64::from_le_bytes(buf[4..12].try_into().unwrap());
+                peer.acked = peer.acked.max(frame_number);
+            }
+        });
+    }
+
+    fn accept_pending(&mut self) {
+        loop {
+            let fd = unsafe {
+                libc::accept4(
+                    self.listener.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                )
+            };
+            if fd < 0 {
+                return;
+            }
+            self.clients.push(Peer {
+                socket: unsafe { OwnedFd::from_raw_fd(fd) },
+                sent: 0,
+                acked: 0,
+            });
+        }
+    }
+}
+
+impl Drop for MemfdServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl FrameSender for MemfdServer {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        self.publish(frame)
+            .map(|_| ())
+            .map_err(|_| PublishError::Backend)
+    }
+}
+
+impl SyncSender for MemfdServer {
+    fn wait_consumed(&mut self, mode: SyncMode, timeout: Duration) -> bool {
+        MemfdServer::wait_consumed(self, mode, timeout)
+    }
+}
+
+/// Receives sealed memfd frames from a `MemfdServer`.
+pub struct MemfdClient {
+    socket: OwnedFd,
+    connected: bool,
+    last_frame_number: u64,
+    /// Number of the first frame the server sent this client; earlier ones were never meant
+    /// for it and don't count as dropped.
+    first_frame_number: Option<u64>,
+    stats: ReceiverStats,
+}
+
+impl MemfdClient {
+    /// Connect to the server bound with `MemfdServer::bind(name)`.
+    pub fn connect(name: &str) -> io::Result<Self> {
+        Self::connect_path(memfd_socket_path(name))
+    }
+
+    pub fn connect_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let socket = connect_socket(path.as_ref())?;
+        set_nonblocking(socket.as_raw_fd())?;
+        Ok(Self {
+            socket,
+            connected: true,
+            last_frame_number: 0,
+            first_frame_number: None,
+            stats: ReceiverStats::default(),
+        })
+    }
+
+    /// Server frame number of the last frame received.
+    pub fn last_frame_number(&self) -> u64 {
+        self.last_frame_number
+    }
+
+    pub fn stats(&self) -> ReceiverStats {
+        self.stats
+    }
+
+    /// The newest frame queued on the socket, if any. Older queued frames are discarded.
+    pub fn try_recv(&mut self) -> Option<Frame> {
+        let (header, memfd) = self.latest_message()?;
+        let frame = read_sealed(&header, memfd)?;
+        self.record(&header);
+        Some(frame)
+    }
+
+    /// Like `try_recv`, reading the memfd straight into a frame from `pool`.
+    pub fn try_recv_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        let (header, memfd) = self.latest_message()?;
+        // Validate before taking a frame, so a bad header never allocates or evicts one.
+        let file = open_sealed_rows(&header, memfd)?;
+        let mut frame = pool.get(header.width, header.height, header.format);
+        read_rows_into(&header, &file, &mut frame)?;
+        frame.set_color_space(header.color_space);
+        self.record(&header);
+        Some(frame)
+    }
+
+    /// Like `try_recv`, mapping the memfd read-only instead of copying it. The seals guarantee
+    /// the server can no longer change the pixels.
+    pub fn lock_frame(&mut self) -> Option<FrameLock<'_>> {
+        let (header, memfd) = self.latest_message()?;
+        let file = open_sealed(&header, memfd)?;
+        let len = usize::try_from(header.data_len).ok()?;
+        let lock = FrameLock::borrowed(
+            header.width,
+            header.height,
+            header.stride as usize,
+            header.format,
+            header.color_space,
+            Mapping::new(&file, len)?,
+        )?;
+        self.record(&header);
+        Some(lock)
+    }
+
+    fn latest_message(&mut self) -> Option<(Header, OwnedFd)> {
+        let mut latest = None;
+        while let Some(message) = self.recv_message() {
+            latest = Some(message);
+        }
+        latest
+    }
+
+    /// Count a frame taken and acknowledge it.
+    fn record(&mut self, header: &Header) {
+        let first = self.first_frame_number.unwrap_or(header.frame_number);
+        let previous = self.last_frame_number.max(first.saturating_sub(1));
+        let skipped = header.frame_number.saturating_sub(previous + 1);
+        self.stats.record_frame(skipped);
+        self.last_frame_number = header.frame_number;
+        self.acknowledge(header.frame_number);
+    }
+
+    /// Tell the server this client took `frame_number`. Best effort: a server that is not
+    /// reading acknowledgements just leaves them queued until the socket is full.
+    fn acknowledge(&self, frame_number: u64) {
+        let mut ack = [0u8; ACK_LEN];
+        ack[0..4].copy_from_slice(&ACK_MAGIC);
+        ack[4..12].copy_from_slice(&frame_number.to_le_bytes());
+        unsafe {
+            libc::send(
+                self.socket.as_raw_fd(),
+                ack.as_ptr().cast(),
+                ack.len(),
+                libc::MSG_NOSIGNAL | libc::MSG_DONTWAIT,
+            )
+        };
+    }
+
+    /// Wait up to `timeout` for a frame.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Frame> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(frame) = self.try_recv() {
+                return Some(frame);
+            }
+            let now = Instant::now();
+            if now >= deadline || !self.connected {
+                return None;
+            }
+            let mut poll = libc::pollfd {
+                fd: self.socket.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ms = (deadline - now).as_millis().clamp(1, i32::MAX as u128) as i32;
+            unsafe { libc::poll(&mut poll, 1, ms) };
+        }
+    }
+
+    /// One queued message with its memfd. Malformed messages are skipped.
+    fn recv_message(&mut self) -> Option<(Header, OwnedFd)> {
+        loop {
+            let mut buf = [0u8; HEADER_LEN];
+            match recv_with_fd(self.socket.as_raw_fd(), &mut buf) {
+                Ok((0, _)) => {
+                    self.connected = false;
+                    return None;
+                }
+                Ok((len, Some(fd))) => {
+                    if let Some(header) = Header::decode(&buf[..len]) {
+                        self.first_frame_number.get_or_insert(header.frame_number);
+                        return Some((header, fd));
+                    }
+                }
+                Ok((_, None)) => {}
+                // Truncated packets are malformed too.
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::Interrupted | io::ErrorKind::InvalidData
+                    ) => {}
+                Err(err) => {
+                    if err.kind() != io::ErrorKind::WouldBlock {
+                        self.connected = false;
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl FrameReceiver for MemfdClient {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        self.try_recv()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn stats(&self) -> Option<ReceiverStats> {
+        Some(self.stats)
+    }
+}
+
+impl LockingReceiver for MemfdClient {
+    fn lock_frame(&mut self) -> Option<FrameLock<'_>> {
+        MemfdClient::lock_frame(self)
+    }
+}
+
+impl PooledReceiver for MemfdClient {
+    fn receive_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        self.try_recv_pooled(pool)
+    }
+}
+
+impl SyncReceiver for MemfdClient {
+    fn wait_frame(&mut self, timeout: Duration) -> Option<Frame> {
+        self.recv_timeout(timeout)
+    }
+}
+
+/// Copy `frame`'s pixels (packed) into a new memfd and seal it.
+fn sealed_memfd(frame: &Frame) -> io::Result<OwnedFd> {
+    let name = CString::new("rusty-syphon-spout-frame").unwrap();
+    let fd =
+        unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.set_len((frame.row_bytes() * frame.height() as usize) as u64)?;
+    if frame.is_packed() {
+        file.write_all(&frame.data()[..frame.row_bytes() * frame.height() as usize])?;
+    } else {
+        for y in 0..frame.height() {
+            file.write_all(frame.row(y))?;
+        }
+    }
+    let seals = REQUIRED_SEALS | libc::F_SEAL_SEAL;
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(OwnedFd::from(file))
+}
+
+/// The memfd as a file, after checking it is sealed and the size the header claims.
+fn open_sealed(header: &Header, memfd: OwnedFd) -> Option<File> {
+    let seals = unsafe { libc::fcntl(memfd.as_raw_fd(), libc::F_GET_SEALS) };
+    if seals < 0 || seals & REQUIRED_SEALS != REQUIRED_SEALS {
+        return None;
+    }
+    let file = File::from(memfd);
+    if file.metadata().ok()?.len() != header.data_len {
+        return None;
+    }
+    Some(file)
+}
+
+/// Read a frame from a memfd after checking it is sealed and the size the header claims.
+fn read_sealed(header: &Header, memfd: OwnedFd) -> Option<Frame> {
+    let file = open_sealed(header, memfd)?;
+    let len = usize::try_from(header.data_len).ok()?;
+    let mut data = vec![0u8; len];
+    file.read_exact_at(&mut data, 0).ok()?;
+    let frame = Frame::from_vec_with_stride(
+        header.width,
+        header.height,
+        header.stride as usize,
+        header.format,
+        data,
+    )?;
+    Some(frame.with_color_space(header.color_space))
+}
+
+/// `open_sealed`, also checking that the header's rows fit in the memfd.
+fn open_sealed_rows(header: &Header, memfd: OwnedFd) -> Option<File> {
+    let row_bytes = (header.width as usize).checked_mul(header.format.bytes_per_pixel())?;
+    let stride = header.stride as usize;
+    let needed = match (header.height as usize).checked_sub(1) {
+        Some(last) => stride.checked_mul(last)?.checked_add(row_bytes)?,
+        None => 0,
+    };
+    if stride < row_bytes || needed as u64 > header.data_len {
+        return None;
+    }
+    open_sealed(header, memfd)
+}
+
+/// Read the rows of a file from `open_sealed_rows` into `frame`, which has the header's size
+/// and format.
+fn read_rows_into(header: &Header, file: &File, frame: &mut Frame) -> Option<()> {
+    let stride = header.stride as usize;
+    if stride == frame.stride() {
+        let len = frame.row_bytes() * header.height as usize;
+        file.read_exact_at(&mut frame.data_mut()[..len], 0).ok()?;
+    } else {
+        for y in 0..header.height {
+            let offset = y as u64 * stride as u64;
+            file.read_exact_at(frame.row_mut(y), offset).ok()?;
+        }
+    }
+    Some(())
+}
+
+/// A read-only shared mapping of a sealed memfd, unmapped on drop.
+struct Mapping {
+    ptr: *const u8,
+    len: usize,
+}
+
+// The mapping is read-only and its memfd is sealed against writes.
+unsafe impl Send for Mapping {}
+
+impl Mapping {
+    fn new(file: &File, len: usize) -> Option<Self> {
+        if len == 0 {
+            return Some(Self {
+                ptr: std::ptr::NonNull::dangling().as_ptr(),
+                len,
+            });
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        Some(Self {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+}
+
+impl AsRef<[u8]> for Mapping {
+    fn as_ref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
+        }
+    }
+}
+
+fn seqpacket_socket() -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn connect_socket(path: &Path) -> io::Result<OwnedFd> {
+    let socket = seqpacket_socket()?;
+    let addr = sockaddr(path)?;
+    let rc = unsafe {
+        libc::connect(
+            socket.as_raw_fd(),
+            &addr as *const _ as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_un>() as libc::socklen_t,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+fn sockaddr(path: &Path) -> io::Result<libc::sockaddr_un> {
+    use std::os::unix::ffi::OsStrExt;
+    let bytes = path.as_os_str().as_bytes();
+    let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+    if bytes.len() >= addr.sun_path.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "socket path too long",
+        ));
+    }
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (dst, src) in addr.sun_path.iter_mut().zip(bytes) {
+        *dst = *src as libc::c_char;
+    }
+    Ok(addr)
+}
+
+fn set_nonblocking(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Space for one fd in a control message, as `u64`s so the buffer is suitably aligned.
+const CMSG_WORDS: usize = 4;
+
+fn send_with_fd(socket: RawFd, payload: &[u8], fd: RawFd) -> io::Result<()> {
+    let mut iov = libc::iovec {
+        iov_base: payload.as_ptr() as *mut libc::c_void,
+        iov_len: payload.len(),
+    };
+    let mut control = [0u64; CMSG_WORDS];
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) as _;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+        if libc::sendmsg(socket, &msg, libc::MSG_NOSIGNAL | libc::MSG_DONTWAIT) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Receive one packet; returns its length and the first fd passed with it, if any. Any other fds
+/// are closed. Packets longer than `buf` fail with `InvalidData`.
+fn recv_with_fd(socket: RawFd, buf: &mut [u8]) -> io::Result<(usize, Option<OwnedFd>)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    let mut control = [0u64; CMSG_WORDS];
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+        let len = libc::recvmsg(
+            socket,
+            &mut msg,
+            libc::MSG_DONTWAIT | libc::MSG_CMSG_CLOEXEC,
+        );
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut fd = None;
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                // Every fd received is now ours: keep the first, close the rest.
+                let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+                let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                    / mem::size_of::<RawFd>();
+                for i in 0..count {
+                    let received = OwnedFd::from_raw_fd(std::ptr::read_unaligned(data.add(i)));
+                    if fd.is_none() {
+                        fd = Some(received);
+                    }
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+        if msg.msg_flags & libc::MSG_TRUNC != 0 {
+            // Longer than any packet we understand; its fd (if any) closes here.
+            drop(fd);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "packet longer than the buffer",
+            ));
+        }
+        Ok((len as usize, fd))
+    }
+}
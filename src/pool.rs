@@ -0,0 +1,246 @@
+//! Reusable frame buffers for receive loops.
+//!
+//! Receiving into a new `Frame` allocates a whole image per call. A `FramePool` keeps frames
+//! that are no longer in use, keyed by size and format, and hands them out again. Frames come
+//! out as `PooledFrame` guards that go back to the pool when dropped, so once the pool has
+//! warmed up a loop that receives, processes and drops each frame allocates nothing.
+//! `PooledReceiver::receive_pooled` receives straight into pooled frames.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use crate::color::ColorSpace;
+use crate::frame::{Frame, FrameReceiver, PixelFormat};
+
+/// Limits on what a `FramePool` keeps while frames are not in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramePoolConfig {
+    /// Idle frames kept per size and format. Frames returned beyond this are freed.
+    pub depth: usize,
+    /// Upper bound on the bytes held by idle frames across all sizes.
+    pub max_bytes: usize,
+}
+
+impl Default for FramePoolConfig {
+    fn default() -> Self {
+        Self {
+            depth: 3,
+            max_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// What a `FramePool` has done so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FramePoolStats {
+    /// Frames handed out that had to be allocated.
+    pub allocations: u64,
+    /// Frames handed out from the idle list.
+    pub reuses: u64,
+    /// Returned frames freed because of `depth` or `max_bytes`, or because they were not
+    /// tightly packed.
+    pub discarded: u64,
+    /// Frames idle in the pool right now.
+    pub idle_frames: usize,
+    /// Bytes held by idle frames right now.
+    pub idle_bytes: usize,
+}
+
+type PoolKey = (u32, u32, PixelFormat);
+
+struct PoolState {
+    config: FramePoolConfig,
+    idle: HashMap<PoolKey, Vec<Frame>>,
+    stats: FramePoolStats,
+}
+
+impl PoolState {
+    fn take(&mut self, key: PoolKey) -> Option<Frame> {
+        let mut frame = self.idle.get_mut(&key)?.pop()?;
+        self.stats.reuses += 1;
+        self.stats.idle_frames -= 1;
+        self.stats.idle_bytes -= frame.data().len();
+        frame.set_color_space(ColorSpace::default());
+        Some(frame)
+    }
+
+    fn put(&mut self, frame: Frame) {
+        let bytes = frame.data().len();
+        let key = (frame.width(), frame.height(), frame.format());
+        let idle = self.idle.get(&key).map_or(0, Vec::len);
+        let fits = frame.is_packed()
+            && self.stats.idle_bytes + bytes <= self.config.max_bytes
+            && idle < self.config.depth;
+        if !fits {
+            self.stats.discarded += 1;
+            return;
+        }
+        // Only kept frames get an entry, so discarded sizes don't grow the map.
+        self.idle.entry(key).or_default().push(frame);
+        self.stats.idle_frames += 1;
+        self.stats.idle_bytes += bytes;
+    }
+
+    /// Free idle frames until the limits hold again.
+    fn trim(&mut self) {
+        let FramePoolConfig { depth, max_bytes } = self.config;
+        for list in self.idle.values_mut() {
+            while list.len() > depth || self.stats.idle_bytes > max_bytes {
+                let Some(frame) = list.pop() else { break };
+                self.stats.idle_frames -= 1;
+                self.stats.idle_bytes -= frame.data().len();
+                self.stats.discarded += 1;
+            }
+        }
+        self.idle.retain(|_, list| !list.is_empty());
+    }
+}
+
+/// Frames to reuse, keyed by size and format. Clones share the same pool. See the module docs.
+#[derive(Clone)]
+pub struct FramePool {
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl Default for FramePool {
+    fn default() -> Self {
+        Self::new(FramePoolConfig::default())
+    }
+}
+
+impl FramePool {
+    pub fn new(config: FramePoolConfig) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PoolState {
+                config,
+                idle: HashMap::new(),
+                stats: FramePoolStats::default(),
+            })),
+        }
+    }
+
+    pub fn config(&self) -> FramePoolConfig {
+        self.state.lock().unwrap().config
+    }
+
+    /// Change the limits, freeing idle frames beyond the new ones.
+    pub fn set_config(&self, config: FramePoolConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.config = config;
+        state.trim();
+    }
+
+    pub fn stats(&self) -> FramePoolStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// A tightly packed frame of this size and format, reused if one is idle. A reused frame
+    /// still holds the pixels it had (tagged `ColorSpace::Srgb` again); callers are expected to
+    /// overwrite them all.
+    pub fn get(&self, width: u32, height: u32, format: PixelFormat) -> PooledFrame {
+        let reused = {
+            let mut state = self.state.lock().unwrap();
+            let reused = state.take((width, height, format));
+            if reused.is_none() {
+                state.stats.allocations += 1;
+            }
+            reused
+        };
+        self.adopt(reused.unwrap_or_else(|| Frame::new(width, height, format)))
+    }
+
+    /// A pooled copy of `frame`, packed, with its color space.
+    pub fn copy_of(&self, frame: &Frame) -> PooledFrame {
+        let mut out = self.get(frame.width(), frame.height(), frame.format());
+        for y in 0..frame.height() {
+            out.row_mut(y).copy_from_slice(frame.row(y));
+        }
+        out.set_color_space(frame.color_space());
+        out
+    }
+
+    /// Wrap `frame` so it goes to this pool when dropped.
+    pub fn adopt(&self, frame: Frame) -> PooledFrame {
+        PooledFrame {
+            frame: Some(frame),
+            pool: self.clone(),
+        }
+    }
+
+    /// Free every idle frame.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.idle.clear();
+        state.stats.idle_frames = 0;
+        state.stats.idle_bytes = 0;
+    }
+}
+
+/// A frame borrowed from a `FramePool`; derefs to `Frame` and goes back to the pool on drop.
+pub struct PooledFrame {
+    /// `None` only once `into_frame` has taken it.
+    frame: Option<Frame>,
+    pool: FramePool,
+}
+
+impl PooledFrame {
+    /// Keep the frame instead of returning it to the pool.
+    pub fn into_frame(mut self) -> Frame {
+        self.frame.take().unwrap()
+    }
+
+    pub fn pool(&self) -> &FramePool {
+        &self.pool
+    }
+}
+
+impl Deref for PooledFrame {
+    type Target = Frame;
+
+    fn deref(&self) -> &Frame {
+        self.frame.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledFrame {
+    fn deref_mut(&mut self) -> &mut Frame {
+        self.frame.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledFrame {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            self.pool.state.lock().unwrap().put(frame);
+        }
+    }
+}
+
+impl fmt::Debug for PooledFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PooledFrame").field(&**self).finish()
+    }
+}
+
+impl PartialEq<Frame> for PooledFrame {
+    fn eq(&self, other: &Frame) -> bool {
+        **self == *other
+    }
+}
+
+/// A `FrameReceiver` that can receive into frames from a `FramePool`.
+pub trait PooledReceiver: FrameReceiver {
+    /// Like `receive_frame`, into a frame from `pool`. The default receives a new frame and
+    /// adopts it into the pool, so only receivers that override this avoid the allocation.
+    fn receive_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        self.receive_frame().map(|frame| pool.adopt(frame))
+    }
+}
+
+impl<R: PooledReceiver + ?Sized> PooledReceiver for Box<R> {
+    fn receive_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        (**self).receive_pooled(pool)
+    }
+}
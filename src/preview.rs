@@ -0,0 +1,431 @@
+//! Browser previews of discovered sources over HTTP.
+//!
+//! `PreviewServer` is a small embedded HTTP/1.1 server on plain `std::net`:
+//!
+//! - `/` — HTML index of `discover_sources`, with a snapshot thumbnail and links for each source;
+//! - `/stream/<id>` — MJPEG (`multipart/x-mixed-replace`) stream of the source;
+//! - `/snapshot/<id>` — the source's next frame as a single JPEG.
+//!
+//! `<id>` is `SourceInfo::id`, percent-encoded. Every stream is limited to `max_fps` and frames
+//! are scaled down to fit `max_size` before encoding, and at most `max_clients` requests are
+//! served at once, so an open browser tab can't starve the host.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use jpeg_encoder::{ColorType, Encoder};
+
+use crate::discovery::{connect_source, discover_sources, find_source, SourceInfo};
+use crate::frame::{Frame, FrameReceiver, PixelFormat};
+use crate::geometry::Size;
+use crate::resample::{ResampleFilter, Resampler};
+
+const BOUNDARY: &str = "rusty-syphon-spout-frame";
+const MAX_REQUEST_HEADER: usize = 8 * 1024;
+/// Bounds on `PreviewConfig::max_fps`, so an infinite or tiny rate can't busy-loop or stall.
+const FPS_RANGE: (f32, f32) = (0.1, 120.0);
+
+/// Limits for a `PreviewServer`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreviewConfig {
+    /// Highest frame rate sent on each stream, clamped to 0.1-120.
+    pub max_fps: f32,
+    /// Frames larger than this are scaled down (keeping their aspect ratio) before encoding.
+    pub max_size: Size,
+    /// JPEG quality, 1-100.
+    pub quality: u8,
+    /// Requests served at once; more get `503 Service Unavailable`.
+    pub max_clients: usize,
+    /// How long a snapshot waits for a frame before answering `504 Gateway Timeout`.
+    pub snapshot_timeout: Duration,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            max_fps: 10.0,
+            max_size: Size::new(1280, 720),
+            quality: 75,
+            max_clients: 8,
+            snapshot_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Serves the HTML index, MJPEG streams and snapshots. Stops and closes all streams on drop.
+pub struct PreviewServer {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl PreviewServer {
+    /// Listen on `addr` (e.g. `"0.0.0.0:8080"`, or port 0 for any free port).
+    pub fn bind(addr: impl ToSocketAddrs, config: PreviewConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let accept_thread = {
+            let shutdown = shutdown.clone();
+            std::thread::Builder::new()
+                .name("preview-accept".to_string())
+                .spawn(move || accept_loop(listener, config, shutdown))?
+        };
+        Ok(Self {
+            addr,
+            shutdown,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// `http://` URL of the index page.
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+}
+
+impl Drop for PreviewServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake the blocking accept so the thread sees the shutdown flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.accept_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Decrements the active request count when a connection thread ends.
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn accept_loop(listener: TcpListener, config: PreviewConfig, shutdown: Arc<AtomicBool>) {
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(mut stream) = stream else { continue };
+        if active.fetch_add(1, Ordering::SeqCst) >= config.max_clients {
+            active.fetch_sub(1, Ordering::SeqCst);
+            // Read the request first: closing with it unread resets the connection, and the
+            // client may never see the response. The short timeout bounds the accept stall.
+            let _ = stream.set_read_timeout(Some(Duration::from_millis(100)));
+            let _ = read_request(&stream);
+            let _ = respond(
+                &mut stream,
+                "503 Service Unavailable",
+                "text/plain",
+                b"too many clients\n",
+            );
+            continue;
+        }
+        let slot = ClientSlot(active.clone());
+        let shutdown = shutdown.clone();
+        let _ = std::thread::Builder::new()
+            .name("preview-client".to_string())
+            .spawn(move || {
+                let _slot = slot;
+                let _ = handle_connection(stream, &config, &shutdown);
+            });
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    config: &PreviewConfig,
+    shutdown: &AtomicBool,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let Some((method, path)) = read_request(&stream)? else {
+        return respond(
+            &mut stream,
+            "400 Bad Request",
+            "text/plain",
+            b"bad request\n",
+        );
+    };
+    if method != "GET" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"GET only\n",
+        );
+    }
+    let path = path.split('?').next().unwrap_or_default();
+    if path == "/" || path == "/index.html" {
+        let html = index_html(&discover_sources());
+        return respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            html.as_bytes(),
+        );
+    }
+    let (kind, id) = match path.strip_prefix("/stream/") {
+        Some(id) => ("stream", id),
+        None => match path.strip_prefix("/snapshot/") {
+            Some(id) => ("snapshot", id),
+            None => return respond(&mut stream, "404 Not Found", "text/plain", b"not found\n"),
+        },
+    };
+    let Some(mut receiver) = percent_decode(id)
+        .and_then(|id| find_source(&id))
+        .and_then(|source| connect_source(&source))
+    else {
+        return respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain",
+            b"no such source\n",
+        );
+    };
+    if kind == "snapshot" {
+        match next_frame(&mut *receiver, config.snapshot_timeout, shutdown) {
+            Some(frame) => {
+                let jpeg = encode_jpeg(&frame, config)?;
+                respond(&mut stream, "200 OK", "image/jpeg", &jpeg)
+            }
+            None => respond(
+                &mut stream,
+                "504 Gateway Timeout",
+                "text/plain",
+                b"no frame\n",
+            ),
+        }
+    } else {
+        stream_mjpeg(stream, &mut *receiver, config, shutdown)
+    }
+}
+
+/// Method and path of the request; headers are read and ignored.
+fn read_request(stream: &TcpStream) -> io::Result<Option<(String, String)>> {
+    let mut reader = BufReader::new(stream).take(MAX_REQUEST_HEADER as u64);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None); // header too long or connection closed
+        }
+        if line == "\r\n" || line == "\n" {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(path), Some(version)) if version.starts_with("HTTP/1.") => {
+            Ok(Some((method.to_string(), path.to_string())))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    let _ = stream.shutdown(Shutdown::Write);
+    Ok(())
+}
+
+fn stream_mjpeg(
+    mut stream: TcpStream,
+    receiver: &mut dyn FrameReceiver,
+    config: &PreviewConfig,
+    shutdown: &AtomicBool,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={BOUNDARY}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n"
+    )?;
+    let (min_fps, max_fps) = FPS_RANGE;
+    // `clamp` passes NaN through; `max` does not.
+    let fps = config.max_fps.max(min_fps).min(max_fps);
+    let interval = Duration::from_secs_f32(1.0 / fps);
+    let mut next_due = Instant::now();
+    while !shutdown.load(Ordering::SeqCst) && receiver.is_connected() {
+        let now = Instant::now();
+        if now < next_due {
+            std::thread::sleep(next_due - now);
+        }
+        // Poll up to one interval for a new frame, then check the connection again.
+        let Some(frame) = next_frame(receiver, interval, shutdown) else {
+            continue;
+        };
+        next_due = Instant::now() + interval;
+        let jpeg = encode_jpeg(&frame, config)?;
+        write!(
+            stream,
+            "--{BOUNDARY}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            jpeg.len()
+        )?;
+        stream.write_all(&jpeg)?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+    }
+    Ok(())
+}
+
+/// Poll `receiver` for a new frame for up to `timeout`.
+fn next_frame(
+    receiver: &mut dyn FrameReceiver,
+    timeout: Duration,
+    shutdown: &AtomicBool,
+) -> Option<Frame> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(frame) = receiver.receive_frame() {
+            return Some(frame);
+        }
+        if Instant::now() >= deadline || shutdown.load(Ordering::SeqCst) || !receiver.is_connected()
+        {
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// Largest size with `size`'s aspect ratio that fits in `max`, never scaling up.
+fn fit_within(size: Size, max: Size) -> Size {
+    if size.width <= max.width && size.height <= max.height {
+        return size;
+    }
+    let scale = (max.width as f64 / size.width as f64).min(max.height as f64 / size.height as f64);
+    Size::new(
+        ((size.width as f64 * scale).round() as u32).max(1),
+        ((size.height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+fn encode_jpeg(frame: &Frame, config: &PreviewConfig) -> io::Result<Vec<u8>> {
+    // JPEG dimensions are 16-bit.
+    let max = Size::new(
+        config.max_size.width.clamp(1, u16::MAX as u32),
+        config.max_size.height.clamp(1, u16::MAX as u32),
+    );
+    let target = fit_within(frame.size(), max);
+    let scaled;
+    let frame = if target == frame.size() {
+        frame
+    } else {
+        scaled = Resampler::new(ResampleFilter::Bilinear).resample(frame, target);
+        &scaled
+    };
+    let mut packed = Vec::with_capacity(frame.row_bytes() * frame.height() as usize);
+    for y in 0..frame.height() {
+        packed.extend_from_slice(frame.row(y));
+    }
+    let color_type = match frame.format() {
+        PixelFormat::Rgba8 => ColorType::Rgba,
+        PixelFormat::Bgra8 => ColorType::Bgra,
+    };
+    let mut jpeg = Vec::new();
+    Encoder::new(&mut jpeg, config.quality.clamp(1, 100))
+        .encode(
+            &packed,
+            frame.width() as u16,
+            frame.height() as u16,
+            color_type,
+        )
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    Ok(jpeg)
+}
+
+fn index_html(sources: &[SourceInfo]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>Sources</title>\
+         <style>body{font-family:sans-serif;margin:1em}li{margin-bottom:1.5em}\
+         img{max-width:100%;display:block;background:#222}</style>\
+         </head><body>\n<h1>Sources</h1>\n",
+    );
+    if sources.is_empty() {
+        html.push_str("<p>No sources found.</p>\n");
+    } else {
+        html.push_str("<ul>\n");
+        for source in sources {
+            let id = percent_encode(&source.id());
+            let size = source
+                .size
+                .map(|s| format!(", {}x{}", s.width, s.height))
+                .unwrap_or_default();
+            html.push_str(&format!(
+                "<li><strong>{name}</strong> <small>{backend}{size}</small><br>\
+                 <a href=\"/stream/{id}\">stream</a> | <a href=\"/snapshot/{id}\">snapshot</a>\
+                 <img src=\"/snapshot/{id}\" alt=\"{name}\" loading=\"lazy\"></li>\n",
+                name = escape_html(&source.display_name()),
+                backend = source.backend.name(),
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Percent-encode everything except unreserved characters and `:`.
+fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~:".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut iter = text.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,281 @@
+//! Python module `rusty_syphon_spout` (feature `python`).
+//!
+//! ```python
+//! import rusty_syphon_spout as rss
+//! for source in rss.discover():
+//!     print(source.id, source.name, source.app_name)
+//! receiver = rss.Receiver("Camera")       # id or name
+//! frame = receiver.wait(1.0)              # numpy uint8 array (height, width, 4), or None
+//! rss.Sender("From Python").send(frame, receiver.format)
+//! ```
+//!
+//! Frames are `numpy.uint8` arrays of shape `(height, width, 4)`; the channel order is the
+//! pixel format `"rgba"` or `"bgra"`. `Receiver.wait` releases the GIL while it waits, and
+//! `discover` while it queries the backends. Receivers and senders stay on the Python thread
+//! that created them. Built as a `cdylib` (e.g. with maturin), the library is importable as the
+//! extension module; embedders register `python_module` with `pyo3::append_to_inittab!`.
+
+use std::time::{Duration, Instant};
+
+use numpy::ndarray::Array3;
+use numpy::{IntoPyArray, PyArray3, PyReadonlyArray3};
+use pyo3::exceptions::{PyLookupError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::discovery::{
+    connect_source, create_sender, discover_sources, SourceBackend, SourceInfo,
+};
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::PublishRequest;
+
+fn parse_format(name: &str) -> PyResult<PixelFormat> {
+    match name {
+        "rgba" => Ok(PixelFormat::Rgba8),
+        "bgra" => Ok(PixelFormat::Bgra8),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown pixel format '{name}' (expected 'rgba' or 'bgra')"
+        ))),
+    }
+}
+
+fn format_name(format: PixelFormat) -> &'static str {
+    match format {
+        PixelFormat::Rgba8 => "rgba",
+        PixelFormat::Bgra8 => "bgra",
+    }
+}
+
+/// Copy `frame` into a new `(height, width, 4)` array, in `format` if given.
+fn frame_to_array(
+    py: Python<'_>,
+    frame: Frame,
+    format: Option<PixelFormat>,
+) -> PyResult<Bound<'_, PyArray3<u8>>> {
+    let frame = match format {
+        Some(format) if format != frame.format() => frame.to_format(format),
+        _ => frame,
+    };
+    let data = frame
+        .extract(&PublishRequest::new(frame.size()))
+        .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
+        .into_owned();
+    let shape = (frame.height() as usize, frame.width() as usize, 4);
+    let array = Array3::from_shape_vec(shape, data)
+        .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+    Ok(array.into_pyarray(py))
+}
+
+/// A discovered source. `width`, `height` and `format` are None when the backend does not
+/// report them.
+#[pyclass(name = "Source", module = "rusty_syphon_spout", frozen, get_all)]
+struct PySource {
+    backend: &'static str,
+    id: String,
+    uuid: String,
+    name: String,
+    app_name: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    format: Option<&'static str>,
+}
+
+impl From<&SourceInfo> for PySource {
+    fn from(info: &SourceInfo) -> Self {
+        Self {
+            backend: info.backend.name(),
+            id: info.id(),
+            uuid: info.uuid.clone(),
+            name: info.name.clone(),
+            app_name: info.app_name.clone(),
+            width: info.size.map(|s| s.width),
+            height: info.size.map(|s| s.height),
+            format: info.format.map(format_name),
+        }
+    }
+}
+
+#[pymethods]
+impl PySource {
+    fn __repr__(&self) -> String {
+        format!(
+            "Source(id={:?}, name={:?}, app_name={:?})",
+            self.id, self.name, self.app_name
+        )
+    }
+}
+
+/// All sources currently visible: local servers, Syphon servers and Spout senders.
+#[pyfunction]
+fn discover(py: Python<'_>) -> Vec<PySource> {
+    py.detach(discover_sources)
+        .iter()
+        .map(PySource::from)
+        .collect()
+}
+
+/// Receives frames from a source, given its id or name (first match).
+#[pyclass(name = "Receiver", module = "rusty_syphon_spout", unsendable)]
+struct PyReceiver {
+    receiver: Box<dyn FrameReceiver>,
+    source: SourceInfo,
+    format: Option<PixelFormat>,
+}
+
+#[pymethods]
+impl PyReceiver {
+    #[new]
+    fn new(py: Python<'_>, source: &str) -> PyResult<Self> {
+        let sources = py.detach(discover_sources);
+        let info = sources
+            .iter()
+            .find(|s| s.id() == source)
+            .or_else(|| sources.iter().find(|s| s.name == source))
+            .cloned()
+            .ok_or_else(|| PyLookupError::new_err(format!("no source '{source}'")))?;
+        let receiver = connect_source(&info).ok_or_else(|| {
+            PyRuntimeError::new_err(format!(
+                "cannot connect to '{}' on the {} backend",
+                info.name,
+                info.backend.name()
+            ))
+        })?;
+        Ok(Self {
+            receiver,
+            source: info,
+            format: None,
+        })
+    }
+
+    /// The source this receiver connected to.
+    #[getter]
+    fn source(&self) -> PySource {
+        PySource::from(&self.source)
+    }
+
+    /// True while the source is still available.
+    #[getter]
+    fn connected(&self) -> bool {
+        self.receiver.is_connected()
+    }
+
+    /// Pixel format of the last frame received, before any conversion; None before the first.
+    #[getter]
+    fn format(&self) -> Option<&'static str> {
+        self.format.map(format_name)
+    }
+
+    /// The new frame since the last call, or None. `format` ("rgba" or "bgra") converts it.
+    #[pyo3(signature = (format=None))]
+    fn receive<'py>(
+        &mut self,
+        py: Python<'py>,
+        format: Option<&str>,
+    ) -> PyResult<Option<Bound<'py, PyArray3<u8>>>> {
+        let format = format.map(parse_format).transpose()?;
+        match self.receiver.receive_frame() {
+            Some(frame) => {
+                self.format = Some(frame.format());
+                frame_to_array(py, frame, format).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Wait up to `timeout` seconds for a new frame, without holding the GIL. Returns None on
+    /// timeout or when the source goes away.
+    #[pyo3(signature = (timeout, format=None))]
+    fn wait<'py>(
+        &mut self,
+        py: Python<'py>,
+        timeout: f64,
+        format: Option<&str>,
+    ) -> PyResult<Option<Bound<'py, PyArray3<u8>>>> {
+        let timeout = Duration::try_from_secs_f64(timeout)
+            .map_err(|_| PyValueError::new_err("timeout must be a non-negative number"))?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(frame) = self.receive(py, format)? {
+                return Ok(Some(frame));
+            }
+            let now = Instant::now();
+            if now >= deadline || !self.receiver.is_connected() {
+                return Ok(None);
+            }
+            let pause = (deadline - now).min(Duration::from_millis(2));
+            py.detach(|| std::thread::sleep(pause));
+            py.check_signals()?;
+        }
+    }
+}
+
+/// Publishes frames as `name` on `backend` ("local", "syphon" or "spout").
+#[pyclass(name = "Sender", module = "rusty_syphon_spout", unsendable)]
+struct PySender {
+    sender: Box<dyn FrameSender>,
+    name: String,
+    backend: SourceBackend,
+}
+
+#[pymethods]
+impl PySender {
+    #[new]
+    #[pyo3(signature = (name, backend="local"))]
+    fn new(name: &str, backend: &str) -> PyResult<Self> {
+        let backend = SourceBackend::from_name(backend)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown backend '{backend}'")))?;
+        let sender = create_sender(backend, name).ok_or_else(|| {
+            PyRuntimeError::new_err(format!(
+                "the {} backend is not available here",
+                backend.name()
+            ))
+        })?;
+        Ok(Self {
+            sender,
+            name: name.to_string(),
+            backend,
+        })
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[getter]
+    fn backend(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Publish a uint8 array of shape (height, width, 4) in `format` ("rgba" or "bgra").
+    #[pyo3(signature = (array, format="rgba"))]
+    fn send(&mut self, array: PyReadonlyArray3<'_, u8>, format: &str) -> PyResult<()> {
+        let format = parse_format(format)?;
+        let view = array.as_array();
+        let (height, width, channels) = view.dim();
+        if channels != 4 {
+            return Err(PyValueError::new_err(format!(
+                "expected shape (height, width, 4), got ({height}, {width}, {channels})"
+            )));
+        }
+        let data = match view.as_slice() {
+            Some(slice) => slice.to_vec(),
+            None => view.iter().copied().collect(),
+        };
+        let frame = Frame::from_vec(width as u32, height as u32, format, data)
+            .ok_or_else(|| PyValueError::new_err("array too large"))?;
+        self.sender
+            .send_frame(&frame)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+}
+
+/// The `rusty_syphon_spout` Python module.
+#[pymodule]
+#[pyo3(name = "rusty_syphon_spout")]
+pub fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(discover, m)?)?;
+    m.add_class::<PySource>()?;
+    m.add_class::<PyReceiver>()?;
+    m.add_class::<PySender>()?;
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! A receiver that survives its sender restarting.
+//!
+//! `ReconnectingReceiver` resolves a `SourceSelector` and connects to whatever it finds. When
+//! the connection goes (`OpenGLClient::is_valid`, `Spout::receiver_is_connected` and so on turn
+//! false) it drops the receiver and resolves the selector again on each `receive_frame`, waiting
+//! longer after each failed attempt. State changes are queued as `ReconnectEvent`s. With
+//! `ReconnectConfig::keep_last_frame`, a copy of the last frame received stays available through
+//! `last_frame` while there is no source.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::discovery::{connect_source, SourceInfo};
+use crate::frame::{Frame, FrameReceiver};
+use crate::selector::{SelectorRule, SourceSelector};
+use crate::stats::ReceiverStats;
+
+/// Retry timing for `ReconnectingReceiver`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectConfig {
+    /// Wait after the first failed attempt.
+    pub initial_delay: Duration,
+    /// Upper bound on the wait between attempts.
+    pub max_delay: Duration,
+    /// Each failed attempt multiplies the wait by this.
+    pub multiplier: f32,
+    /// How long a new connection may report `is_connected() == false` before it counts as lost.
+    /// Spout receivers only report connected after their first receive.
+    pub connect_timeout: Duration,
+    /// Keep a copy of each frame received for `ReconnectingReceiver::last_frame`. Off by
+    /// default, since it copies every frame.
+    pub keep_last_frame: bool,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            connect_timeout: Duration::from_secs(1),
+            keep_last_frame: false,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Wait after the `attempt`th failed attempt in a row (1-based).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        if self.initial_delay.is_zero() {
+            return Duration::ZERO;
+        }
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let factor = (self.multiplier.max(1.0) as f64).powi(exponent);
+        // Clamp before converting: the unclamped product overflows `Duration` after enough
+        // attempts.
+        let secs = (self.initial_delay.as_secs_f64() * factor).min(self.max_delay.as_secs_f64());
+        Duration::from_secs_f64(secs)
+    }
+}
+
+/// Where a `ReconnectingReceiver` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// Never connected yet.
+    Connecting,
+    Connected,
+    /// Lost the source and looking for it again.
+    Reconnecting,
+}
+
+/// Something that happened to a `ReconnectingReceiver`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectEvent {
+    /// Connected to `source`, found by `rule`.
+    Connected {
+        source: SourceInfo,
+        rule: SelectorRule,
+    },
+    /// The connection to `source` went away.
+    Lost { source: SourceInfo },
+    /// Attempt number `attempt` found nothing to connect to, or its connection never reported
+    /// connected; the next is in `delay`.
+    RetryScheduled { attempt: u32, delay: Duration },
+}
+
+/// Receives from whichever source its selector resolves to, reconnecting with backoff.
+pub struct ReconnectingReceiver {
+    selector: SourceSelector,
+    config: ReconnectConfig,
+    receiver: Option<Box<dyn FrameReceiver>>,
+    source: Option<SourceInfo>,
+    state: ConnectionState,
+    /// When the current connection was made, until it first reports connected.
+    connecting_since: Option<Instant>,
+    attempts: u32,
+    next_attempt: Instant,
+    last_frame: Option<Frame>,
+    events: VecDeque<ReconnectEvent>,
+}
+
+impl ReconnectingReceiver {
+    /// Connects on the first `receive_frame`.
+    pub fn new(selector: SourceSelector, config: ReconnectConfig) -> Self {
+        Self {
+            selector,
+            config,
+            receiver: None,
+            source: None,
+            state: ConnectionState::Connecting,
+            connecting_since: None,
+            attempts: 0,
+            next_attempt: Instant::now(),
+            last_frame: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// The selector, updated with the UUID of each source connected to.
+    pub fn selector(&self) -> &SourceSelector {
+        &self.selector
+    }
+
+    pub fn config(&self) -> &ReconnectConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// The source currently connected to.
+    pub fn source(&self) -> Option<&SourceInfo> {
+        self.source.as_ref()
+    }
+
+    /// The most recent frame received, kept across disconnects. Always `None` unless
+    /// `ReconnectConfig::keep_last_frame` is set.
+    pub fn last_frame(&self) -> Option<&Frame> {
+        self.last_frame.as_ref()
+    }
+
+    /// Next pending event.
+    pub fn try_recv_event(&mut self) -> Option<ReconnectEvent> {
+        self.events.pop_front()
+    }
+
+    /// All events pending right now.
+    pub fn drain_events(&mut self) -> Vec<ReconnectEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// Check the connection and, if there is none, try to connect when the backoff allows.
+    /// `receive_frame` does this first.
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+        if let Some(receiver) = &self.receiver {
+            if receiver.is_connected() {
+                self.connecting_since = None;
+                self.attempts = 0;
+                return;
+            }
+            let settling = self
+                .connecting_since
+                .is_some_and(|since| now.duration_since(since) < self.config.connect_timeout);
+            if settling {
+                return;
+            }
+            self.receiver = None;
+            self.state = ConnectionState::Reconnecting;
+            if let Some(source) = self.source.take() {
+                self.events.push_back(ReconnectEvent::Lost { source });
+            }
+            // A connection that never reported connected counts as a failed attempt, so a
+            // source that accepts connections but never delivers still backs off.
+            if self.connecting_since.take().is_some() {
+                self.schedule_retry(now);
+            } else {
+                self.attempts = 0;
+                self.next_attempt = now;
+            }
+        }
+        if now >= self.next_attempt {
+            self.attempt(now);
+        }
+    }
+
+    fn attempt(&mut self, now: Instant) {
+        let connected = self
+            .selector
+            .resolve()
+            .and_then(|found| connect_source(&found.source).map(|receiver| (found, receiver)));
+        match connected {
+            Some((found, receiver)) => {
+                self.selector.remember(&found.source);
+                if receiver.is_connected() {
+                    self.attempts = 0;
+                } else {
+                    self.connecting_since = Some(now);
+                }
+                self.receiver = Some(receiver);
+                self.source = Some(found.source.clone());
+                self.state = ConnectionState::Connected;
+                self.events.push_back(ReconnectEvent::Connected {
+                    source: found.source,
+                    rule: found.rule,
+                });
+            }
+            None => self.schedule_retry(now),
+        }
+    }
+
+    fn schedule_retry(&mut self, now: Instant) {
+        self.attempts += 1;
+        let delay = self.config.delay(self.attempts);
+        self.next_attempt = now + delay;
+        self.events.push_back(ReconnectEvent::RetryScheduled {
+            attempt: self.attempts,
+            delay,
+        });
+    }
+}
+
+impl FrameReceiver for ReconnectingReceiver {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        self.poll();
+        let frame = self.receiver.as_mut()?.receive_frame()?;
+        if self.connecting_since.take().is_some() {
+            self.attempts = 0;
+        }
+        if self.config.keep_last_frame {
+            self.last_frame = Some(frame.clone());
+        }
+        Some(frame)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.state == ConnectionState::Connected
+    }
+
+    fn stats(&self) -> Option<ReceiverStats> {
+        self.receiver.as_ref()?.stats()
+    }
+}
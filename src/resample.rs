@@ -0,0 +1,353 @@
+//! Resampling CPU frames to a fixed output size.
+//!
+//! Senders pick their own size (`receiver_sender_width`/`height`, `OpenGLImage::texture_size`,
+//! `LocalServerInfo::size`); a `Resampler` normalizes whatever arrives to the size an output needs.
+//! Filtering is separable (horizontal then vertical pass) on premultiplied alpha, and rows are
+//! split across threads.
+
+use std::num::NonZeroUsize;
+
+use crate::frame::{Frame, PixelFormat};
+use crate::geometry::{Rect, Size};
+
+/// Reconstruction filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ResampleFilter {
+    /// Nearest pixel; no blending.
+    Nearest,
+    /// Linear interpolation (triangle filter).
+    #[default]
+    Bilinear,
+    /// Catmull-Rom cubic.
+    Bicubic,
+    /// Lanczos with 3 lobes.
+    Lanczos3,
+}
+
+impl ResampleFilter {
+    /// Kernel radius in source pixels at scale 1.
+    fn support(&self) -> f32 {
+        match self {
+            ResampleFilter::Nearest => 0.5,
+            ResampleFilter::Bilinear => 1.0,
+            ResampleFilter::Bicubic => 2.0,
+            ResampleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ResampleFilter::Nearest => {
+                if x < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ResampleFilter::Bilinear => (1.0 - x).max(0.0),
+            ResampleFilter::Bicubic => {
+                // Catmull-Rom (B = 0, C = 0.5).
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            ResampleFilter::Lanczos3 => {
+                if x < 1e-6 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = std::f32::consts::PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// How the source aspect ratio is handled when it differs from the output's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ScaleMode {
+    /// Scale each axis independently to fill the output; distorts the image.
+    #[default]
+    Stretch,
+    /// Scale uniformly so the whole source is visible; the rest is filled with the background (letterbox/pillarbox).
+    Fit,
+    /// Scale uniformly so the output is covered; the source is cropped around its center.
+    Fill,
+}
+
+/// Resamples frames to a target size with a chosen filter and scale mode.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    filter: ResampleFilter,
+    mode: ScaleMode,
+    background: [u8; 4],
+    threads: usize,
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Self::new(ResampleFilter::default())
+    }
+}
+
+impl Resampler {
+    /// Stretch with `filter`, opaque black bars, one thread per available core.
+    pub fn new(filter: ResampleFilter) -> Self {
+        Self {
+            filter,
+            mode: ScaleMode::Stretch,
+            background: [0, 0, 0, 255],
+            threads: std::thread::available_parallelism()
+                .map(NonZeroUsize::get)
+                .unwrap_or(1),
+        }
+    }
+
+    pub fn filter(mut self, filter: ResampleFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn mode(mut self, mode: ScaleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Color of the bars in `ScaleMode::Fit`, as RGBA regardless of the frame format.
+    pub fn background(mut self, rgba: [u8; 4]) -> Self {
+        self.background = rgba;
+        self
+    }
+
+    /// Maximum worker threads (at least 1).
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Where the scaled image lands in an output of `size`, and which part of the source it shows.
+    /// Returns `(source_rect, destination_rect)`.
+    pub fn placement(&self, source: Size, size: Size) -> (Rect, Rect) {
+        let full_src = Rect::from_size(source);
+        let full_dst = Rect::from_size(size);
+        if source.is_empty() || size.is_empty() {
+            return (full_src, full_dst);
+        }
+        let sx = size.width as f64 / source.width as f64;
+        let sy = size.height as f64 / source.height as f64;
+        match self.mode {
+            ScaleMode::Stretch => (full_src, full_dst),
+            ScaleMode::Fit => {
+                let s = sx.min(sy);
+                let w = ((source.width as f64 * s).round() as u32).clamp(1, size.width);
+                let h = ((source.height as f64 * s).round() as u32).clamp(1, size.height);
+                let dst = Rect::new((size.width - w) / 2, (size.height - h) / 2, w, h);
+                (full_src, dst)
+            }
+            ScaleMode::Fill => {
+                let s = sx.max(sy);
+                let w = ((size.width as f64 / s).round() as u32).clamp(1, source.width);
+                let h = ((size.height as f64 / s).round() as u32).clamp(1, source.height);
+                let src = Rect::new((source.width - w) / 2, (source.height - h) / 2, w, h);
+                (src, full_dst)
+            }
+        }
+    }
+
+    /// Resample `frame` to `size`. The output is tightly packed in the frame's format and color space.
+    pub fn resample(&self, frame: &Frame, size: Size) -> Frame {
+        let mut out = Frame::new(size.width, size.height, frame.format())
+            .with_color_space(frame.color_space());
+        if size.is_empty() {
+            return out;
+        }
+        let (src_rect, dst_rect) = self.placement(frame.size(), size);
+        if dst_rect != Rect::from_size(size) {
+            let bg = match frame.format() {
+                PixelFormat::Rgba8 => self.background,
+                PixelFormat::Bgra8 => {
+                    let [r, g, b, a] = self.background;
+                    [b, g, r, a]
+                }
+            };
+            for y in 0..size.height {
+                for px in out.row_mut(y).chunks_exact_mut(4) {
+                    px.copy_from_slice(&bg);
+                }
+            }
+        }
+        if frame.size().is_empty() {
+            return out;
+        }
+        let scaled = if self.filter == ResampleFilter::Nearest {
+            self.nearest(frame, src_rect, dst_rect.size())
+        } else {
+            self.convolve(frame, src_rect, dst_rect.size())
+        };
+        for y in 0..dst_rect.height {
+            let start = dst_rect.x as usize * 4;
+            let row = scaled.row(y);
+            out.row_mut(dst_rect.y + y)[start..start + row.len()].copy_from_slice(row);
+        }
+        out
+    }
+
+    fn nearest(&self, frame: &Frame, src: Rect, size: Size) -> Frame {
+        let mut out = Frame::new(size.width, size.height, frame.format());
+        let row_bytes = out.row_bytes();
+        let columns: Vec<usize> = (0..size.width)
+            .map(|x| {
+                let sx = src.x as u64 + (x as u64 * 2 + 1) * src.width as u64 / (size.width as u64 * 2);
+                sx as usize * 4
+            })
+            .collect();
+        for_each_row_chunk(out.data_mut(), row_bytes, self.threads, |y0, rows| {
+            for (i, row) in rows.chunks_exact_mut(row_bytes).enumerate() {
+                let y = (y0 + i) as u64;
+                let sy = src.y as u64 + (y * 2 + 1) * src.height as u64 / (size.height as u64 * 2);
+                let src_row = frame.row(sy as u32);
+                for (px, &sx) in row.chunks_exact_mut(4).zip(&columns) {
+                    px.copy_from_slice(&src_row[sx..sx + 4]);
+                }
+            }
+        });
+        out
+    }
+
+    fn convolve(&self, frame: &Frame, src: Rect, size: Size) -> Frame {
+        let h_weights = axis_weights(self.filter, src.x, src.width, frame.width(), size.width);
+        let v_weights = axis_weights(self.filter, src.y, src.height, frame.height(), size.height);
+
+        // Horizontal pass over only the source rows the vertical pass will read.
+        let first_row = v_weights.iter().map(|t| t.start).min().unwrap_or(0);
+        let last_row = v_weights
+            .iter()
+            .map(|t| t.start + t.weights.len())
+            .max()
+            .unwrap_or(0);
+        let tmp_row_len = size.width as usize * 4;
+        let mut tmp = vec![0f32; tmp_row_len * (last_row - first_row)];
+        for_each_row_chunk(&mut tmp, tmp_row_len, self.threads, |r0, rows| {
+            for (i, row) in rows.chunks_exact_mut(tmp_row_len).enumerate() {
+                let src_row = frame.row((first_row + r0 + i) as u32);
+                for (px, taps) in row.chunks_exact_mut(4).zip(&h_weights) {
+                    let mut acc = [0f32; 4];
+                    for (k, &w) in taps.weights.iter().enumerate() {
+                        let s = &src_row[(taps.start + k) * 4..(taps.start + k) * 4 + 4];
+                        let a = s[3] as f32 * w;
+                        acc[0] += s[0] as f32 * a;
+                        acc[1] += s[1] as f32 * a;
+                        acc[2] += s[2] as f32 * a;
+                        acc[3] += a;
+                    }
+                    px.copy_from_slice(&acc);
+                }
+            }
+        });
+
+        // Vertical pass, then un-premultiply.
+        let mut out = Frame::new(size.width, size.height, frame.format());
+        let row_bytes = out.row_bytes();
+        for_each_row_chunk(out.data_mut(), row_bytes, self.threads, |y0, rows| {
+            for (i, row) in rows.chunks_exact_mut(row_bytes).enumerate() {
+                let taps = &v_weights[y0 + i];
+                for (x, px) in row.chunks_exact_mut(4).enumerate() {
+                    let mut acc = [0f32; 4];
+                    for (k, &w) in taps.weights.iter().enumerate() {
+                        let t = (taps.start + k - first_row) * tmp_row_len + x * 4;
+                        for c in 0..4 {
+                            acc[c] += tmp[t + c] * w;
+                        }
+                    }
+                    let alpha = acc[3];
+                    if alpha <= 0.0 {
+                        px.fill(0);
+                        continue;
+                    }
+                    for c in 0..3 {
+                        px[c] = (acc[c] / alpha).round().clamp(0.0, 255.0) as u8;
+                    }
+                    px[3] = alpha.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+        out
+    }
+}
+
+/// Resample `frame` to `size` (stretching) with `filter`.
+pub fn resample(frame: &Frame, size: Size, filter: ResampleFilter) -> Frame {
+    Resampler::new(filter).resample(frame, size)
+}
+
+/// Contiguous source taps for one output pixel.
+struct Taps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Normalized filter taps mapping `src_len` pixels starting at `src_start` onto `dst_len` pixels.
+/// Taps are clamped to `[0, limit)`, i.e. the whole source frame, so edges blend with real neighbours.
+fn axis_weights(
+    filter: ResampleFilter,
+    src_start: u32,
+    src_len: u32,
+    limit: u32,
+    dst_len: u32,
+) -> Vec<Taps> {
+    let scale = src_len as f32 / dst_len as f32;
+    let filter_scale = scale.max(1.0);
+    let support = filter.support() * filter_scale;
+    (0..dst_len)
+        .map(|i| {
+            let center = src_start as f32 + (i as f32 + 0.5) * scale;
+            let lo = ((center - support).floor() as i64).max(0) as usize;
+            let hi = ((center + support).ceil() as i64).min(limit as i64).max(lo as i64 + 1) as usize;
+            let mut weights: Vec<f32> = (lo..hi)
+                .map(|j| filter.weight((j as f32 + 0.5 - center) / filter_scale))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum.abs() > f32::EPSILON {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            } else {
+                // Degenerate (e.g. nearest kernel between taps): take the closest pixel.
+                weights.iter_mut().for_each(|w| *w = 0.0);
+                let nearest = (center.floor() as usize).clamp(lo, hi - 1);
+                weights[nearest - lo] = 1.0;
+            }
+            Taps { start: lo, weights }
+        })
+        .collect()
+}
+
+/// Split `data` (rows of `row_len` elements) into up to `threads` contiguous chunks and run `f`
+/// on each as `(first_row_index, rows)`.
+fn for_each_row_chunk<T: Send>(
+    data: &mut [T],
+    row_len: usize,
+    threads: usize,
+    f: impl Fn(usize, &mut [T]) + Sync,
+) {
+    if row_len == 0 || data.is_empty() {
+        return;
+    }
+    let rows = data.len() / row_len;
+    let rows_per_chunk = rows.div_ceil(threads.clamp(1, rows));
+    if rows_per_chunk >= rows {
+        f(0, data);
+        return;
+    }
+    std::thread::scope(|scope| {
+        for (i, chunk) in data.chunks_mut(rows_per_chunk * row_len).enumerate() {
+            let f = &f;
+            scope.spawn(move || f(i * rows_per_chunk, chunk));
+        }
+    });
+}
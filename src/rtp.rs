@@ -0,0 +1,651 @@
+//! Uncompressed video over RTP/UDP (RFC 4175), for broadcast gear and tools such as GStreamer's
+//! `rtpvrawpay` / `rtpvrawdepay` and ffmpeg.
+//!
+//! `RtpSender` packetizes CPU frames (8-bit RGBA, BGRA or YCbCr 4:2:2) into RTP packets that fit
+//! the configured MTU, with a 90 kHz timestamp per frame and the marker bit on each frame's last
+//! packet. `RtpReceiver` reassembles frames by line number and offset, so packets may arrive in
+//! any order, and `RtpInput` republishes a receiver's frames as a `LocalServer`. RFC 4175 carries
+//! no frame size in-band; both ends are configured with it, as an SDP file would (`RtpSender::sdp`).
+
+use std::collections::HashSet;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::{PublishError, Size};
+use crate::local::{LocalServer, LocalServerInfo};
+use crate::stats::ReceiverStats;
+use crate::util::random_u64;
+use crate::yuv::{YuvFormat, YuvFrame, YuvMatrix, YuvRange};
+
+/// RTP clock rate for video payloads.
+pub const RTP_CLOCK_RATE: u32 = 90_000;
+
+/// Dynamic payload type used unless configured otherwise.
+pub const RTP_DEFAULT_PAYLOAD_TYPE: u8 = 96;
+
+/// Default largest UDP payload, leaving room for IP/UDP headers within a 1500-byte Ethernet MTU.
+pub const RTP_DEFAULT_MTU: usize = 1400;
+
+const RTP_HEADER_LEN: usize = 12;
+const PAYLOAD_HEADER_LEN: usize = 2;
+const LINE_HEADER_LEN: usize = 6;
+/// Line numbers and offsets are 15-bit fields.
+const MAX_DIMENSION: u32 = 1 << 15;
+/// Frames being reassembled at once; older partial frames count as dropped.
+const MAX_PENDING_FRAMES: usize = 3;
+
+/// RFC 4175 sampling, all at 8 bits per component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RtpSampling {
+    #[default]
+    Rgba,
+    Bgra,
+    /// YCbCr 4:2:2 (BT.709, limited range), sent as Cb Y0 Cr Y1. Width must be even.
+    YCbCr422,
+}
+
+impl RtpSampling {
+    /// Bytes and pixels in one pixel group.
+    pub fn pgroup(&self) -> (usize, u32) {
+        match self {
+            RtpSampling::Rgba | RtpSampling::Bgra => (4, 1),
+            RtpSampling::YCbCr422 => (4, 2),
+        }
+    }
+
+    /// The SDP `sampling` parameter.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RtpSampling::Rgba => "RGBA",
+            RtpSampling::Bgra => "BGRA",
+            RtpSampling::YCbCr422 => "YCbCr-4:2:2",
+        }
+    }
+
+    /// Bytes in one line of `width` pixels.
+    fn line_bytes(&self, width: u32) -> usize {
+        let (bytes, pixels) = self.pgroup();
+        (width / pixels) as usize * bytes
+    }
+
+    fn check_size(&self, size: Size) -> io::Result<()> {
+        let (_, pixels) = self.pgroup();
+        if size.width == 0
+            || size.height == 0
+            || size.width > MAX_DIMENSION
+            || size.height > MAX_DIMENSION
+            || !size.width.is_multiple_of(pixels)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{}x{} cannot be sent as {}",
+                    size.width,
+                    size.height,
+                    self.name()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Packed pixels of `frame` in this sampling.
+    fn encode(&self, frame: &Frame) -> Vec<u8> {
+        let frame = match self {
+            RtpSampling::Rgba => frame.to_format(PixelFormat::Rgba8),
+            RtpSampling::Bgra => frame.to_format(PixelFormat::Bgra8),
+            RtpSampling::YCbCr422 => {
+                return YuvFrame::from_frame(
+                    frame,
+                    YuvFormat::Uyvy,
+                    YuvMatrix::Bt709,
+                    YuvRange::Limited,
+                )
+                .into_vec()
+            }
+        };
+        let mut out = Vec::with_capacity(frame.row_bytes() * frame.height() as usize);
+        for y in 0..frame.height() {
+            out.extend_from_slice(frame.row(y));
+        }
+        out
+    }
+
+    /// Frame from packed pixels in this sampling.
+    fn decode(&self, width: u32, height: u32, data: Vec<u8>) -> Option<Frame> {
+        match self {
+            RtpSampling::Rgba => Frame::from_vec(width, height, PixelFormat::Rgba8, data),
+            RtpSampling::Bgra => Frame::from_vec(width, height, PixelFormat::Bgra8, data),
+            RtpSampling::YCbCr422 => YuvFrame::from_vec(width, height, YuvFormat::Uyvy, data)
+                .map(|yuv| yuv.to_frame(PixelFormat::Rgba8)),
+        }
+    }
+}
+
+/// Sends frames as RFC 4175 RTP packets to one UDP destination (unicast or multicast).
+pub struct RtpSender {
+    socket: UdpSocket,
+    sampling: RtpSampling,
+    payload_type: u8,
+    ssrc: u32,
+    mtu: usize,
+    sequence: u32,
+    started: Instant,
+    /// Random start for RTP timestamps (RFC 3550 §5.1).
+    timestamp_offset: u32,
+}
+
+impl RtpSender {
+    /// Send to `dest` from an ephemeral local port, with a random SSRC, sequence start and
+    /// timestamp start.
+    pub fn connect(dest: impl ToSocketAddrs) -> io::Result<Self> {
+        let dest = dest
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no destination address"))?;
+        let socket = match dest {
+            SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?,
+            SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?,
+        };
+        socket.connect(dest)?;
+        let random = random_u64();
+        Ok(Self {
+            socket,
+            sampling: RtpSampling::default(),
+            payload_type: RTP_DEFAULT_PAYLOAD_TYPE,
+            ssrc: random as u32,
+            mtu: RTP_DEFAULT_MTU,
+            sequence: (random >> 32) as u16 as u32,
+            started: Instant::now(),
+            timestamp_offset: random_u64() as u32,
+        })
+    }
+
+    pub fn sampling(mut self, sampling: RtpSampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Dynamic payload type (96-127) announced in the SDP.
+    pub fn payload_type(mut self, payload_type: u8) -> Self {
+        self.payload_type = payload_type & 0x7F;
+        self
+    }
+
+    pub fn ssrc(mut self, ssrc: u32) -> Self {
+        self.ssrc = ssrc;
+        self
+    }
+
+    /// Largest UDP payload per packet (at least room for one pixel group).
+    pub fn mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu.max(RTP_HEADER_LEN + PAYLOAD_HEADER_LEN + LINE_HEADER_LEN + 4);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /// 90 kHz timestamp for now, counted from a random start when the sender was created.
+    pub fn timestamp_now(&self) -> u32 {
+        let ticks = self.started.elapsed().as_micros() * RTP_CLOCK_RATE as u128 / 1_000_000;
+        self.timestamp_offset.wrapping_add(ticks as u32)
+    }
+
+    /// Split `frame` into RTP packets stamped with `timestamp`, advancing the sequence number.
+    pub fn packetize(&mut self, frame: &Frame, timestamp: u32) -> io::Result<Vec<Vec<u8>>> {
+        let sampling = self.sampling;
+        sampling.check_size(frame.size())?;
+        let data = sampling.encode(frame);
+        let (pgroup_bytes, pgroup_pixels) = sampling.pgroup();
+        let line_bytes = sampling.line_bytes(frame.width());
+        let budget = self.mtu - RTP_HEADER_LEN - PAYLOAD_HEADER_LEN;
+        let (mut line, mut offset) = (0u32, 0u32);
+        let mut packets = Vec::new();
+        while line < frame.height() {
+            // Pack as many line segments as fit.
+            let mut segments = Vec::new();
+            let mut remaining = budget;
+            while line < frame.height() && remaining >= LINE_HEADER_LEN + pgroup_bytes {
+                remaining -= LINE_HEADER_LEN;
+                let groups = ((frame.width() - offset) / pgroup_pixels) as usize;
+                let groups = groups.min(remaining / pgroup_bytes);
+                remaining -= groups * pgroup_bytes;
+                segments.push((line, offset, groups * pgroup_bytes));
+                offset += groups as u32 * pgroup_pixels;
+                if offset == frame.width() {
+                    line += 1;
+                    offset = 0;
+                }
+            }
+            let marker = line == frame.height();
+            let mut packet = Vec::with_capacity(self.mtu);
+            packet.push(0x80);
+            packet.push((marker as u8) << 7 | self.payload_type);
+            packet.extend_from_slice(&(self.sequence as u16).to_be_bytes());
+            packet.extend_from_slice(&timestamp.to_be_bytes());
+            packet.extend_from_slice(&self.ssrc.to_be_bytes());
+            packet.extend_from_slice(&((self.sequence >> 16) as u16).to_be_bytes());
+            for (i, &(line, offset, len)) in segments.iter().enumerate() {
+                let continuation = if i + 1 < segments.len() { 0x8000 } else { 0 };
+                packet.extend_from_slice(&(len as u16).to_be_bytes());
+                packet.extend_from_slice(&(line as u16).to_be_bytes());
+                packet.extend_from_slice(&(offset as u16 | continuation).to_be_bytes());
+            }
+            for &(line, offset, len) in &segments {
+                let start = line as usize * line_bytes
+                    + offset as usize / pgroup_pixels as usize * pgroup_bytes;
+                packet.extend_from_slice(&data[start..start + len]);
+            }
+            packets.push(packet);
+            self.sequence = self.sequence.wrapping_add(1);
+        }
+        Ok(packets)
+    }
+
+    /// Send `frame` stamped with `timestamp`. Returns the number of packets sent.
+    pub fn send(&mut self, frame: &Frame, timestamp: u32) -> io::Result<usize> {
+        let packets = self.packetize(frame, timestamp)?;
+        for packet in &packets {
+            self.socket.send(packet)?;
+        }
+        Ok(packets.len())
+    }
+
+    /// Send the next frame from `receiver`, if it has one. Returns true if a frame was sent.
+    pub fn forward(&mut self, receiver: &mut dyn FrameReceiver) -> io::Result<bool> {
+        match receiver.receive_frame() {
+            Some(frame) => {
+                self.send(&frame, self.timestamp_now())?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// SDP description of this stream for frames of `size`, for receivers that read one.
+    pub fn sdp(&self, size: Size) -> io::Result<String> {
+        let local = self.socket.local_addr()?;
+        let dest = self.socket.peer_addr()?;
+        let family = |addr: &SocketAddr| if addr.is_ipv4() { "IP4" } else { "IP6" };
+        let colorimetry = match self.sampling {
+            RtpSampling::YCbCr422 => "BT709",
+            _ => "SRGB",
+        };
+        Ok(format!(
+            "v=0\r\n\
+             o=- {ssrc} 0 IN {lf} {lip}\r\n\
+             s=rusty-syphon-spout\r\n\
+             c=IN {df} {dip}\r\n\
+             t=0 0\r\n\
+             m=video {port} RTP/AVP {pt}\r\n\
+             a=rtpmap:{pt} raw/{RTP_CLOCK_RATE}\r\n\
+             a=fmtp:{pt} sampling={sampling}; width={w}; height={h}; depth=8; colorimetry={colorimetry}\r\n",
+            ssrc = self.ssrc,
+            lf = family(&local),
+            lip = local.ip(),
+            df = family(&dest),
+            dip = dest.ip(),
+            port = dest.port(),
+            pt = self.payload_type,
+            sampling = self.sampling.name(),
+            w = size.width,
+            h = size.height,
+        ))
+    }
+}
+
+impl FrameSender for RtpSender {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        let timestamp = self.timestamp_now();
+        self.send(frame, timestamp)
+            .map(|_| ())
+            .map_err(|_| PublishError::Backend)
+    }
+}
+
+/// A frame being reassembled.
+struct PartialFrame {
+    timestamp: u32,
+    data: Vec<u8>,
+    /// Byte ranges of each line written so far, disjoint.
+    lines: Vec<Vec<Range<usize>>>,
+    /// Bytes covered by `lines`; the frame is complete once this reaches `data.len()`.
+    covered: usize,
+    sequences: HashSet<u32>,
+}
+
+impl PartialFrame {
+    /// Mark `range` of `line` as written.
+    fn cover(&mut self, line: usize, range: Range<usize>) {
+        let ranges = &mut self.lines[line];
+        let mut merged = range.clone();
+        let mut already = 0;
+        // Fold in every range that overlaps or touches this one.
+        ranges.retain(|r| {
+            if r.end < range.start || r.start > range.end {
+                return true;
+            }
+            merged = merged.start.min(r.start)..merged.end.max(r.end);
+            already += r.len();
+            false
+        });
+        self.covered += merged.len() - already;
+        ranges.push(merged);
+    }
+}
+
+/// Receives RFC 4175 RTP packets on a UDP port and reassembles frames of a fixed size.
+pub struct RtpReceiver {
+    socket: UdpSocket,
+    size: Size,
+    sampling: RtpSampling,
+    payload_type: Option<u8>,
+    /// SSRC of the stream being received; another one means the sender restarted.
+    ssrc: Option<u32>,
+    pending: Vec<PartialFrame>,
+    last_completed: Option<u32>,
+    dropped_since_last: u64,
+    stats: ReceiverStats,
+    /// Datagram buffer reused across `try_recv` calls.
+    buf: Vec<u8>,
+}
+
+impl RtpReceiver {
+    /// Listen on `addr` for a stream of `size` frames in `sampling`.
+    pub fn bind(addr: impl ToSocketAddrs, size: Size, sampling: RtpSampling) -> io::Result<Self> {
+        sampling.check_size(size)?;
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            size,
+            sampling,
+            payload_type: None,
+            ssrc: None,
+            pending: Vec::new(),
+            last_completed: None,
+            dropped_since_last: 0,
+            stats: ReceiverStats::default(),
+            buf: vec![0; 65536],
+        })
+    }
+
+    /// Only accept packets with this payload type (any by default).
+    pub fn payload_type(mut self, payload_type: u8) -> Self {
+        self.payload_type = Some(payload_type & 0x7F);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn stats(&self) -> ReceiverStats {
+        self.stats
+    }
+
+    /// RTP timestamp of the last completed frame.
+    pub fn last_timestamp(&self) -> Option<u32> {
+        self.last_completed
+    }
+
+    /// Read all queued packets and return the newest frame they completed, if any.
+    pub fn try_recv(&mut self) -> Option<Frame> {
+        // Taken out while packets are handled, which needs `self` mutably.
+        let mut buf = std::mem::take(&mut self.buf);
+        let mut latest = None;
+        loop {
+            match self.socket.recv(&mut buf) {
+                Ok(len) => {
+                    if let Some(frame) = self.handle_packet(&buf[..len]) {
+                        latest = Some(frame);
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+        self.buf = buf;
+        latest
+    }
+
+    /// Wait up to `timeout` for a complete frame.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Frame> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(frame) = self.try_recv() {
+                return Some(frame);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            // Block for the next packet, then drain without blocking again.
+            let _ = self.socket.set_nonblocking(false);
+            let _ = self
+                .socket
+                .set_read_timeout(Some((deadline - now).max(Duration::from_millis(1))));
+            let mut peek = [0u8; 1];
+            let _ = self.socket.peek(&mut peek);
+            let _ = self.socket.set_nonblocking(true);
+        }
+    }
+
+    /// Place one packet; returns the frame it completes.
+    fn handle_packet(&mut self, packet: &[u8]) -> Option<Frame> {
+        if packet.len() < RTP_HEADER_LEN + PAYLOAD_HEADER_LEN || packet[0] >> 6 != 2 {
+            return None;
+        }
+        let csrc_len = (packet[0] & 0x0F) as usize * 4;
+        let has_extension = packet[0] & 0x10 != 0;
+        if self.payload_type.is_some_and(|pt| pt != packet[1] & 0x7F) {
+            return None;
+        }
+        let sequence_low = u16::from_be_bytes([packet[2], packet[3]]);
+        let timestamp = u32::from_be_bytes(packet[4..8].try_into().unwrap());
+        let ssrc = u32::from_be_bytes(packet[8..12].try_into().unwrap());
+        if self.ssrc != Some(ssrc) {
+            // A new sender: its timestamps have nothing to do with the old one's.
+            self.ssrc = Some(ssrc);
+            self.pending.clear();
+            self.last_completed = None;
+            self.dropped_since_last = 0;
+        }
+        if self
+            .last_completed
+            .is_some_and(|last| timestamp.wrapping_sub(last) as i32 <= 0)
+        {
+            return None; // late packet of a frame already delivered or given up on
+        }
+        let mut pos = RTP_HEADER_LEN + csrc_len;
+        if has_extension {
+            let words = u16::from_be_bytes([*packet.get(pos + 2)?, *packet.get(pos + 3)?]);
+            pos += 4 + words as usize * 4;
+        }
+        let mut payload = packet.get(pos..)?;
+        if packet[0] & 0x20 != 0 {
+            let padding = *payload.last()? as usize;
+            payload = payload.get(..payload.len().checked_sub(padding)?)?;
+        }
+        let sequence_high = u16::from_be_bytes([*payload.first()?, *payload.get(1)?]);
+        let sequence = (sequence_high as u32) << 16 | sequence_low as u32;
+
+        // Line headers, then the data of each segment in order.
+        let mut segments = Vec::new();
+        let mut header_pos = PAYLOAD_HEADER_LEN;
+        loop {
+            let header = payload.get(header_pos..header_pos + LINE_HEADER_LEN)?;
+            header_pos += LINE_HEADER_LEN;
+            let len = u16::from_be_bytes([header[0], header[1]]) as usize;
+            let line = (u16::from_be_bytes([header[2], header[3]]) & 0x7FFF) as u32;
+            let offset_word = u16::from_be_bytes([header[4], header[5]]);
+            segments.push((line, (offset_word & 0x7FFF) as u32, len));
+            if offset_word & 0x8000 == 0 {
+                break;
+            }
+        }
+
+        let index = self.pending_index(timestamp);
+        let (pgroup_bytes, pgroup_pixels) = self.sampling.pgroup();
+        let line_bytes = self.sampling.line_bytes(self.size.width);
+        let partial = &mut self.pending[index];
+        if !partial.sequences.insert(sequence) {
+            return None; // duplicate
+        }
+        let mut data_pos = header_pos;
+        for (line, offset, len) in segments {
+            let src = payload.get(data_pos..data_pos + len)?;
+            data_pos += len;
+            if line >= self.size.height
+                || !offset.is_multiple_of(pgroup_pixels)
+                || !len.is_multiple_of(pgroup_bytes)
+            {
+                continue;
+            }
+            let start =
+                line as usize * line_bytes + (offset / pgroup_pixels) as usize * pgroup_bytes;
+            if start + len > (line as usize + 1) * line_bytes {
+                continue;
+            }
+            partial.data[start..start + len].copy_from_slice(src);
+            let line_start = start - line as usize * line_bytes;
+            partial.cover(line as usize, line_start..line_start + len);
+        }
+        if partial.covered < partial.data.len() {
+            return None;
+        }
+
+        let partial = self.pending.remove(index);
+        // Anything older than a completed frame will never be shown.
+        let before = self.pending.len();
+        self.pending
+            .retain(|p| p.timestamp.wrapping_sub(timestamp) as i32 > 0);
+        self.dropped_since_last += (before - self.pending.len()) as u64;
+        self.last_completed = Some(timestamp);
+        let frame = self
+            .sampling
+            .decode(self.size.width, self.size.height, partial.data)?;
+        self.stats
+            .record_frame(std::mem::take(&mut self.dropped_since_last));
+        Some(frame)
+    }
+
+    /// Index of the partial frame for `timestamp`, starting one (and evicting the oldest) if needed.
+    fn pending_index(&mut self, timestamp: u32) -> usize {
+        if let Some(index) = self.pending.iter().position(|p| p.timestamp == timestamp) {
+            return index;
+        }
+        if self.pending.len() == MAX_PENDING_FRAMES {
+            let oldest = (0..self.pending.len())
+                .min_by_key(|&i| self.pending[i].timestamp.wrapping_sub(timestamp) as i32)
+                .unwrap();
+            self.pending.remove(oldest);
+            self.dropped_since_last += 1;
+        }
+        let len = self.sampling.line_bytes(self.size.width) * self.size.height as usize;
+        self.pending.push(PartialFrame {
+            timestamp,
+            data: vec![0; len],
+            lines: vec![Vec::new(); self.size.height as usize],
+            covered: 0,
+            sequences: HashSet::new(),
+        });
+        self.pending.len() - 1
+    }
+}
+
+impl FrameReceiver for RtpReceiver {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        self.try_recv()
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn stats(&self) -> Option<ReceiverStats> {
+        Some(self.stats)
+    }
+}
+
+/// Republishes frames from an `RtpReceiver` as a `LocalServer`, from a background thread.
+/// Stops and withdraws the server on drop.
+pub struct RtpInput {
+    server: Arc<LocalServer>,
+    local_addr: SocketAddr,
+    stats: Arc<Mutex<ReceiverStats>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RtpInput {
+    /// Start publishing `receiver`'s frames under `name`.
+    pub fn start(mut receiver: RtpReceiver, name: &str) -> io::Result<Self> {
+        let local_addr = receiver.local_addr()?;
+        let server = Arc::new(LocalServer::new(name));
+        let stats = Arc::new(Mutex::new(ReceiverStats::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let server = server.clone();
+            let stats = stats.clone();
+            let shutdown = shutdown.clone();
+            std::thread::Builder::new()
+                .name(format!("rtp-input-{}", name))
+                .spawn(move || {
+                    while !shutdown.load(Ordering::SeqCst) {
+                        if let Some(frame) = receiver.recv_timeout(Duration::from_millis(50)) {
+                            server.publish(frame);
+                        }
+                        *stats.lock().unwrap() = receiver.stats();
+                    }
+                })?
+        };
+        Ok(Self {
+            server,
+            local_addr,
+            stats,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// The server clients connect to with `LocalClient::connect`.
+    pub fn server_info(&self) -> LocalServerInfo {
+        self.server.info()
+    }
+
+    /// UDP address packets are received on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn stats(&self) -> ReceiverStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+impl Drop for RtpInput {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+        self.server.stop();
+    }
+}
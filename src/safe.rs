@@ -3,6 +3,7 @@
 //! OpenGL: CGL context and GL usage must follow Syphon's and macOS's rules.
 //! Metal: pass `MTLDevice`/`MTLTexture`/`MTLCommandBuffer` pointers (e.g. from the `metal` crate).
 
+#[cfg(any(target_os = "macos", target_os = "windows"))]
 use std::ptr::NonNull;
 #[cfg(target_os = "macos")]
 use std::ffi::CStr;
@@ -12,7 +13,20 @@ use std::os::raw::c_char;
 #[cfg(target_os = "macos")]
 use crate::ffi;
 #[cfg(target_os = "windows")]
+use crate::channel::MemoryBuffer;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::{PublishError, PublishRequest};
+#[cfg(target_os = "windows")]
 use crate::ffi as spout_ffi;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crate::lock::LockingReceiver;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crate::pool::{FramePool, PooledFrame, PooledReceiver};
+#[cfg(target_os = "windows")]
+use crate::sync::{SyncMode, SyncReceiver, SyncSender};
+#[cfg(target_os = "windows")]
+use std::time::Duration;
 
 /// CGL context (from OpenGL/OpenGL.h). On macOS this is the real type from the FFI; elsewhere a placeholder.
 #[cfg(target_os = "macos")]
@@ -24,6 +38,7 @@ pub type CGLContextObj = *mut std::ffi::c_void;
 pub const GL_TEXTURE_RECTANGLE: u32 = 0x84F5;
 
 /// Create a headless CGL context for offscreen OpenGL (e.g. tests). Caller must destroy with `cgl_destroy_context`.
+/// On Linux use `egl_create_headless_context` instead.
 #[cfg(target_os = "macos")]
 pub fn cgl_create_headless_context() -> Option<CGLContextObj> {
     let ctx = unsafe { ffi::syphon_cgl_create_headless_context() };
@@ -56,7 +71,7 @@ pub fn cgl_make_current(ctx: CGLContextObj) {
 #[cfg(not(target_os = "macos"))]
 pub fn cgl_make_current(_ctx: CGLContextObj) {}
 
-/// Create a GL_TEXTURE_RECTANGLE RGBA8 texture and upload `rgba` (width*height*4 bytes). CGL context must be current. Returns 0 on failure.
+/// Create a GL_TEXTURE_RECTANGLE RGBA8 texture and upload `rgba` (width*height*4 bytes). A CGL (macOS) or EGL (Linux) context must be current. Returns 0 on failure.
 #[cfg(target_os = "macos")]
 pub fn gl_create_texture_rectangle_rgba8(width: usize, height: usize, rgba: &[u8]) -> u32 {
     let expected = width * height * 4;
@@ -67,12 +82,16 @@ pub fn gl_create_texture_rectangle_rgba8(width: usize, height: usize, rgba: &[u8
         ffi::syphon_gl_create_texture_rectangle_rgba8(width, height, rgba.as_ptr())
     }
 }
-#[cfg(not(target_os = "macos"))]
+#[cfg(target_os = "linux")]
+pub fn gl_create_texture_rectangle_rgba8(width: usize, height: usize, rgba: &[u8]) -> u32 {
+    crate::egl::create_texture_rectangle_rgba8(width, height, rgba)
+}
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
 pub fn gl_create_texture_rectangle_rgba8(_width: usize, _height: usize, _rgba: &[u8]) -> u32 {
     0
 }
 
-/// Read back a GL_TEXTURE_RECTANGLE texture into `out_rgba` (width*height*4 bytes). CGL context must be current.
+/// Read back a GL_TEXTURE_RECTANGLE texture into `out_rgba` (width*height*4 bytes). A CGL (macOS) or EGL (Linux) context must be current.
 #[cfg(target_os = "macos")]
 pub fn gl_read_texture_rectangle_rgba8(tex_id: u32, width: usize, height: usize, out_rgba: &mut [u8]) {
     let expected = width * height * 4;
@@ -83,7 +102,11 @@ pub fn gl_read_texture_rectangle_rgba8(tex_id: u32, width: usize, height: usize,
         ffi::syphon_gl_read_texture_rectangle_rgba8(tex_id, width, height, out_rgba.as_mut_ptr());
     }
 }
-#[cfg(not(target_os = "macos"))]
+#[cfg(target_os = "linux")]
+pub fn gl_read_texture_rectangle_rgba8(tex_id: u32, width: usize, height: usize, out_rgba: &mut [u8]) {
+    crate::egl::read_texture_rectangle_rgba8(tex_id, width, height, out_rgba);
+}
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
 pub fn gl_read_texture_rectangle_rgba8(_tex_id: u32, _width: usize, _height: usize, _out_rgba: &mut [u8]) {
 }
 
@@ -94,7 +117,11 @@ pub fn gl_delete_texture(tex_id: u32) {
         unsafe { ffi::syphon_gl_delete_texture(tex_id) };
     }
 }
-#[cfg(not(target_os = "macos"))]
+#[cfg(target_os = "linux")]
+pub fn gl_delete_texture(tex_id: u32) {
+    crate::egl::delete_texture(tex_id);
+}
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
 pub fn gl_delete_texture(_tex_id: u32) {}
 
 /// Server directory: shared singleton listing available Syphon servers.
@@ -556,7 +583,33 @@ impl OpenGLServer {
         None
     }
 
+    /// Publish the region of `request` from a texture. Validates the region against the texture size first.
+    pub fn publish(
+        &self,
+        tex_id: u32,
+        target: u32,
+        request: &PublishRequest,
+    ) -> Result<(), PublishError> {
+        request.validate()?;
+        let region = request.publish_region();
+        let size = request.texture_size();
+        self.publish_frame(
+            tex_id,
+            target,
+            region.x as f64,
+            region.y as f64,
+            region.width as f64,
+            region.height as f64,
+            size.width as f64,
+            size.height as f64,
+            request.is_flipped(),
+        );
+        Ok(())
+    }
+
     /// Publish a frame from a texture. Region (x,y,w,h) and texture size (tex_w, tex_h), flipped.
+    /// Prefer `publish`, which validates the region.
+    #[allow(clippy::too_many_arguments)]
     pub fn publish_frame(
         &self,
         tex_id: u32,
@@ -773,6 +826,84 @@ impl OpenGLImage {
     }
 }
 
+#[cfg(target_os = "macos")]
+impl OpenGLClient {
+    /// The new frame's image and its size, if there is a non-empty one.
+    fn new_frame_sized(&self) -> Option<(OpenGLImage, u32, u32)> {
+        if !self.has_new_frame() {
+            return None;
+        }
+        let image = self.new_frame_image()?;
+        let (w, h) = image.texture_size();
+        let (width, height) = (w as u32, h as u32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some((image, width, height))
+    }
+}
+
+/// Reads each new frame's rectangle texture back as RGBA. The client's CGL context must be current.
+#[cfg(target_os = "macos")]
+impl FrameReceiver for OpenGLClient {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        let (image, width, height) = self.new_frame_sized()?;
+        let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+        gl_read_texture_rectangle_rgba8(
+            image.texture_name(),
+            width as usize,
+            height as usize,
+            frame.data_mut(),
+        );
+        Some(frame)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_valid()
+    }
+}
+
+/// Copies: the frame is a GPU texture read back into CPU memory.
+#[cfg(target_os = "macos")]
+impl LockingReceiver for OpenGLClient {}
+
+/// Reads the texture back into a pooled RGBA frame.
+#[cfg(target_os = "macos")]
+impl PooledReceiver for OpenGLClient {
+    fn receive_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        let (image, width, height) = self.new_frame_sized()?;
+        let mut frame = pool.get(width, height, PixelFormat::Rgba8);
+        gl_read_texture_rectangle_rgba8(
+            image.texture_name(),
+            width as usize,
+            height as usize,
+            frame.data_mut(),
+        );
+        Some(frame)
+    }
+}
+
+/// Uploads each frame to a temporary rectangle texture and publishes it. The server's CGL context must be current.
+#[cfg(target_os = "macos")]
+impl FrameSender for OpenGLServer {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        let request = PublishRequest::new(frame.size());
+        let rgba = frame.to_format(PixelFormat::Rgba8);
+        let pixels = rgba.extract(&request)?;
+        let tex_id = gl_create_texture_rectangle_rgba8(
+            frame.width() as usize,
+            frame.height() as usize,
+            &pixels,
+        );
+        if tex_id == 0 {
+            return Err(PublishError::Backend);
+        }
+        let result = self.publish(tex_id, GL_TEXTURE_RECTANGLE, &request);
+        gl_delete_texture(tex_id);
+        result
+    }
+}
+
 impl Drop for OpenGLImage {
     fn drop(&mut self) {
         #[cfg(target_os = "macos")]
@@ -866,7 +997,34 @@ impl MetalServer {
         None
     }
 
+    /// Publish the region of `request` from a Metal texture. You must commit `command_buffer`.
+    /// `request` carries the texture's size so the region can be validated before publishing.
+    pub fn publish(
+        &self,
+        texture: MTLTexturePtr,
+        command_buffer: MTLCommandBufferPtr,
+        request: &PublishRequest,
+    ) -> Result<(), PublishError> {
+        if texture.is_null() || command_buffer.is_null() {
+            return Err(PublishError::NullTexture);
+        }
+        request.validate()?;
+        let region = request.publish_region();
+        self.publish_frame(
+            texture,
+            command_buffer,
+            region.x as f64,
+            region.y as f64,
+            region.width as f64,
+            region.height as f64,
+            request.is_flipped(),
+        );
+        Ok(())
+    }
+
     /// Publish a frame from a Metal texture. Region (x, y, w, h). You must commit `command_buffer`.
+    /// Prefer `publish`, which validates the region.
+    #[allow(clippy::too_many_arguments)]
     pub fn publish_frame(
         &self,
         texture: MTLTexturePtr,
@@ -1143,6 +1301,48 @@ impl Spout {
         }
     }
 
+    /// Send the region of `request` from an OpenGL texture. Spout copies from the texture origin,
+    /// so the region must start at (0, 0); other regions return `PublishError::UnsupportedRegion`.
+    pub fn sender_publish_texture(
+        &self,
+        tex_id: u32,
+        target: u32,
+        request: &PublishRequest,
+    ) -> Result<(), PublishError> {
+        request.validate()?;
+        let region = request.publish_region();
+        if region.x != 0 || region.y != 0 {
+            return Err(PublishError::UnsupportedRegion(region));
+        }
+        if self.sender_send_texture(tex_id, target, region.width, region.height, request.is_flipped()) {
+            Ok(())
+        } else {
+            Err(PublishError::Backend)
+        }
+    }
+
+    /// Send the region of `request` from a CPU frame. Sub-rectangles and padded rows are
+    /// copied out using the frame's stride, so any region inside the frame can be published.
+    pub fn sender_publish_frame(
+        &self,
+        frame: &Frame,
+        request: &PublishRequest,
+    ) -> Result<(), PublishError> {
+        let pixels = frame.extract(request)?;
+        let region = request.publish_region();
+        if self.sender_send_image(
+            &pixels,
+            region.width,
+            region.height,
+            frame.format().gl_format(),
+            request.is_flipped(),
+        ) {
+            Ok(())
+        } else {
+            Err(PublishError::Backend)
+        }
+    }
+
     /// Release the sender and free resources.
     pub fn sender_release(&self) {
         unsafe { spout_ffi::spout_sender_release(self.handle.as_ptr()) };
@@ -1380,21 +1580,15 @@ impl Spout {
 
     /// Signal a frame-sync event for a sender.
     pub fn set_frame_sync(&self, sendername: Option<&str>) {
-        let ptr = sendername
-            .and_then(|s| std::ffi::CString::new(s).ok())
-            .as_ref()
-            .map(|c| c.as_ptr())
-            .unwrap_or(std::ptr::null());
+        let name = sendername.and_then(|s| std::ffi::CString::new(s).ok());
+        let ptr = name.as_ref().map_or(std::ptr::null(), |c| c.as_ptr());
         unsafe { spout_ffi::spout_set_frame_sync(self.handle.as_ptr(), ptr) };
     }
 
     /// Wait (or poll with timeout 0) for a frame-sync event.
     pub fn wait_frame_sync(&self, sendername: Option<&str>, timeout_ms: u32) -> bool {
-        let ptr = sendername
-            .and_then(|s| std::ffi::CString::new(s).ok())
-            .as_ref()
-            .map(|c| c.as_ptr())
-            .unwrap_or(std::ptr::null());
+        let name = sendername.and_then(|s| std::ffi::CString::new(s).ok());
+        let ptr = name.as_ref().map_or(std::ptr::null(), |c| c.as_ptr());
         unsafe { spout_ffi::spout_wait_frame_sync(self.handle.as_ptr(), ptr, timeout_ms) }
     }
 
@@ -1450,6 +1644,14 @@ impl Spout {
         }
     }
 
+    /// The sender's memory buffer as a `MemoryBuffer`, for use with `MessageSender` / `MessageReceiver`.
+    pub fn memory_buffer<'a>(&'a self, sendername: &str) -> SpoutMemoryBuffer<'a> {
+        SpoutMemoryBuffer {
+            spout: self,
+            sendername: sendername.to_string(),
+        }
+    }
+
     /// Maximum number of registered senders allowed by Spout.
     pub fn max_senders(&self) -> i32 {
         unsafe { spout_ffi::spout_get_max_senders(self.handle.as_ptr()) }
@@ -1486,6 +1688,109 @@ impl Spout {
     }
 }
 
+/// Receives through `receiver_receive_image` as RGBA. Returns `None` while connecting and on the
+/// call that reports a size change; the next call allocates the new size. With frame sync
+/// enabled, signals the sender's sync event after each frame (see `SyncSender for Spout`).
+#[cfg(target_os = "windows")]
+impl FrameReceiver for Spout {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        let (width, height) = self.receiver_frame_size()?;
+        let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+        self.receive_into(&mut frame).then_some(frame)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.receiver_is_connected()
+    }
+}
+
+/// Copies: Spout reads the sender's texture, or in CPU mode its shared memory, into our buffer
+/// and lends no pointer to either.
+#[cfg(target_os = "windows")]
+impl LockingReceiver for Spout {}
+
+/// Receives into pooled RGBA frames; frames that turn out not to be new go straight back.
+#[cfg(target_os = "windows")]
+impl PooledReceiver for Spout {
+    fn receive_pooled(&mut self, pool: &FramePool) -> Option<PooledFrame> {
+        let (width, height) = self.receiver_frame_size()?;
+        let mut frame = pool.get(width, height, PixelFormat::Rgba8);
+        self.receive_into(&mut frame).then_some(frame)
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Spout {
+    /// The sender's size, or `None` while connecting.
+    fn receiver_frame_size(&self) -> Option<(u32, u32)> {
+        let width = self.receiver_sender_width();
+        let height = self.receiver_sender_height();
+        if width == 0 || height == 0 {
+            // Texture 0 only connects and picks up the sender size.
+            self.receiver_receive_texture(0, 0, false);
+            return None;
+        }
+        Some((width, height))
+    }
+
+    /// Receive into `frame`, sized by `receiver_frame_size`. True if it now holds a new frame.
+    fn receive_into(&self, frame: &mut Frame) -> bool {
+        if !self.receiver_receive_image(frame.data_mut(), PixelFormat::Rgba8.gl_format(), false) {
+            return false;
+        }
+        if self.receiver_is_updated() || !self.receiver_is_frame_new() {
+            return false;
+        }
+        if self.is_frame_sync_enabled() {
+            self.set_frame_sync(self.receiver_sender_name().as_deref());
+        }
+        true
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl FrameSender for Spout {
+    fn send_frame(&mut self, frame: &Frame) -> Result<(), PublishError> {
+        self.sender_publish_frame(frame, &PublishRequest::new(frame.size()))
+    }
+}
+
+/// Waits on the sender's frame-sync event, which receivers signal from `receive_frame`. Needs
+/// `enable_frame_sync(true)` on both sides; returns true at once while it is off or before the
+/// first frame. The event does not count receivers, so `SyncMode::All` waits like `Any`.
+#[cfg(target_os = "windows")]
+impl SyncSender for Spout {
+    fn wait_consumed(&mut self, _mode: SyncMode, timeout: Duration) -> bool {
+        if !self.is_frame_sync_enabled() || !self.sender_is_initialized() {
+            return true;
+        }
+        let timeout_ms = timeout.as_millis().min(u32::MAX as u128) as u32;
+        self.wait_frame_sync(self.sender_name().as_deref(), timeout_ms)
+    }
+}
+
+/// Polls `receive_frame`; Spout has no event for new frames that the receiver can wait on.
+#[cfg(target_os = "windows")]
+impl SyncReceiver for Spout {}
+
+/// A Spout sender's shared memory buffer (see `Spout::memory_buffer`).
+#[cfg(target_os = "windows")]
+pub struct SpoutMemoryBuffer<'a> {
+    spout: &'a Spout,
+    sendername: String,
+}
+
+#[cfg(target_os = "windows")]
+impl MemoryBuffer for SpoutMemoryBuffer<'_> {
+    fn write_bytes(&self, data: &[u8]) -> bool {
+        self.spout.write_memory_buffer(&self.sendername, data)
+    }
+
+    fn read_bytes(&self, out: &mut [u8]) -> usize {
+        self.spout.read_memory_buffer(&self.sendername, out)
+    }
+}
+
 /// Sender info from discovery (width, height, DX share handle, format).
 #[cfg(target_os = "windows")]
 #[derive(Debug, Clone)]
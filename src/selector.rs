@@ -0,0 +1,286 @@
+//! Source selectors that survive app restarts.
+//!
+//! Syphon UUIDs change every time a server app restarts, so a stored UUID alone goes stale. A
+//! `SourceSelector` tries its rules in `priority` order — the UUID, then the exact name and app
+//! name, then a glob or regex on the name — and returns the first source a rule matches. It is
+//! plain data (serializable with the `serde` feature) and can be resolved again against a fresh
+//! list whenever the directory changes: `discover_sources`, `syphon_sources(&directory)` or
+//! `spout_sources(&spout)`.
+
+use std::fmt;
+
+use regex::Regex;
+
+use crate::description::SourceDescription;
+use crate::discovery::{discover_sources, SourceBackend, SourceInfo};
+
+/// One way of matching a source, in the order given by `SourceSelector::priority`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SelectorRule {
+    /// `uuid` equals the source UUID.
+    Uuid,
+    /// `name` and `app_name` both equal the source's.
+    NameAndApp,
+    /// `pattern` matches the source name.
+    Pattern,
+}
+
+/// A glob (`*` any run of characters, `?` one character, matched against the whole name) or a
+/// regex (unanchored; use `^...$` to match the whole name).
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "PatternRepr", into = "PatternRepr")
+)]
+pub enum NamePattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub fn glob(pattern: &str) -> Self {
+        NamePattern::Glob(pattern.to_string())
+    }
+
+    /// Compile a regex pattern.
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(NamePattern::Regex)
+    }
+
+    /// The pattern as written.
+    pub fn as_str(&self) -> &str {
+        match self {
+            NamePattern::Glob(pattern) => pattern,
+            NamePattern::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(pattern) => glob_matches(pattern, name),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl fmt::Debug for NamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamePattern::Glob(pattern) => f.debug_tuple("Glob").field(pattern).finish(),
+            NamePattern::Regex(regex) => f.debug_tuple("Regex").field(&regex.as_str()).finish(),
+        }
+    }
+}
+
+/// Patterns are equal when they are the same kind and read the same.
+impl PartialEq for NamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (NamePattern::Glob(_), NamePattern::Glob(_))
+                | (NamePattern::Regex(_), NamePattern::Regex(_))
+        ) && self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for NamePattern {}
+
+/// Serialized form of `NamePattern`: `{"glob": "..."}` or `{"regex": "..."}`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PatternRepr {
+    Glob(String),
+    Regex(String),
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PatternRepr> for NamePattern {
+    type Error = regex::Error;
+
+    fn try_from(repr: PatternRepr) -> Result<Self, Self::Error> {
+        match repr {
+            PatternRepr::Glob(pattern) => Ok(NamePattern::Glob(pattern)),
+            PatternRepr::Regex(pattern) => NamePattern::regex(&pattern),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<NamePattern> for PatternRepr {
+    fn from(pattern: NamePattern) -> Self {
+        match pattern {
+            NamePattern::Glob(pattern) => PatternRepr::Glob(pattern),
+            NamePattern::Regex(regex) => PatternRepr::Regex(regex.as_str().to_string()),
+        }
+    }
+}
+
+/// `*` and `?` wildcards over chars; everything else matches literally.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is currently standing in for.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` absorb one more character and retry.
+                Some((after, from)) => {
+                    star = Some((after, from + 1));
+                    p = after;
+                    n = from + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Which source a selector resolved to, and the rule that found it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorMatch {
+    pub source: SourceInfo,
+    pub rule: SelectorRule,
+}
+
+/// Finds a source by UUID, name and app name, or name pattern. Rules whose field is unset are
+/// skipped, as are rules left out of `priority`. Among several sources matching the same rule
+/// the first in list order wins (`discover_sources` lists local, then Syphon, then Spout).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct SourceSelector {
+    /// Only consider sources on this backend.
+    pub backend: Option<SourceBackend>,
+    pub uuid: Option<String>,
+    /// Matched together with `app_name` by `SelectorRule::NameAndApp`.
+    pub name: Option<String>,
+    pub app_name: Option<String>,
+    pub pattern: Option<NamePattern>,
+    /// Rules to try, first to last. Default: UUID, name and app, pattern.
+    pub priority: Vec<SelectorRule>,
+}
+
+impl Default for SourceSelector {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            uuid: None,
+            name: None,
+            app_name: None,
+            pattern: None,
+            priority: vec![
+                SelectorRule::Uuid,
+                SelectorRule::NameAndApp,
+                SelectorRule::Pattern,
+            ],
+        }
+    }
+}
+
+impl SourceSelector {
+    /// Match by name and app name on any backend.
+    pub fn name_and_app(name: &str, app_name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            app_name: Some(app_name.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Match by name pattern on any backend.
+    pub fn pattern(pattern: NamePattern) -> Self {
+        Self {
+            pattern: Some(pattern),
+            ..Self::default()
+        }
+    }
+
+    /// UUID first, then name and app name, on the described source's backend.
+    pub fn from_description(desc: &SourceDescription) -> Self {
+        Self {
+            backend: Some(desc.backend),
+            uuid: Some(desc.uuid.clone()),
+            name: Some(desc.name.clone()),
+            app_name: Some(desc.app_name.clone()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: NamePattern) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    pub fn with_priority(mut self, priority: &[SelectorRule]) -> Self {
+        self.priority = priority.to_vec();
+        self
+    }
+
+    /// True if `rule` is set up on this selector and matches `source`.
+    pub fn matches(&self, rule: SelectorRule, source: &SourceInfo) -> bool {
+        if self
+            .backend
+            .is_some_and(|backend| backend != source.backend)
+        {
+            return false;
+        }
+        match rule {
+            SelectorRule::Uuid => self.uuid.as_deref() == Some(source.uuid.as_str()),
+            SelectorRule::NameAndApp => match (&self.name, &self.app_name) {
+                (Some(name), Some(app_name)) => {
+                    *name == source.name && *app_name == source.app_name
+                }
+                _ => false,
+            },
+            SelectorRule::Pattern => self
+                .pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.matches(&source.name)),
+        }
+    }
+
+    /// The best match in `sources`, by rule priority then list order.
+    pub fn resolve_in(&self, sources: &[SourceInfo]) -> Option<SelectorMatch> {
+        self.priority.iter().find_map(|&rule| {
+            sources
+                .iter()
+                .find(|source| self.matches(rule, source))
+                .map(|source| SelectorMatch {
+                    source: source.clone(),
+                    rule,
+                })
+        })
+    }
+
+    /// The best match among all sources currently visible.
+    pub fn resolve(&self) -> Option<SelectorMatch> {
+        self.resolve_in(&discover_sources())
+    }
+
+    /// Point the UUID rule at `source`, e.g. after a name or pattern match found a restarted
+    /// app, so later resolves find it by UUID.
+    pub fn remember(&mut self, source: &SourceInfo) {
+        self.uuid = Some(source.uuid.clone());
+    }
+}
@@ -0,0 +1,29 @@
+//! Receive-side counters, reported by `FrameReceiver::stats`.
+
+use std::time::Instant;
+
+/// What a receiver has seen since it was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReceiverStats {
+    /// Frames taken from the transport, including any later found corrupted.
+    pub frames_received: u64,
+    /// Frames the sender published that this receiver never saw (it polled too slowly).
+    pub frames_dropped: u64,
+    /// Frames whose checksum matched (integrity mode only).
+    pub frames_verified: u64,
+    /// Frames whose checksum matched none sent recently: corrupted or torn (integrity mode only).
+    pub frames_corrupted: u64,
+    /// Frames that arrived before any checksum was available (integrity mode only).
+    pub frames_unverified: u64,
+    /// When the last frame was received.
+    pub last_frame_at: Option<Instant>,
+}
+
+impl ReceiverStats {
+    /// Record a received frame; `skipped` is how many frames were missed before it.
+    pub(crate) fn record_frame(&mut self, skipped: u64) {
+        self.frames_received += 1;
+        self.frames_dropped += skipped;
+        self.last_frame_at = Some(Instant::now());
+    }
+}
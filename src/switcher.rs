@@ -0,0 +1,249 @@
+//! Switching between sources without a black flash.
+//!
+//! Connecting a receiver to a new sender (`Spout::receiver_set_name`, a new `OpenGLClient`)
+//! leaves it without a frame for a while. `SourceSwitcher` keeps showing the current source
+//! while the next one connects, and only once the next source has delivered its first frame does
+//! it cut or crossfade over to it. The switcher is itself a `FrameReceiver`: each
+//! `receive_frame` pulls from its sources and returns the output frame, and `publish` sends it to
+//! any `FrameSender` (e.g. a `LocalServer`, or a server from `create_sender`).
+
+use std::time::{Duration, Instant};
+
+use crate::compositor::{compose_layers, LayerStyle, Layout};
+use crate::discovery::{connect_source, SourceInfo};
+use crate::frame::{Frame, FrameReceiver, FrameSender};
+use crate::geometry::{PublishError, Rect};
+
+/// How the output moves to the next source once it has a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Transition {
+    /// Switch on the next source's first frame.
+    #[default]
+    Cut,
+    /// Blend from the current to the next source over this long. Both keep updating meanwhile.
+    Crossfade(Duration),
+}
+
+/// What a `SourceSwitcher` is doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SwitchState {
+    /// No source yet.
+    Idle,
+    /// Showing the current source.
+    Live,
+    /// Showing the current source while the next one connects.
+    Waiting,
+    /// Crossfading; `progress` goes from 0 to 1.
+    Fading { progress: f32 },
+}
+
+struct Input {
+    receiver: Box<dyn FrameReceiver>,
+    frame: Option<Frame>,
+}
+
+impl Input {
+    fn new(receiver: Box<dyn FrameReceiver>) -> Self {
+        Self {
+            receiver,
+            frame: None,
+        }
+    }
+
+    /// Pull a new frame, if any. Returns true if one arrived.
+    fn update(&mut self) -> bool {
+        match self.receiver.receive_frame() {
+            Some(frame) => {
+                self.frame = Some(frame);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Shows one source at a time and moves to the next without gaps. See the module docs.
+pub struct SourceSwitcher {
+    transition: Transition,
+    current: Option<Input>,
+    next: Option<Input>,
+    /// When the crossfade to `next` started, and how long it lasts.
+    fade: Option<(Instant, Duration)>,
+}
+
+impl Default for SourceSwitcher {
+    fn default() -> Self {
+        Self::new(Transition::Cut)
+    }
+}
+
+impl SourceSwitcher {
+    pub fn new(transition: Transition) -> Self {
+        Self {
+            transition,
+            current: None,
+            next: None,
+            fade: None,
+        }
+    }
+
+    pub fn transition(&self) -> Transition {
+        self.transition
+    }
+
+    /// Applies from the next switch on; a crossfade in progress keeps its duration.
+    pub fn set_transition(&mut self, transition: Transition) {
+        self.transition = transition;
+    }
+
+    /// Start switching to `receiver`. The current source stays on the output until `receiver`
+    /// delivers a frame. Replaces a switch that has not started fading yet; during a fade, the
+    /// fade completes first.
+    pub fn switch_to(&mut self, receiver: impl FrameReceiver + 'static) {
+        if self.fade.is_some() {
+            self.finish_fade();
+        }
+        self.next = Some(Input::new(Box::new(receiver)));
+    }
+
+    /// Connect to `source` and start switching to it. Returns false if it cannot be opened.
+    pub fn switch_to_source(&mut self, source: &SourceInfo) -> bool {
+        match connect_source(source) {
+            Some(receiver) => {
+                self.switch_to(receiver);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the next source if it has not started fading in.
+    pub fn cancel_switch(&mut self) {
+        if self.fade.is_none() {
+            self.next = None;
+        }
+    }
+
+    pub fn state(&self) -> SwitchState {
+        match (&self.current, &self.next, self.fade) {
+            (_, Some(_), Some(fade)) => SwitchState::Fading {
+                progress: fade_progress(fade, Instant::now()),
+            },
+            (_, Some(_), None) => SwitchState::Waiting,
+            (Some(_), None, _) => SwitchState::Live,
+            (None, None, _) => SwitchState::Idle,
+        }
+    }
+
+    /// The frame most recently taken from the current source.
+    pub fn current_frame(&self) -> Option<&Frame> {
+        self.current.as_ref()?.frame.as_ref()
+    }
+
+    /// Pull from the sources and send the output to `sender` if there is a new one. Returns
+    /// whether a frame was sent.
+    pub fn publish(&mut self, sender: &mut dyn FrameSender) -> Result<bool, PublishError> {
+        match self.receive_frame() {
+            Some(frame) => sender.send_frame(&frame).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    fn transition_duration(&self) -> Option<Duration> {
+        match self.transition {
+            Transition::Cut => None,
+            Transition::Crossfade(duration) => Some(duration),
+        }
+    }
+
+    /// The current frame, if `changed`.
+    fn output_if(&self, changed: bool) -> Option<Frame> {
+        if changed {
+            self.current_frame().cloned()
+        } else {
+            None
+        }
+    }
+
+    fn finish_fade(&mut self) {
+        self.fade = None;
+        self.current = self.next.take();
+    }
+
+    /// The crossfade between the current and next frames at `progress`, at the next frame's
+    /// size and format. The current frame is scaled to fit.
+    fn blend(&self, progress: f32) -> Option<Frame> {
+        let to = self.next.as_ref()?.frame.as_ref()?;
+        let from = self.current.as_ref().and_then(|input| input.frame.as_ref());
+        let size = to.size();
+        let full = Rect::new(0, 0, size.width, size.height);
+        let layers = [
+            (from, LayerStyle::default()),
+            (
+                Some(to),
+                LayerStyle {
+                    opacity: progress,
+                    ..LayerStyle::default()
+                },
+            ),
+        ];
+        let out = compose_layers(
+            size,
+            &Layout::Custom(vec![full, full]),
+            [0, 0, 0, 255],
+            &layers,
+        );
+        Some(out.to_format(to.format()))
+    }
+}
+
+/// How far a crossfade that started at `start` and lasts `duration` is at `now`, 0.0-1.0.
+fn fade_progress((start, duration): (Instant, Duration), now: Instant) -> f32 {
+    if duration.is_zero() {
+        return 1.0;
+    }
+    (now.duration_since(start).as_secs_f32() / duration.as_secs_f32()).min(1.0)
+}
+
+impl FrameReceiver for SourceSwitcher {
+    /// The output frame, if it changed since the last call: the current source's new frame, or a
+    /// step of the crossfade.
+    fn receive_frame(&mut self) -> Option<Frame> {
+        let current_changed = self.current.as_mut().is_some_and(Input::update);
+        let Some(next) = self.next.as_mut() else {
+            return self.output_if(current_changed);
+        };
+        next.update();
+        if self.fade.is_none() {
+            if next.frame.is_none() {
+                if !next.receiver.is_connected() {
+                    // Gone before its first frame: stay on the current source.
+                    self.next = None;
+                }
+                return self.output_if(current_changed);
+            }
+            match self.transition_duration() {
+                Some(duration) if !duration.is_zero() && self.current_frame().is_some() => {
+                    self.fade = Some((Instant::now(), duration));
+                }
+                _ => {
+                    self.finish_fade();
+                    return self.output_if(true);
+                }
+            }
+        }
+        let progress = fade_progress(self.fade?, Instant::now());
+        if progress >= 1.0 {
+            self.finish_fade();
+            return self.output_if(true);
+        }
+        self.blend(progress)
+    }
+
+    /// True while the source on the output is connected.
+    fn is_connected(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|input| input.receiver.is_connected())
+    }
+}
@@ -0,0 +1,89 @@
+//! Frame synchronization between senders and receivers.
+//!
+//! By default senders publish as fast as they like and receivers take whatever frame is newest,
+//! so a fast sender drops frames a slow receiver never sees. `SyncSender::wait_consumed` lets a
+//! sender hold the next frame until one or all of its receivers have taken the previous one, and
+//! `SyncReceiver::wait_frame` lets a receiver block until the next frame arrives.
+//!
+//! `LocalServer` / `LocalClient` count takes directly; `MemfdClient`s acknowledge each frame over
+//! the socket. On Windows, `Spout` maps both onto its frame-sync event (`enable_frame_sync`): the
+//! receiver signals it after each frame, and the sender waits on it. That event cannot count
+//! receivers, so there `SyncMode::All` behaves like `SyncMode::Any`.
+
+use std::time::{Duration, Instant};
+
+use crate::frame::{Frame, FrameReceiver, FrameSender};
+use crate::geometry::PublishError;
+
+/// How many receivers a sender waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SyncMode {
+    /// At least one receiver took the previous frame.
+    #[default]
+    Any,
+    /// Every attached receiver took the previous frame.
+    All,
+}
+
+impl SyncMode {
+    /// True once `taken` of `receivers` receivers have the frame. With no receivers there is
+    /// nothing to wait for.
+    pub(crate) fn satisfied(self, taken: usize, receivers: usize) -> bool {
+        receivers == 0
+            || match self {
+                SyncMode::Any => taken > 0,
+                SyncMode::All => taken >= receivers,
+            }
+    }
+}
+
+/// A `FrameSender` that can wait for its receivers.
+pub trait SyncSender: FrameSender {
+    /// Block until receivers have taken the last frame sent, as `mode` requires, or `timeout`
+    /// passes. Returns false on timeout. Returns true at once before the first frame and while
+    /// no receiver is attached.
+    fn wait_consumed(&mut self, mode: SyncMode, timeout: Duration) -> bool;
+
+    /// Wait for the previous frame to be taken, then send `frame`. The frame is sent even if the
+    /// wait timed out; returns whether it did not.
+    fn send_frame_synced(
+        &mut self,
+        frame: &Frame,
+        mode: SyncMode,
+        timeout: Duration,
+    ) -> Result<bool, PublishError> {
+        let consumed = self.wait_consumed(mode, timeout);
+        self.send_frame(frame)?;
+        Ok(consumed)
+    }
+}
+
+/// A `FrameReceiver` that can block until the next frame.
+pub trait SyncReceiver: FrameReceiver {
+    /// The next new frame, waiting up to `timeout` for one. Returns `None` on timeout or when
+    /// the source goes away. The default polls `receive_frame` every millisecond.
+    fn wait_frame(&mut self, timeout: Duration) -> Option<Frame> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(frame) = self.receive_frame() {
+                return Some(frame);
+            }
+            if Instant::now() >= deadline || !self.is_connected() {
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+impl<S: SyncSender + ?Sized> SyncSender for Box<S> {
+    fn wait_consumed(&mut self, mode: SyncMode, timeout: Duration) -> bool {
+        (**self).wait_consumed(mode, timeout)
+    }
+}
+
+impl<R: SyncReceiver + ?Sized> SyncReceiver for Box<R> {
+    fn wait_frame(&mut self, timeout: Duration) -> Option<Frame> {
+        (**self).wait_frame(timeout)
+    }
+}
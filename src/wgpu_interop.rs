@@ -0,0 +1,500 @@
+//! wgpu texture interop (feature `wgpu`).
+//!
+//! `WgpuSender` publishes a `wgpu::Texture` on any backend and `WgpuReceiver` uploads received
+//! frames into one. On macOS, when the device runs on Metal and the sender is a Syphon server,
+//! textures are published natively: a `MetalServer` on wgpu's own `MTLDevice` blits them on
+//! wgpu's command queue. `Bgra8Unorm` textures are blitted as they are; other formats are read
+//! back, swizzled and uploaded into a `Bgra8Unorm` texture that is blitted instead. Everywhere
+//! else textures take the staged path: the texture is copied into a mappable buffer and
+//! published as a CPU `Frame`, and received frames are written with `Queue::write_texture`. The
+//! staged path works on any adapter, including software ones (llvmpipe through GL, lavapipe
+//! through Vulkan).
+//!
+//! Textures must be 2D, 8-bit RGBA or BGRA (sRGB or not). Textures that are read back (staged,
+//! or native but not `Bgra8Unorm`) need `COPY_SRC` usage. Only mip level 0 of layer 0 is used.
+
+use std::fmt;
+use std::sync::mpsc;
+
+use crate::discovery::{connect_source, create_sender, SourceBackend, SourceInfo};
+use crate::frame::{Frame, FrameReceiver, FrameSender, PixelFormat};
+use crate::geometry::PublishError;
+#[cfg(target_os = "macos")]
+use crate::{
+    geometry::{PublishRequest, Size},
+    safe::{MTLCommandBufferPtr, MTLDevicePtr, MTLTexturePtr, MetalServer},
+};
+
+/// How a `WgpuSender` gets textures to the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WgpuPath {
+    /// GPU to GPU, without leaving the device (Syphon Metal on macOS). Only `Bgra8Unorm`
+    /// textures skip the CPU; see the module docs.
+    Native,
+    /// Copied through a CPU `Frame`.
+    Staged,
+}
+
+#[derive(Debug)]
+pub enum WgpuError {
+    /// The texture format is not 8-bit RGBA or BGRA.
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// The texture lacks a usage the copy needs (`COPY_SRC` to publish, `COPY_DST` to receive).
+    MissingUsage(wgpu::TextureUsages),
+    /// The frame and texture sizes differ.
+    SizeMismatch,
+    /// Mapping the staging buffer failed.
+    Map(wgpu::BufferAsyncError),
+    /// Waiting for the GPU failed.
+    Poll(wgpu::PollError),
+    /// The backend rejected the frame.
+    Publish(PublishError),
+}
+
+impl fmt::Display for WgpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WgpuError::UnsupportedFormat(format) => {
+                write!(f, "unsupported texture format {format:?}")
+            }
+            WgpuError::MissingUsage(usage) => write!(f, "texture needs {usage:?} usage"),
+            WgpuError::SizeMismatch => write!(f, "frame and texture sizes differ"),
+            WgpuError::Map(err) => write!(f, "mapping the staging buffer failed: {err}"),
+            WgpuError::Poll(err) => write!(f, "waiting for the GPU failed: {err}"),
+            WgpuError::Publish(err) => write!(f, "publish failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for WgpuError {}
+
+impl From<PublishError> for WgpuError {
+    fn from(err: PublishError) -> Self {
+        WgpuError::Publish(err)
+    }
+}
+
+/// The pixel format matching a texture format, if it can be shared.
+pub fn pixel_format_for_texture(format: wgpu::TextureFormat) -> Option<PixelFormat> {
+    match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {
+            Some(PixelFormat::Rgba8)
+        }
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+            Some(PixelFormat::Bgra8)
+        }
+        _ => None,
+    }
+}
+
+/// The texture format for a pixel format, sRGB-encoded or not.
+pub fn texture_format_for_pixel(format: PixelFormat, srgb: bool) -> wgpu::TextureFormat {
+    match (format, srgb) {
+        (PixelFormat::Rgba8, false) => wgpu::TextureFormat::Rgba8Unorm,
+        (PixelFormat::Rgba8, true) => wgpu::TextureFormat::Rgba8UnormSrgb,
+        (PixelFormat::Bgra8, false) => wgpu::TextureFormat::Bgra8Unorm,
+        (PixelFormat::Bgra8, true) => wgpu::TextureFormat::Bgra8UnormSrgb,
+    }
+}
+
+/// A mappable buffer reused across reads while the texture size stays the same.
+#[derive(Default)]
+struct Staging {
+    buffer: Option<wgpu::Buffer>,
+}
+
+impl Staging {
+    fn read(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) -> Result<Frame, WgpuError> {
+        let format = pixel_format_for_texture(texture.format())
+            .ok_or(WgpuError::UnsupportedFormat(texture.format()))?;
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(WgpuError::MissingUsage(wgpu::TextureUsages::COPY_SRC));
+        }
+        let (width, height) = (texture.width(), texture.height());
+        // Buffer rows must be 256-byte aligned; the frame keeps the padding as its stride.
+        let stride = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let size = stride as u64 * height as u64;
+        if self.buffer.as_ref().is_none_or(|b| b.size() != size) {
+            self.buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("rusty-syphon-spout staging"),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        let buffer = self.buffer.as_ref().unwrap();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("rusty-syphon-spout read"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(stride),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([encoder.finish()]);
+
+        let (done, mapped) = mpsc::channel();
+        buffer.map_async(wgpu::MapMode::Read, .., move |result| {
+            let _ = done.send(result);
+        });
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(WgpuError::Poll)?;
+        mapped
+            .recv()
+            .expect("map callback runs during poll")
+            .map_err(WgpuError::Map)?;
+        let data = buffer
+            .get_mapped_range(..)
+            .expect("buffer was just mapped")
+            .to_vec();
+        buffer.unmap();
+        Ok(
+            Frame::from_vec_with_stride(width, height, stride as usize, format, data)
+                .expect("staging buffer holds every row"),
+        )
+    }
+}
+
+/// Copy `texture` into a `Frame` through a staging buffer, waiting for the GPU. The texture
+/// needs `COPY_SRC` usage.
+pub fn read_wgpu_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Frame, WgpuError> {
+    Staging::default().read(device, queue, texture)
+}
+
+/// Write `frame` into `texture`, swapping channels if the texture's order differs. The texture
+/// needs `COPY_DST` usage and the frame's size.
+pub fn write_wgpu_texture(
+    queue: &wgpu::Queue,
+    frame: &Frame,
+    texture: &wgpu::Texture,
+) -> Result<(), WgpuError> {
+    let format = pixel_format_for_texture(texture.format())
+        .ok_or(WgpuError::UnsupportedFormat(texture.format()))?;
+    if !texture.usage().contains(wgpu::TextureUsages::COPY_DST) {
+        return Err(WgpuError::MissingUsage(wgpu::TextureUsages::COPY_DST));
+    }
+    if frame.width() != texture.width() || frame.height() != texture.height() {
+        return Err(WgpuError::SizeMismatch);
+    }
+    let converted;
+    let frame = if frame.format() == format {
+        frame
+    } else {
+        converted = frame.to_format(format);
+        &converted
+    };
+    let data = frame.data();
+    // The last row may stop at the pixels, without padding.
+    let len = frame.stride() * (frame.height() as usize - 1) + frame.row_bytes();
+    queue.write_texture(
+        texture.as_image_copy(),
+        &data[..len],
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(frame.stride() as u32),
+            rows_per_image: Some(frame.height()),
+        },
+        texture.size(),
+    );
+    Ok(())
+}
+
+enum Target {
+    Staged {
+        sender: Box<dyn FrameSender>,
+        staging: Staging,
+    },
+    #[cfg(target_os = "macos")]
+    Native(native::Native),
+}
+
+/// Publishes `wgpu::Texture`s as a server.
+pub struct WgpuSender {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    target: Target,
+}
+
+impl WgpuSender {
+    /// Publish as `name` on `backend`: natively for Syphon when the device runs on Metal (with
+    /// a CPU copy for formats other than `Bgra8Unorm`), staged otherwise. Returns `None` if the backend is unavailable here.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        backend: SourceBackend,
+        name: &str,
+    ) -> Option<Self> {
+        #[cfg(target_os = "macos")]
+        if backend == SourceBackend::Syphon {
+            if let Some(native) = native::Native::new(device, name) {
+                return Some(Self {
+                    device: device.clone(),
+                    queue: queue.clone(),
+                    target: Target::Native(native),
+                });
+            }
+        }
+        Some(Self::with_sender(
+            device,
+            queue,
+            create_sender(backend, name)?,
+        ))
+    }
+
+    /// Publish through `sender` with the staged path.
+    pub fn with_sender(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sender: Box<dyn FrameSender>,
+    ) -> Self {
+        Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            target: Target::Staged {
+                sender,
+                staging: Staging::default(),
+            },
+        }
+    }
+
+    pub fn path(&self) -> WgpuPath {
+        match self.target {
+            Target::Staged { .. } => WgpuPath::Staged,
+            #[cfg(target_os = "macos")]
+            Target::Native(_) => WgpuPath::Native,
+        }
+    }
+
+    /// Publish `texture` once the work already submitted to the queue has rendered it.
+    pub fn publish(&mut self, texture: &wgpu::Texture) -> Result<(), WgpuError> {
+        match &mut self.target {
+            Target::Staged { sender, staging } => {
+                let frame = staging.read(&self.device, &self.queue, texture)?;
+                Ok(sender.send_frame(&frame)?)
+            }
+            #[cfg(target_os = "macos")]
+            Target::Native(native) => native.publish(&self.device, &self.queue, texture),
+        }
+    }
+}
+
+/// Receives frames into a `wgpu::Texture` it owns, recreated when the frame size or format
+/// changes.
+pub struct WgpuReceiver {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    receiver: Box<dyn FrameReceiver>,
+    usage: wgpu::TextureUsages,
+    texture: Option<wgpu::Texture>,
+}
+
+impl WgpuReceiver {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        receiver: Box<dyn FrameReceiver>,
+    ) -> Self {
+        Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            receiver,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            texture: None,
+        }
+    }
+
+    /// Connect to `source` (see `connect_source`).
+    pub fn connect(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &SourceInfo,
+    ) -> Option<Self> {
+        Some(Self::new(device, queue, connect_source(source)?))
+    }
+
+    /// Usages of the texture besides `COPY_DST` (default `TEXTURE_BINDING | COPY_SRC`).
+    pub fn usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// The texture holding the last frame, once one arrived.
+    pub fn texture(&self) -> Option<&wgpu::Texture> {
+        self.texture.as_ref()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.receiver.is_connected()
+    }
+
+    /// Upload the next frame, if one arrived, into `texture()`. Returns true if it did; the
+    /// texture may then be a new one.
+    pub fn receive(&mut self) -> Result<bool, WgpuError> {
+        let Some(frame) = self.receiver.receive_frame() else {
+            return Ok(false);
+        };
+        let format = texture_format_for_pixel(frame.format(), false);
+        let reuse = self.texture.as_ref().is_some_and(|t| {
+            t.width() == frame.width() && t.height() == frame.height() && t.format() == format
+        });
+        if !reuse {
+            self.texture = Some(self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("rusty-syphon-spout received"),
+                size: wgpu::Extent3d {
+                    width: frame.width(),
+                    height: frame.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: self.usage | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            }));
+        }
+        write_wgpu_texture(&self.queue, &frame, self.texture.as_ref().unwrap())?;
+        Ok(true)
+    }
+
+    /// Upload the next frame, if one arrived, into the caller's `texture` of the same size.
+    pub fn receive_into(&mut self, texture: &wgpu::Texture) -> Result<bool, WgpuError> {
+        match self.receiver.receive_frame() {
+            Some(frame) => write_wgpu_texture(&self.queue, &frame, texture).map(|()| true),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Syphon Metal publishing on wgpu's own device and queue.
+#[cfg(target_os = "macos")]
+mod native {
+    use std::ffi::{c_char, c_void, CStr};
+
+    use super::*;
+
+    type Metal = wgpu::hal::api::Metal;
+
+    #[link(name = "objc")]
+    extern "C" {
+        fn sel_registerName(name: *const c_char) -> *const c_void;
+        fn objc_msgSend();
+        fn objc_autoreleasePoolPush() -> *mut c_void;
+        fn objc_autoreleasePoolPop(pool: *mut c_void);
+    }
+
+    /// `[receiver selector]` for a method without arguments returning an object or nothing.
+    unsafe fn send(receiver: *mut c_void, selector: &CStr) -> *mut c_void {
+        let send: unsafe extern "C" fn(*mut c_void, *const c_void) -> *mut c_void =
+            std::mem::transmute::<unsafe extern "C" fn(), _>(objc_msgSend);
+        send(receiver, sel_registerName(selector.as_ptr()))
+    }
+
+    /// A Syphon Metal server on wgpu's device, and what other formats need to reach it.
+    pub(super) struct Native {
+        server: MetalServer,
+        staging: Staging,
+        /// `Bgra8Unorm` copy of textures in other formats, kept while the size stays the same.
+        scratch: Option<wgpu::Texture>,
+    }
+
+    impl Native {
+        pub(super) fn new(device: &wgpu::Device, name: &str) -> Option<Self> {
+            let hal = unsafe { device.as_hal::<Metal>() }?;
+            let raw: *const _ = &**hal.raw_device();
+            Some(Self {
+                server: MetalServer::new(Some(name), raw as MTLDevicePtr, None)?,
+                staging: Staging::default(),
+                scratch: None,
+            })
+        }
+
+        /// Blit `texture`, or, unless it is `Bgra8Unorm`, a BGRA copy of it made on the CPU.
+        pub(super) fn publish(
+            &mut self,
+            device: &wgpu::Device,
+            queue: &wgpu::Queue,
+            texture: &wgpu::Texture,
+        ) -> Result<(), WgpuError> {
+            if texture.format() == wgpu::TextureFormat::Bgra8Unorm {
+                return blit(&self.server, queue, texture);
+            }
+            let frame = self.staging.read(device, queue, texture)?;
+            let (width, height) = (texture.width(), texture.height());
+            if self
+                .scratch
+                .as_ref()
+                .is_none_or(|t| t.width() != width || t.height() != height)
+            {
+                self.scratch = Some(device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("rusty-syphon-spout native"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Bgra8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                }));
+            }
+            let scratch = self.scratch.as_ref().unwrap();
+            write_wgpu_texture(queue, &frame, scratch)?;
+            blit(&self.server, queue, scratch)
+        }
+    }
+
+    /// Blit a `Bgra8Unorm` texture into the server on wgpu's queue.
+    fn blit(
+        server: &MetalServer,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) -> Result<(), WgpuError> {
+        // Flush pending `write_texture`s so they are ordered before the blit.
+        queue.submit([]);
+        let hal_texture = unsafe { texture.as_hal::<Metal>() }.ok_or(PublishError::NullTexture)?;
+        let hal_queue = unsafe { queue.as_hal::<Metal>() }.ok_or(PublishError::Backend)?;
+        let raw_texture: *const _ = hal_texture.raw_handle();
+        let raw_queue: *const _ = hal_queue.as_raw();
+        let request = PublishRequest::new(Size::new(texture.width(), texture.height()));
+        unsafe {
+            let pool = objc_autoreleasePoolPush();
+            let command_buffer = send(raw_queue as *mut c_void, c"commandBuffer");
+            let result = server.publish(
+                raw_texture as MTLTexturePtr,
+                command_buffer as MTLCommandBufferPtr,
+                &request,
+            );
+            if !command_buffer.is_null() {
+                send(command_buffer, c"commit");
+            }
+            objc_autoreleasePoolPop(pool);
+            Ok(result?)
+        }
+    }
+}
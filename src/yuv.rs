@@ -0,0 +1,480 @@
+//! Planar and semi-planar YUV frames (NV12, I420, UYVY, P010) and RGB↔YUV conversion.
+//!
+//! Syphon, Spout and `LocalServer` move packed RGBA, so YUV sources (capture cards, decoders) are
+//! converted with `YuvFrame::from_frame` before publishing, and YUV sinks (encoders) convert
+//! received frames with `to_frame`. Chroma is averaged over each subsampled block when encoding
+//! and replicated when decoding. Alpha is dropped (decoded frames are opaque).
+
+use crate::color::ColorSpace;
+use crate::frame::{Frame, PixelFormat};
+
+/// YUV memory layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvFormat {
+    /// 4:2:0, Y plane then one interleaved UV plane, 8-bit.
+    Nv12,
+    /// 4:2:0, separate Y, U and V planes, 8-bit.
+    I420,
+    /// 4:2:2 packed as U Y0 V Y1, 8-bit.
+    Uyvy,
+    /// 4:2:0 like NV12, 10 bits in the high bits of little-endian 16-bit samples.
+    P010,
+}
+
+impl YuvFormat {
+    /// 8 or 10.
+    pub fn bit_depth(&self) -> u32 {
+        match self {
+            YuvFormat::P010 => 10,
+            _ => 8,
+        }
+    }
+
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            YuvFormat::P010 => 2,
+            _ => 1,
+        }
+    }
+
+    /// Horizontal and vertical chroma subsampling factors.
+    pub fn chroma_subsampling(&self) -> (u32, u32) {
+        match self {
+            YuvFormat::Uyvy => (2, 1),
+            _ => (2, 2),
+        }
+    }
+
+    pub fn plane_count(&self) -> usize {
+        match self {
+            YuvFormat::Uyvy => 1,
+            YuvFormat::Nv12 | YuvFormat::P010 => 2,
+            YuvFormat::I420 => 3,
+        }
+    }
+
+    /// Size of a chroma plane (in chroma samples) for a `width` x `height` image. Odd sizes round up.
+    pub fn chroma_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (sx, sy) = self.chroma_subsampling();
+        (width.div_ceil(sx), height.div_ceil(sy))
+    }
+
+    /// Bytes per row of `plane` (tightly packed).
+    pub fn plane_stride(&self, plane: usize, width: u32) -> usize {
+        let (cw, _) = self.chroma_size(width, 1);
+        let bps = self.bytes_per_sample();
+        match (self, plane) {
+            (YuvFormat::Uyvy, _) => cw as usize * 4,
+            (_, 0) => width as usize * bps,
+            (YuvFormat::I420, _) => cw as usize * bps,
+            _ => cw as usize * 2 * bps,
+        }
+    }
+
+    /// Rows in `plane`.
+    pub fn plane_rows(&self, plane: usize, height: u32) -> usize {
+        match (self, plane) {
+            (YuvFormat::Uyvy, _) | (_, 0) => height as usize,
+            _ => self.chroma_size(1, height).1 as usize,
+        }
+    }
+
+    /// Total bytes of a tightly packed frame.
+    pub fn frame_len(&self, width: u32, height: u32) -> usize {
+        (0..self.plane_count())
+            .map(|p| self.plane_stride(p, width) * self.plane_rows(p, height))
+            .sum()
+    }
+}
+
+/// RGB↔YUV matrix coefficients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum YuvMatrix {
+    /// ITU-R BT.601 (SD video).
+    Bt601,
+    /// ITU-R BT.709 (HD video).
+    #[default]
+    Bt709,
+    /// ITU-R BT.2020 non-constant luminance (UHD / HDR).
+    Bt2020,
+}
+
+impl YuvMatrix {
+    /// Luma weights (Kr, Kb).
+    pub fn coefficients(&self) -> (f32, f32) {
+        match self {
+            YuvMatrix::Bt601 => (0.299, 0.114),
+            YuvMatrix::Bt709 => (0.2126, 0.0722),
+            YuvMatrix::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+
+    /// The matrix conventionally paired with `color_space`.
+    pub fn for_color_space(color_space: ColorSpace) -> Self {
+        match color_space {
+            ColorSpace::Rec2020Pq | ColorSpace::Rec2020Hlg => YuvMatrix::Bt2020,
+            _ => YuvMatrix::Bt709,
+        }
+    }
+}
+
+/// Quantization range of YUV samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum YuvRange {
+    /// Video ("TV") range: Y 16–235, chroma 16–240 at 8 bits.
+    #[default]
+    Limited,
+    /// Full ("PC") range: 0–255 at 8 bits.
+    Full,
+}
+
+impl YuvRange {
+    /// (Y offset, Y scale, chroma scale) for `bit_depth`; chroma is centred on 2^(bit_depth-1).
+    fn quantization(&self, bit_depth: u32) -> (f32, f32, f32) {
+        let unit = (1u32 << (bit_depth - 8)) as f32;
+        match self {
+            YuvRange::Limited => (16.0 * unit, 219.0 * unit, 224.0 * unit),
+            YuvRange::Full => {
+                let max = ((1u32 << bit_depth) - 1) as f32;
+                (0.0, max, max)
+            }
+        }
+    }
+}
+
+/// An owned, tightly packed YUV image with its matrix and range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YuvFrame {
+    width: u32,
+    height: u32,
+    format: YuvFormat,
+    matrix: YuvMatrix,
+    range: YuvRange,
+    data: Vec<u8>,
+}
+
+impl YuvFrame {
+    /// Wrap tightly packed planes (BT.709, limited range unless retagged). Returns `None` if
+    /// `data` is shorter than `format.frame_len(width, height)`.
+    pub fn from_vec(width: u32, height: u32, format: YuvFormat, data: Vec<u8>) -> Option<Self> {
+        if data.len() < format.frame_len(width, height) {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            format,
+            matrix: YuvMatrix::default(),
+            range: YuvRange::default(),
+            data,
+        })
+    }
+
+    /// Convert an RGBA/BGRA frame.
+    pub fn from_frame(
+        frame: &Frame,
+        format: YuvFormat,
+        matrix: YuvMatrix,
+        range: YuvRange,
+    ) -> Self {
+        let (width, height) = (frame.width(), frame.height());
+        let (kr, kb) = matrix.coefficients();
+        let (r_at, b_at) = match frame.format() {
+            PixelFormat::Rgba8 => (0, 2),
+            PixelFormat::Bgra8 => (2, 0),
+        };
+        let mut planes = Planes::new(format, width, height);
+        for y in 0..height {
+            let row = frame.row(y);
+            for x in 0..width {
+                let px = &row[x as usize * 4..x as usize * 4 + 4];
+                let r = px[r_at] as f32 / 255.0;
+                let g = px[1] as f32 / 255.0;
+                let b = px[b_at] as f32 / 255.0;
+                let luma = kr * r + (1.0 - kr - kb) * g + kb * b;
+                planes.y[(y * width + x) as usize] = luma;
+                let c = planes.chroma_index(x, y);
+                planes.u[c] += (b - luma) / (2.0 * (1.0 - kb));
+                planes.v[c] += (r - luma) / (2.0 * (1.0 - kr));
+                planes.count[c] += 1.0;
+            }
+        }
+        for ((u, v), n) in planes.u.iter_mut().zip(&mut planes.v).zip(&planes.count) {
+            if *n > 0.0 {
+                *u /= n;
+                *v /= n;
+            }
+        }
+        let mut out = Self {
+            width,
+            height,
+            format,
+            matrix,
+            range,
+            data: vec![0; format.frame_len(width, height)],
+        };
+        out.pack(&planes);
+        out
+    }
+
+    /// Convert to an opaque frame in `pixel_format`.
+    pub fn to_frame(&self, pixel_format: PixelFormat) -> Frame {
+        let planes = self.unpack();
+        let (kr, kb) = self.matrix.coefficients();
+        let kg = 1.0 - kr - kb;
+        let (r_at, b_at) = match pixel_format {
+            PixelFormat::Rgba8 => (0, 2),
+            PixelFormat::Bgra8 => (2, 0),
+        };
+        let mut frame = Frame::new(self.width, self.height, pixel_format);
+        for y in 0..self.height {
+            let row = frame.row_mut(y);
+            for x in 0..self.width {
+                let luma = planes.y[(y * self.width + x) as usize];
+                let c = planes.chroma_index(x, y);
+                let (cb, cr) = (planes.u[c], planes.v[c]);
+                let r = luma + 2.0 * (1.0 - kr) * cr;
+                let b = luma + 2.0 * (1.0 - kb) * cb;
+                let g = (luma - kr * r - kb * b) / kg;
+                let px = &mut row[x as usize * 4..x as usize * 4 + 4];
+                px[r_at] = to_u8(r);
+                px[1] = to_u8(g);
+                px[b_at] = to_u8(b);
+                px[3] = 255;
+            }
+        }
+        frame
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> YuvFormat {
+        self.format
+    }
+
+    pub fn matrix(&self) -> YuvMatrix {
+        self.matrix
+    }
+
+    pub fn range(&self) -> YuvRange {
+        self.range
+    }
+
+    /// Retag the matrix without touching the samples.
+    pub fn with_matrix(mut self, matrix: YuvMatrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    /// Retag the range without touching the samples.
+    pub fn with_range(mut self, range: YuvRange) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// All planes, back to back.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Bytes of `plane` (0 = Y, or the only plane for UYVY). Panics if `plane` is out of range.
+    pub fn plane(&self, plane: usize) -> &[u8] {
+        let start = self.plane_offset(plane);
+        let len = self.format.plane_stride(plane, self.width)
+            * self.format.plane_rows(plane, self.height);
+        &self.data[start..start + len]
+    }
+
+    pub fn plane_stride(&self, plane: usize) -> usize {
+        self.format.plane_stride(plane, self.width)
+    }
+
+    fn plane_offset(&self, plane: usize) -> usize {
+        assert!(
+            plane < self.format.plane_count(),
+            "plane {plane} out of range"
+        );
+        (0..plane)
+            .map(|p| {
+                self.format.plane_stride(p, self.width) * self.format.plane_rows(p, self.height)
+            })
+            .sum()
+    }
+
+    fn pack(&mut self, planes: &Planes) {
+        let depth = self.format.bit_depth();
+        let (offset, y_scale, c_scale) = self.range.quantization(depth);
+        let mid = (1u32 << (depth - 1)) as f32;
+        let max = (1u32 << depth) - 1;
+        let quantize =
+            |v: f32, off: f32, scale: f32| (off + v * scale).round().clamp(0.0, max as f32) as u16;
+        let ys: Vec<u16> = planes
+            .y
+            .iter()
+            .map(|&v| quantize(v, offset, y_scale))
+            .collect();
+        let us: Vec<u16> = planes
+            .u
+            .iter()
+            .map(|&v| quantize(v, mid, c_scale))
+            .collect();
+        let vs: Vec<u16> = planes
+            .v
+            .iter()
+            .map(|&v| quantize(v, mid, c_scale))
+            .collect();
+        let (w, h) = (self.width as usize, self.height as usize);
+        let cw = planes.chroma_width;
+        match self.format {
+            YuvFormat::Uyvy => {
+                let stride = self.plane_stride(0);
+                for y in 0..h {
+                    for cx in 0..cw {
+                        let c = y * cw + cx;
+                        let x0 = cx * 2;
+                        let y1 = if x0 + 1 < w {
+                            ys[y * w + x0 + 1]
+                        } else {
+                            ys[y * w + x0]
+                        };
+                        let out = &mut self.data[y * stride + cx * 4..y * stride + cx * 4 + 4];
+                        out.copy_from_slice(&[
+                            us[c] as u8,
+                            ys[y * w + x0] as u8,
+                            vs[c] as u8,
+                            y1 as u8,
+                        ]);
+                    }
+                }
+            }
+            YuvFormat::I420 => {
+                let mut samples = ys.iter().chain(&us).chain(&vs);
+                for byte in self.data.iter_mut() {
+                    *byte = *samples.next().unwrap() as u8;
+                }
+            }
+            YuvFormat::Nv12 | YuvFormat::P010 => {
+                let interleaved = us.iter().zip(&vs).flat_map(|(u, v)| [*u, *v]);
+                let samples = ys.iter().copied().chain(interleaved);
+                if self.format == YuvFormat::P010 {
+                    for (out, s) in self.data.chunks_exact_mut(2).zip(samples) {
+                        out.copy_from_slice(&(s << 6).to_le_bytes());
+                    }
+                } else {
+                    for (out, s) in self.data.iter_mut().zip(samples) {
+                        *out = s as u8;
+                    }
+                }
+            }
+        }
+    }
+
+    fn unpack(&self) -> Planes {
+        let depth = self.format.bit_depth();
+        let (offset, y_scale, c_scale) = self.range.quantization(depth);
+        let mid = (1u32 << (depth - 1)) as f32;
+        let luma = |s: u16| (s as f32 - offset) / y_scale;
+        let chroma = |s: u16| (s as f32 - mid) / c_scale;
+        let mut planes = Planes::new(self.format, self.width, self.height);
+        let (w, h) = (self.width as usize, self.height as usize);
+        let cw = planes.chroma_width;
+        let chroma_len = planes.u.len();
+        match self.format {
+            YuvFormat::Uyvy => {
+                let stride = self.plane_stride(0);
+                for y in 0..h {
+                    for cx in 0..cw {
+                        let p = &self.data[y * stride + cx * 4..y * stride + cx * 4 + 4];
+                        planes.u[y * cw + cx] = chroma(p[0] as u16);
+                        planes.v[y * cw + cx] = chroma(p[2] as u16);
+                        planes.y[y * w + cx * 2] = luma(p[1] as u16);
+                        if cx * 2 + 1 < w {
+                            planes.y[y * w + cx * 2 + 1] = luma(p[3] as u16);
+                        }
+                    }
+                }
+            }
+            YuvFormat::I420 => {
+                let (y_plane, rest) = self.data.split_at(w * h);
+                for (out, s) in planes.y.iter_mut().zip(y_plane) {
+                    *out = luma(*s as u16);
+                }
+                for (i, s) in rest[..chroma_len * 2].iter().enumerate() {
+                    if i < chroma_len {
+                        planes.u[i] = chroma(*s as u16);
+                    } else {
+                        planes.v[i - chroma_len] = chroma(*s as u16);
+                    }
+                }
+            }
+            YuvFormat::Nv12 | YuvFormat::P010 => {
+                let samples: Vec<u16> = if self.format == YuvFormat::P010 {
+                    self.data
+                        .chunks_exact(2)
+                        .map(|b| u16::from_le_bytes([b[0], b[1]]) >> 6)
+                        .collect()
+                } else {
+                    self.data.iter().map(|&b| b as u16).collect()
+                };
+                for (out, s) in planes.y.iter_mut().zip(&samples) {
+                    *out = luma(*s);
+                }
+                for (i, uv) in samples[w * h..w * h + chroma_len * 2]
+                    .chunks_exact(2)
+                    .enumerate()
+                {
+                    planes.u[i] = chroma(uv[0]);
+                    planes.v[i] = chroma(uv[1]);
+                }
+            }
+        }
+        planes
+    }
+}
+
+/// Normalized samples: Y in 0..1, U/V in -0.5..0.5, chroma at subsampled resolution.
+struct Planes {
+    width: usize,
+    chroma_width: usize,
+    subsampling: (u32, u32),
+    y: Vec<f32>,
+    u: Vec<f32>,
+    v: Vec<f32>,
+    /// Pixels accumulated into each chroma sample while encoding.
+    count: Vec<f32>,
+}
+
+impl Planes {
+    fn new(format: YuvFormat, width: u32, height: u32) -> Self {
+        let (cw, ch) = format.chroma_size(width, height);
+        let chroma_len = cw as usize * ch as usize;
+        Self {
+            width: width as usize,
+            chroma_width: cw as usize,
+            subsampling: format.chroma_subsampling(),
+            y: vec![0.0; width as usize * height as usize],
+            u: vec![0.0; chroma_len],
+            v: vec![0.0; chroma_len],
+            count: vec![0.0; chroma_len],
+        }
+    }
+
+    fn chroma_index(&self, x: u32, y: u32) -> usize {
+        debug_assert!((x as usize) < self.width);
+        (y / self.subsampling.1) as usize * self.chroma_width + (x / self.subsampling.0) as usize
+    }
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v * 255.0).round().clamp(0.0, 255.0) as u8
+}
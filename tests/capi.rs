@@ -0,0 +1,242 @@
+//! The C ABI: called from Rust through its exported symbols, and from C through the header.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::PathBuf;
+use std::process::Command;
+use std::ptr;
+
+use rusty_syphon_spout::{Frame, LocalClient, LocalServer, PixelFormat};
+
+#[repr(C)]
+struct SourceInfo {
+    backend: i32,
+    id: *const c_char,
+    uuid: *const c_char,
+    name: *const c_char,
+    app_name: *const c_char,
+    width: u32,
+    height: u32,
+    format: i32,
+}
+
+#[repr(C)]
+struct RssFrame {
+    pixels: *const u8,
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: i32,
+}
+
+enum SourceList {}
+enum Sender {}
+enum Receiver {}
+
+extern "C" {
+    fn rss_abi_version() -> u32;
+    fn rss_error_message(error: i32) -> *const c_char;
+    fn rss_discover(out: *mut *mut SourceList) -> i32;
+    fn rss_source_list_len(list: *const SourceList) -> usize;
+    fn rss_source_list_get(list: *const SourceList, index: usize, out: *mut SourceInfo) -> i32;
+    fn rss_source_list_free(list: *mut SourceList);
+    fn rss_sender_create(backend: i32, name: *const c_char, out: *mut *mut Sender) -> i32;
+    fn rss_sender_send(
+        sender: *mut Sender,
+        pixels: *const u8,
+        width: u32,
+        height: u32,
+        stride: usize,
+        format: i32,
+    ) -> i32;
+    fn rss_sender_destroy(sender: *mut Sender);
+    fn rss_receiver_connect(source: *const c_char, out: *mut *mut Receiver) -> i32;
+    fn rss_receiver_receive(
+        receiver: *mut Receiver,
+        callback: Option<unsafe extern "C" fn(*const RssFrame, *mut c_void)>,
+        user_data: *mut c_void,
+    ) -> i32;
+    fn rss_receiver_destroy(receiver: *mut Receiver);
+}
+
+const RSS_OK: i32 = 0;
+const RSS_ERROR_NULL_ARGUMENT: i32 = -1;
+const RSS_ERROR_INVALID_ARGUMENT: i32 = -2;
+const RSS_ERROR_NOT_FOUND: i32 = -3;
+const RSS_ERROR_NO_FRAME: i32 = -5;
+
+unsafe fn string(s: *const c_char) -> String {
+    CStr::from_ptr(s).to_str().unwrap().to_string()
+}
+
+#[test]
+fn version_and_error_messages() {
+    unsafe {
+        assert_eq!(rss_abi_version(), 1);
+        assert_eq!(
+            string(rss_error_message(RSS_ERROR_NOT_FOUND)),
+            "no such source"
+        );
+        assert_eq!(string(rss_error_message(-999)), "unknown error");
+    }
+}
+
+#[test]
+fn discovery_lists_local_servers() {
+    let server = LocalServer::new("capi-list");
+    server.publish(Frame::new(6, 4, PixelFormat::Bgra8));
+    unsafe {
+        let mut list = ptr::null_mut();
+        assert_eq!(rss_discover(&mut list), RSS_OK);
+        let mut found = None;
+        for i in 0..rss_source_list_len(list) {
+            let mut info = std::mem::zeroed::<SourceInfo>();
+            assert_eq!(rss_source_list_get(list, i, &mut info), RSS_OK);
+            if string(info.name) == "capi-list" {
+                found = Some((
+                    string(info.id),
+                    info.backend,
+                    info.width,
+                    info.height,
+                    info.format,
+                ));
+            }
+        }
+        let mut info = std::mem::zeroed::<SourceInfo>();
+        let len = rss_source_list_len(list);
+        assert_eq!(
+            rss_source_list_get(list, len, &mut info),
+            RSS_ERROR_INVALID_ARGUMENT
+        );
+        rss_source_list_free(list);
+        assert_eq!(
+            found,
+            Some((format!("local:{}", server.uuid()), 0, 6, 4, 1))
+        );
+    }
+}
+
+unsafe extern "C" fn store_frame(frame: *const RssFrame, user_data: *mut c_void) {
+    let frame = &*frame;
+    let out = &mut *(user_data as *mut Vec<u8>);
+    for y in 0..frame.height as usize {
+        let row = std::slice::from_raw_parts(
+            frame.pixels.add(y * frame.stride),
+            frame.width as usize * 4,
+        );
+        out.extend_from_slice(row);
+    }
+}
+
+#[test]
+fn sender_and_receiver_round_trip() {
+    unsafe {
+        let name = CString::new("capi-roundtrip").unwrap();
+        let mut sender = ptr::null_mut();
+        assert_eq!(rss_sender_create(0, name.as_ptr(), &mut sender), RSS_OK);
+        assert_eq!(
+            rss_sender_create(7, name.as_ptr(), &mut sender),
+            RSS_ERROR_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            rss_sender_create(0, ptr::null(), &mut sender),
+            RSS_ERROR_NULL_ARGUMENT
+        );
+
+        // Visible to Rust clients like any local server.
+        let mut client = LocalClient::connect("capi-roundtrip").unwrap();
+        let pixels: Vec<u8> = (0..2 * 10).map(|i| i as u8).collect();
+        assert_eq!(
+            rss_sender_send(sender, pixels.as_ptr(), 2, 2, 10, 0),
+            RSS_OK
+        );
+        assert_eq!(
+            rss_sender_send(sender, pixels.as_ptr(), 3, 2, 10, 0),
+            RSS_ERROR_INVALID_ARGUMENT
+        );
+        // Sizes that overflow are rejected before anything is allocated.
+        assert_eq!(
+            rss_sender_send(sender, pixels.as_ptr(), u32::MAX, u32::MAX, usize::MAX, 0),
+            RSS_ERROR_INVALID_ARGUMENT
+        );
+        let frame = client.new_frame().unwrap();
+        assert_eq!(frame.row(1), &pixels[10..18]);
+
+        let mut receiver = ptr::null_mut();
+        let missing = CString::new("capi-no-such-source").unwrap();
+        assert_eq!(
+            rss_receiver_connect(missing.as_ptr(), &mut receiver),
+            RSS_ERROR_NOT_FOUND
+        );
+        assert_eq!(rss_receiver_connect(name.as_ptr(), &mut receiver), RSS_OK);
+        let mut received = Vec::new();
+        let user_data = &mut received as *mut Vec<u8> as *mut c_void;
+        assert_eq!(
+            rss_receiver_receive(receiver, Some(store_frame), user_data),
+            RSS_OK
+        );
+        assert_eq!(received, [&pixels[..8], &pixels[10..18]].concat());
+        assert_eq!(
+            rss_receiver_receive(receiver, Some(store_frame), user_data),
+            RSS_ERROR_NO_FRAME
+        );
+        assert_eq!(
+            rss_receiver_receive(receiver, None, user_data),
+            RSS_ERROR_NULL_ARGUMENT
+        );
+
+        rss_receiver_destroy(receiver);
+        rss_sender_destroy(sender);
+        rss_sender_destroy(ptr::null_mut());
+    }
+}
+
+/// Directory holding the cdylib: the test binary lives in its `deps` subdirectory.
+fn library_dir() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().to_path_buf()
+}
+
+#[test]
+#[cfg(unix)]
+fn c_program_builds_against_the_header() {
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        eprintln!("skipping: no C compiler ({cc})");
+        return;
+    }
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // `cargo test` only builds the rlib; build the cdylib into the same target directory.
+    let lib_dir = library_dir();
+    let target_dir = lib_dir.parent().unwrap();
+    let mut build = Command::new(env!("CARGO"));
+    build
+        .args(["build", "--lib", "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir);
+    if lib_dir.file_name().unwrap() == "release" {
+        build.arg("--release");
+    }
+    assert!(build.status().unwrap().success(), "cdylib build failed");
+    let exe = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("capi_smoke");
+    let status = Command::new(&cc)
+        .args(["-std=c99", "-Wall", "-Werror", "-o"])
+        .arg(&exe)
+        .arg(root.join("tests/capi/smoke.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lrusty_syphon_spout")
+        .status()
+        .unwrap();
+    assert!(status.success(), "C build failed");
+    let output = Command::new(&exe).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,66 @@
+/* Built and run by tests/capi.rs against include/rusty_syphon_spout.h and the cdylib. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "rusty_syphon_spout.h"
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #cond); \
+            return 1;                                                 \
+        }                                                             \
+    } while (0)
+
+static void count_named(const rss_source_info *info, void *user_data) {
+    if (strcmp(info->name, "capi-smoke") == 0 && info->backend == RSS_BACKEND_LOCAL) {
+        ++*(int *)user_data;
+    }
+}
+
+static void copy_frame(const rss_frame *frame, void *user_data) {
+    memcpy(user_data, frame->pixels, (size_t)frame->width * 4);
+}
+
+int main(void) {
+    CHECK(rss_abi_version() == RSS_ABI_VERSION);
+    CHECK(strlen(rss_error_message(RSS_ERROR_NOT_FOUND)) > 0);
+
+    rss_sender *sender = NULL;
+    CHECK(rss_sender_create(RSS_BACKEND_LOCAL, "capi-smoke", &sender) == RSS_OK);
+    int found = 0;
+    CHECK(rss_discover_each(count_named, &found) == RSS_OK);
+    CHECK(found == 1);
+
+    rss_receiver *receiver = NULL;
+    CHECK(rss_receiver_connect("capi-smoke", &receiver) == RSS_OK);
+    CHECK(rss_receiver_is_connected(receiver));
+    rss_frame frame;
+    CHECK(rss_receiver_receive_into(receiver, NULL, 0, &frame) == RSS_ERROR_NO_FRAME);
+
+    /* Two rows of three pixels with two bytes of row padding. */
+    uint8_t pixels[2 * 14];
+    for (size_t i = 0; i < sizeof pixels; ++i) pixels[i] = (uint8_t)i;
+    CHECK(rss_sender_send(sender, pixels, 3, 2, 14, RSS_PIXEL_FORMAT_BGRA8) == RSS_OK);
+
+    uint8_t small[8];
+    CHECK(rss_receiver_receive_into(receiver, small, sizeof small, &frame) ==
+          RSS_ERROR_BUFFER_TOO_SMALL);
+    CHECK(frame.width == 3 && frame.height == 2 && frame.pixels == NULL);
+    uint8_t packed[24];
+    CHECK(rss_receiver_receive_into(receiver, packed, sizeof packed, &frame) == RSS_OK);
+    CHECK(frame.format == RSS_PIXEL_FORMAT_BGRA8 && frame.stride == 12);
+    CHECK(memcmp(packed, pixels, 12) == 0 && memcmp(packed + 12, pixels + 14, 12) == 0);
+
+    CHECK(rss_sender_send(sender, pixels, 3, 2, 14, RSS_PIXEL_FORMAT_BGRA8) == RSS_OK);
+    uint8_t row[12];
+    CHECK(rss_receiver_receive(receiver, copy_frame, row) == RSS_OK);
+    CHECK(memcmp(row, pixels, 12) == 0);
+
+    rss_sender_destroy(sender);
+    CHECK(rss_receiver_receive(receiver, copy_frame, row) == RSS_ERROR_DISCONNECTED);
+    rss_receiver_destroy(receiver);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,161 @@
+//! Color space tags, transfer functions and conversion (runs on every platform).
+
+use rusty_syphon_spout::{
+    advertise_color_space, advertised_color_space, convert_color_space, withdraw_color_space,
+    ColorConverter, ColorSpace, Frame, LocalServer, PixelFormat, Primaries, Rect, TransferFunction,
+};
+
+fn solid(rgba: [u8; 4], format: PixelFormat) -> Frame {
+    Frame::from_vec(2, 2, format, rgba.repeat(4)).unwrap()
+}
+
+fn close(a: f32, b: f32, tolerance: f32) -> bool {
+    (a - b).abs() <= tolerance
+}
+
+#[test]
+fn frames_default_to_srgb_and_keep_tag_through_crop() {
+    let frame = Frame::new(4, 4, PixelFormat::Rgba8);
+    assert_eq!(frame.color_space(), ColorSpace::Srgb);
+    let frame = frame.with_color_space(ColorSpace::DisplayP3);
+    let cropped = frame.crop(Rect::new(1, 1, 2, 2)).unwrap();
+    assert_eq!(cropped.color_space(), ColorSpace::DisplayP3);
+}
+
+#[test]
+fn names_round_trip() {
+    for space in ColorSpace::ALL {
+        assert_eq!(ColorSpace::from_name(space.name()), Some(space));
+    }
+    assert_eq!(ColorSpace::from_name("adobe-rgb"), None);
+    assert!(ColorSpace::Rec2020Pq.is_hdr());
+    assert!(!ColorSpace::Rec709.is_hdr());
+}
+
+#[test]
+fn transfer_functions_match_reference_values() {
+    let srgb = TransferFunction::Srgb;
+    assert!(close(srgb.to_linear(0.5), 0.2140, 1e-4));
+    assert!(close(srgb.from_linear(0.18), 0.4614, 1e-4));
+    // PQ: 100 cd/m² encodes to ~0.5081.
+    assert!(close(TransferFunction::Pq.from_linear(0.01), 0.5081, 1e-3));
+    // HLG: 1/12 of scene light sits at signal 0.5.
+    assert!(close(
+        TransferFunction::Hlg.from_linear(1.0 / 12.0),
+        0.5,
+        1e-5
+    ));
+    assert!(close(
+        TransferFunction::Bt709.from_linear(0.018),
+        0.081,
+        1e-3
+    ));
+
+    let all = [
+        TransferFunction::Srgb,
+        TransferFunction::Linear,
+        TransferFunction::Bt709,
+        TransferFunction::Pq,
+        TransferFunction::Hlg,
+    ];
+    for tf in all {
+        for i in 0..=20 {
+            let v = i as f32 / 20.0;
+            assert!(
+                close(tf.from_linear(tf.to_linear(v)), v, 1e-4),
+                "{tf:?} at {v}"
+            );
+        }
+    }
+}
+
+#[test]
+fn primaries_matrix_matches_srgb_spec() {
+    let m = Primaries::Bt709.rgb_to_xyz();
+    let expected = [
+        [0.4124, 0.3576, 0.1805],
+        [0.2126, 0.7152, 0.0722],
+        [0.0193, 0.1192, 0.9505],
+    ];
+    for (row, exp) in m.iter().zip(expected) {
+        for (v, e) in row.iter().zip(exp) {
+            assert!((v - e).abs() < 1e-3, "{m:?}");
+        }
+    }
+    let k = Primaries::Bt2020.luma_coefficients();
+    assert!((k[0] - 0.2627).abs() < 1e-3 && (k[2] - 0.0593).abs() < 1e-3);
+}
+
+#[test]
+fn srgb_to_linear_frame() {
+    let frame = solid([128, 0, 255, 77], PixelFormat::Rgba8);
+    let linear = convert_color_space(&frame, ColorSpace::LinearSrgb);
+    assert_eq!(linear.color_space(), ColorSpace::LinearSrgb);
+    assert_eq!(&linear.row(0)[..4], &[55, 0, 255, 77]);
+
+    let bgra = solid([255, 0, 128, 77], PixelFormat::Bgra8);
+    let linear = convert_color_space(&bgra, ColorSpace::LinearSrgb);
+    assert_eq!(&linear.row(0)[..4], &[255, 0, 55, 77]);
+}
+
+#[test]
+fn wide_gamut_round_trip_is_near_lossless() {
+    let to_p3 = ColorConverter::new(ColorSpace::Srgb, ColorSpace::DisplayP3);
+    let back = ColorConverter::new(ColorSpace::DisplayP3, ColorSpace::Srgb);
+    for rgb in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.2, 0.4, 0.9]] {
+        let out = back.convert_rgb(to_p3.convert_rgb(rgb));
+        assert!(
+            out.iter().zip(rgb).all(|(a, b)| close(*a, b, 1e-4)),
+            "{rgb:?} -> {out:?}"
+        );
+    }
+    for rgb in [[200, 60, 70], [52, 200, 90], [128, 128, 128]] {
+        let frame = solid([rgb[0], rgb[1], rgb[2], 255], PixelFormat::Rgba8);
+        let out = back.convert(&to_p3.convert(&frame));
+        for c in 0..3 {
+            assert!(
+                out.row(0)[c].abs_diff(rgb[c]) <= 1,
+                "{rgb:?} -> {:?}",
+                out.row(0)
+            );
+        }
+    }
+    // sRGB red is inside P3, so it no longer saturates the red channel.
+    let red = to_p3.convert_rgb([1.0, 0.0, 0.0]);
+    assert!(red[0] < 0.95 && red[1] > 0.1);
+}
+
+#[test]
+fn sdr_white_lands_at_bt2408_reference_levels() {
+    let pq = ColorConverter::new(ColorSpace::Srgb, ColorSpace::Rec2020Pq).convert_rgb([1.0; 3]);
+    assert!(pq.iter().all(|&v| close(v, 0.5807, 2e-3)), "{pq:?}");
+    let hlg = ColorConverter::new(ColorSpace::Srgb, ColorSpace::Rec2020Hlg).convert_rgb([1.0; 3]);
+    assert!(hlg.iter().all(|&v| close(v, 0.75, 2e-3)), "{hlg:?}");
+    let back = ColorConverter::new(ColorSpace::Rec2020Hlg, ColorSpace::Srgb).convert_rgb(hlg);
+    assert!(back.iter().all(|&v| close(v, 1.0, 2e-3)), "{back:?}");
+}
+
+#[test]
+fn identity_conversion_only_retags() {
+    let frame = solid([10, 20, 30, 40], PixelFormat::Rgba8);
+    let converter = ColorConverter::new(ColorSpace::Srgb, ColorSpace::Srgb);
+    assert!(converter.is_identity());
+    assert_eq!(converter.convert(&frame), frame);
+}
+
+#[test]
+fn local_server_advertises_color_space() {
+    let server = LocalServer::new("color-space-test");
+    assert_eq!(server.info().color_space, ColorSpace::Srgb);
+    server.set_color_space(ColorSpace::Rec2020Pq);
+    assert_eq!(server.info().color_space, ColorSpace::Rec2020Pq);
+}
+
+#[test]
+fn advertisement_for_native_servers() {
+    let name = "color-space-test/native";
+    advertise_color_space(name, ColorSpace::Rec709).unwrap();
+    assert_eq!(advertised_color_space(name), Some(ColorSpace::Rec709));
+    withdraw_color_space(name);
+    assert_eq!(advertised_color_space(name), None);
+}
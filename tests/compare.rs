@@ -0,0 +1,150 @@
+//! Frame comparison metrics and assertions (runs on every platform).
+
+use rusty_syphon_spout::{
+    assert_frames_match, compare_frames, diff_image, CompareError, Frame, FrameCompare, PixelFormat,
+};
+
+fn pattern(width: u32, height: u32) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            let y = y as usize;
+            px.copy_from_slice(&[(x * 13) as u8, (y * 17) as u8, ((x + y) * 5) as u8, 255]);
+        }
+    }
+    frame
+}
+
+fn flip(frame: &Frame) -> Frame {
+    let mut out = frame.clone();
+    for y in 0..frame.height() {
+        out.row_mut(frame.height() - 1 - y)
+            .copy_from_slice(frame.row(y));
+    }
+    out
+}
+
+#[test]
+fn identical_frames() {
+    let frame = pattern(16, 12);
+    let result = compare_frames(&frame, &frame).unwrap();
+    assert!(result.is_identical());
+    assert_eq!(result.psnr, f64::INFINITY);
+    assert!((result.ssim - 1.0).abs() < 1e-9);
+    assert_eq!(result.first_difference, None);
+    // Channel order does not matter, only meaning.
+    assert!(compare_frames(&frame, &frame.to_format(PixelFormat::Bgra8))
+        .unwrap()
+        .is_identical());
+}
+
+#[test]
+fn metrics_for_known_error() {
+    let expected = pattern(16, 16);
+    let mut actual = expected.clone();
+    actual.row_mut(3)[5 * 4] ^= 0x10; // one channel off by 16
+    let result = compare_frames(&expected, &actual).unwrap();
+    assert_eq!(result.max_abs_diff, 16);
+    assert_eq!(result.differing_pixels, 1);
+    let samples = 16.0 * 16.0 * 4.0;
+    assert!((result.mean_abs_error - 16.0 / samples).abs() < 1e-12);
+    assert!((result.mse - 256.0 / samples).abs() < 1e-12);
+    let psnr = 10.0 * (255.0f64 * 255.0 / result.mse).log10();
+    assert!((result.psnr - psnr).abs() < 1e-9);
+    assert!(result.ssim < 1.0 && result.ssim > 0.9);
+    let first = result.first_difference.unwrap();
+    assert_eq!((first.x, first.y), (5, 3));
+    assert_eq!(result.worst_difference, Some(first));
+}
+
+#[test]
+fn tolerance_alpha_and_flip() {
+    let expected = pattern(8, 8);
+    let mut actual = expected.clone();
+    for y in 0..8 {
+        for px in actual.row_mut(y).chunks_exact_mut(4) {
+            px[1] = px[1].saturating_add(1);
+            px[3] = 0;
+        }
+    }
+    assert!(FrameCompare::new()
+        .tolerance(1)
+        .check(&expected, &actual)
+        .is_err());
+    FrameCompare::new()
+        .tolerance(1)
+        .ignore_alpha()
+        .assert(&expected, &actual);
+
+    let flipped = flip(&expected);
+    assert!(FrameCompare::new().check(&expected, &flipped).is_err());
+    FrameCompare::new().flipped().assert(&expected, &flipped);
+}
+
+#[test]
+fn psnr_and_ssim_thresholds() {
+    let expected = pattern(32, 32);
+    let mut noisy = expected.clone();
+    for y in 0..32 {
+        for (i, v) in noisy.row_mut(y).iter_mut().enumerate() {
+            if i % 4 != 3 && (i + y as usize).is_multiple_of(3) {
+                *v = v.saturating_add(3);
+            }
+        }
+    }
+    let compare = FrameCompare::new().tolerance(3);
+    let result = compare.check(&expected, &noisy).unwrap();
+    assert!(result.psnr > 40.0 && result.psnr.is_finite());
+    assert!(matches!(
+        compare.clone().min_psnr(60.0).check(&expected, &noisy),
+        Err(CompareError::PsnrTooLow { .. })
+    ));
+    assert!(matches!(
+        compare.min_ssim(1.0).check(&expected, &noisy),
+        Err(CompareError::SsimTooLow { .. })
+    ));
+}
+
+#[test]
+fn diff_image_highlights_changes() {
+    let expected = pattern(4, 4);
+    let mut actual = expected.clone();
+    actual.row_mut(2)[4..7].copy_from_slice(&[0, 0, 0]);
+    let diff = diff_image(&expected, &actual).unwrap();
+    assert_eq!(&diff.row(0)[..8], &[0, 0, 0, 255, 0, 0, 0, 255]);
+    let e = &expected.row(2)[4..7];
+    assert_eq!(&diff.row(2)[4..8], &[e[0], e[1], e[2], 255]);
+}
+
+#[test]
+fn size_mismatch() {
+    let a = pattern(4, 4);
+    let b = pattern(4, 5);
+    assert!(compare_frames(&a, &b).is_none());
+    assert!(matches!(
+        FrameCompare::new().check(&a, &b),
+        Err(CompareError::SizeMismatch { .. })
+    ));
+}
+
+#[test]
+fn failure_message_points_at_divergence() {
+    let expected = pattern(8, 8);
+    let mut actual = expected.clone();
+    actual.row_mut(6)[2 * 4 + 2] = actual.row(6)[2 * 4 + 2].wrapping_add(40);
+    let err = FrameCompare::new()
+        .tolerance(2)
+        .check(&expected, &actual)
+        .unwrap_err();
+    let message = err.to_string();
+    assert!(
+        message.contains("1 pixels differ by more than 2"),
+        "{message}"
+    );
+    assert!(message.contains("first difference at (2, 6)"), "{message}");
+
+    let panic = std::panic::catch_unwind(|| assert_frames_match(&expected, &actual, 2));
+    let payload = panic.unwrap_err();
+    let text = payload.downcast_ref::<String>().unwrap();
+    assert!(text.contains("(2, 6)"));
+}
@@ -0,0 +1,102 @@
+//! Compositor layouts and blending over the pure-Rust backend (runs on every platform).
+
+use rusty_syphon_spout::{
+    compose_layers, BlendMode, Compositor, Corner, Frame, LayerStyle, Layout, LocalClient,
+    LocalServer, PixelFormat, Rect, Size,
+};
+
+fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Frame {
+    let data = rgba.repeat((width * height) as usize);
+    Frame::from_vec(width, height, PixelFormat::Rgba8, data).unwrap()
+}
+
+fn pixel(frame: &Frame, x: u32, y: u32) -> [u8; 4] {
+    let row = frame.row(y);
+    let i = x as usize * 4;
+    [row[i], row[i + 1], row[i + 2], row[i + 3]]
+}
+
+#[test]
+fn grid_layout_tiles_the_output() {
+    let rects = Layout::Grid { columns: None }.rects(Size::new(100, 50), 3);
+    assert_eq!(
+        rects,
+        vec![
+            Some(Rect::new(0, 0, 50, 25)),
+            Some(Rect::new(50, 0, 50, 25)),
+            Some(Rect::new(0, 25, 50, 25)),
+        ]
+    );
+    let rects = Layout::Grid { columns: Some(3) }.rects(Size::new(10, 10), 3);
+    assert_eq!(rects[2], Some(Rect::new(6, 0, 4, 10)));
+}
+
+#[test]
+fn picture_in_picture_insets_stack_from_the_corner() {
+    let layout = Layout::PictureInPicture {
+        corner: Corner::BottomRight,
+        scale: 0.25,
+        margin: 10,
+    };
+    let rects = layout.rects(Size::new(400, 200), 3);
+    assert_eq!(rects[0], Some(Rect::new(0, 0, 400, 200)));
+    assert_eq!(rects[1], Some(Rect::new(290, 140, 100, 50)));
+    assert_eq!(rects[2], Some(Rect::new(290, 80, 100, 50)));
+}
+
+#[test]
+fn blend_modes_and_opacity() {
+    let size = Size::new(1, 1);
+    let layout = Layout::Custom(vec![Rect::new(0, 0, 1, 1); 2]);
+    let base = solid(1, 1, [100, 200, 50, 255]);
+    let top = solid(1, 1, [200, 100, 255, 255]);
+    let compose = |blend, opacity| {
+        let style = LayerStyle {
+            opacity,
+            blend,
+            visible: true,
+        };
+        let out = compose_layers(
+            size,
+            &layout,
+            [0, 0, 0, 255],
+            &[(Some(&base), LayerStyle::default()), (Some(&top), style)],
+        );
+        pixel(&out, 0, 0)
+    };
+    assert_eq!(compose(BlendMode::Normal, 1.0), [200, 100, 255, 255]);
+    assert_eq!(compose(BlendMode::Normal, 0.0), [100, 200, 50, 255]);
+    assert_eq!(compose(BlendMode::Normal, 0.5), [150, 150, 153, 255]);
+    assert_eq!(compose(BlendMode::Add, 1.0), [255, 255, 255, 255]);
+    assert_eq!(compose(BlendMode::Multiply, 1.0), [78, 78, 50, 255]);
+    assert_eq!(compose(BlendMode::Screen, 1.0), [222, 222, 255, 255]);
+}
+
+#[test]
+fn composes_local_sources_and_publishes() {
+    let red = LocalServer::new("compositor-test-red");
+    let blue = LocalServer::new("compositor-test-blue");
+    red.publish(solid(8, 8, [255, 0, 0, 255]));
+    blue.publish(solid(4, 4, [0, 0, 255, 255]));
+
+    let mut compositor = Compositor::new(Size::new(16, 8));
+    compositor.add_source(LocalClient::connect_uuid(red.uuid()).unwrap());
+    compositor.add_source(LocalClient::connect_uuid(blue.uuid()).unwrap());
+
+    let mut output = LocalServer::new("compositor-test-output");
+    let mut viewer = LocalClient::connect_uuid(output.uuid()).unwrap();
+    compositor.publish(&mut output).unwrap();
+    let frame = viewer.new_frame().expect("composited frame");
+    assert_eq!(frame.size(), Size::new(16, 8));
+    assert_eq!(pixel(&frame, 3, 3), [255, 0, 0, 255]);
+    assert_eq!(pixel(&frame, 12, 3), [0, 0, 255, 255]);
+
+    // No new frames: the last frame of each source is reused.
+    compositor.set_opacity(1, 0.0);
+    let frame = compositor.compose();
+    assert_eq!(pixel(&frame, 12, 3), [0, 0, 0, 255]);
+    assert_eq!(pixel(&frame, 3, 3), [255, 0, 0, 255]);
+
+    compositor.set_format(PixelFormat::Bgra8);
+    assert_eq!(pixel(&compositor.compose(), 3, 3), [0, 0, 255, 255]);
+}
@@ -0,0 +1,140 @@
+//! Client-to-server control channel over the loopback transport (runs on every platform).
+
+use rusty_syphon_spout::{ControlClient, ControlEvent, ControlMessage, ControlServer, Size};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn unique_name(tag: &str) -> String {
+    format!("control-test-{}-{}", tag, std::process::id())
+}
+
+#[test]
+fn client_messages_arrive_as_events() {
+    let name = unique_name("events");
+    let server = ControlServer::bind(&name).expect("bind control server");
+    let mut client = ControlClient::connect(&name, "monitor").expect("connect");
+
+    let client_id = match server.recv_timeout(TIMEOUT) {
+        Some(ControlEvent::Connected { client, name }) => {
+            assert_eq!(name, "monitor");
+            client
+        }
+        other => panic!("expected Connected, got {:?}", other),
+    };
+
+    let messages = [
+        ControlMessage::RequestSize(Size::new(1920, 1080)),
+        ControlMessage::RequestFrameRate(59.94),
+        ControlMessage::FramesDropped(3),
+        ControlMessage::Text("hello".into()),
+        ControlMessage::Custom(vec![0, 1, 2, 255]),
+    ];
+    for message in &messages {
+        client.send(message).unwrap();
+    }
+    for expected in messages {
+        assert_eq!(
+            server.recv_timeout(TIMEOUT),
+            Some(ControlEvent::Message {
+                client: client_id,
+                message: expected,
+            })
+        );
+    }
+    assert_eq!(server.clients(), vec![client_id]);
+
+    drop(client);
+    assert_eq!(
+        server.recv_timeout(TIMEOUT),
+        Some(ControlEvent::Disconnected { client: client_id })
+    );
+    assert!(server.clients().is_empty());
+}
+
+#[test]
+fn clients_are_told_apart() {
+    let name = unique_name("two-clients");
+    let server = ControlServer::bind(&name).unwrap();
+    let mut a = ControlClient::connect(&name, "a").unwrap();
+    let mut b = ControlClient::connect(&name, "b").unwrap();
+    a.send(&ControlMessage::FramesDropped(1)).unwrap();
+    b.send(&ControlMessage::FramesDropped(2)).unwrap();
+
+    let mut connected = std::collections::HashMap::new();
+    let mut dropped = std::collections::HashMap::new();
+    while dropped.len() < 2 {
+        match server.recv_timeout(TIMEOUT).expect("event") {
+            ControlEvent::Connected { client, name } => {
+                connected.insert(client, name);
+            }
+            ControlEvent::Message {
+                client,
+                message: ControlMessage::FramesDropped(n),
+            } => {
+                dropped.insert(connected[&client].clone(), n);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    assert_eq!(dropped["a"], 1);
+    assert_eq!(dropped["b"], 2);
+}
+
+#[test]
+fn failed_handshakes_are_not_reported() {
+    use std::io::Write;
+
+    let name = unique_name("handshake");
+    let server = ControlServer::bind(&name).unwrap();
+    // A length-prefixed frame that is not a hello, then hang up.
+    let mut stream = std::net::TcpStream::connect(server.local_addr()).unwrap();
+    stream.write_all(&[1, 0, 0, 0, 0xFF]).unwrap();
+    drop(stream);
+    assert_eq!(server.recv_timeout(Duration::from_millis(200)), None);
+
+    // A real client afterwards still gets both events.
+    let client = ControlClient::connect(&name, "after").unwrap();
+    let Some(ControlEvent::Connected { client: id, .. }) = server.recv_timeout(TIMEOUT) else {
+        panic!("expected Connected");
+    };
+    drop(client);
+    assert_eq!(
+        server.recv_timeout(TIMEOUT),
+        Some(ControlEvent::Disconnected { client: id })
+    );
+}
+
+#[test]
+fn only_clients_past_the_handshake_are_listed() {
+    let name = unique_name("listed");
+    let server = ControlServer::bind(&name).unwrap();
+    // Connected, but silent: no hello yet.
+    let _silent = std::net::TcpStream::connect(server.local_addr()).unwrap();
+    let _client = ControlClient::connect(&name, "listed").unwrap();
+    let Some(ControlEvent::Connected { client: id, .. }) = server.recv_timeout(TIMEOUT) else {
+        panic!("expected Connected");
+    };
+    assert_eq!(server.clients(), vec![id]);
+}
+
+#[test]
+fn unknown_server_is_not_found() {
+    let err = ControlClient::connect(&unique_name("missing"), "x").err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn dropped_server_unregisters() {
+    let name = unique_name("dropped");
+    let server = ControlServer::bind(&name).unwrap();
+    let mut client = ControlClient::connect(&name, "late").unwrap();
+    drop(server);
+    assert!(ControlClient::connect(&name, "after").is_err());
+    // The open connection was shut down; writes eventually fail.
+    let failed = (0..100).any(|_| {
+        std::thread::sleep(Duration::from_millis(10));
+        client.send(&ControlMessage::Text("ping".into())).is_err()
+    });
+    assert!(failed);
+}
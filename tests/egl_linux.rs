@@ -0,0 +1,76 @@
+//! Headless EGL context and GL texture helpers on Linux (Mesa llvmpipe is enough).
+//! Skips with a message if libEGL is not installed.
+
+#![cfg(target_os = "linux")]
+
+use rusty_syphon_spout::{
+    egl_available, egl_create_headless_context, egl_destroy_context, egl_make_current,
+    gl_create_texture_rectangle_rgba8, gl_delete_texture, gl_read_texture_rectangle_rgba8, Frame,
+    FrameCompare, PixelFormat,
+};
+
+const W: usize = 37;
+const H: usize = 21;
+
+fn pattern() -> Vec<u8> {
+    let mut data = vec![0u8; W * H * 4];
+    for y in 0..H {
+        for x in 0..W {
+            let i = (y * W + x) * 4;
+            data[i..i + 4].copy_from_slice(&[(x * 7) as u8, (y * 11) as u8, (x + y) as u8, 200]);
+        }
+    }
+    data
+}
+
+#[test]
+fn texture_roundtrip_on_headless_context() {
+    if !egl_available() {
+        eprintln!("libEGL not found; skipping");
+        return;
+    }
+    let ctx = egl_create_headless_context().expect("create headless EGL context");
+    assert!(egl_make_current(&ctx));
+
+    let sent = pattern();
+    let tex = gl_create_texture_rectangle_rgba8(W, H, &sent);
+    assert_ne!(tex, 0, "create texture failed");
+    let mut readback = vec![0u8; W * H * 4];
+    gl_read_texture_rectangle_rgba8(tex, W, H, &mut readback);
+    gl_delete_texture(tex);
+
+    let sent = Frame::from_vec(W as u32, H as u32, PixelFormat::Rgba8, sent).unwrap();
+    let received = Frame::from_vec(W as u32, H as u32, PixelFormat::Rgba8, readback).unwrap();
+    FrameCompare::new().assert(&sent, &received);
+    egl_destroy_context(ctx);
+}
+
+#[test]
+fn helpers_are_inert_without_a_current_context() {
+    let data = pattern();
+    std::thread::spawn(move || {
+        assert_eq!(gl_create_texture_rectangle_rgba8(W, H, &data), 0);
+        let mut out = vec![7u8; 16];
+        gl_read_texture_rectangle_rgba8(1, 2, 2, &mut out);
+        assert_eq!(out, vec![7u8; 16]);
+        gl_delete_texture(1);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn undersized_readback_buffer_is_refused() {
+    if !egl_available() {
+        return;
+    }
+    let ctx = egl_create_headless_context().expect("create headless EGL context");
+    assert!(egl_make_current(&ctx));
+    let tex = gl_create_texture_rectangle_rgba8(W, H, &pattern());
+    // Claims a smaller size than the texture has: nothing must be written.
+    let mut small = vec![1u8; 4 * 4 * 4];
+    gl_read_texture_rectangle_rgba8(tex, 4, 4, &mut small);
+    assert!(small.iter().all(|&b| b == 1));
+    gl_delete_texture(tex);
+    egl_destroy_context(ctx);
+}
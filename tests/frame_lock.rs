@@ -0,0 +1,110 @@
+//! Borrowed frame access from local servers (every platform) and memfd sockets (Linux), and
+//! the copy fallback.
+
+use std::sync::Arc;
+
+use rusty_syphon_spout::{
+    ColorSpace, Frame, FrameReceiver, LocalClient, LocalServer, LockingReceiver, PixelFormat,
+};
+
+fn frame(width: u32, height: u32, seed: u8) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Bgra8);
+    frame.data_mut().fill(seed);
+    frame.with_color_space(ColorSpace::Rec709)
+}
+
+#[test]
+fn local_lock_points_at_the_published_frame() {
+    let server = LocalServer::new("frame-lock-local");
+    let mut client = LocalClient::connect("frame-lock-local").unwrap();
+    assert!(client.lock_frame().is_none());
+
+    let published = Arc::new(frame(4, 3, 1));
+    server.publish(published.clone());
+    let lock = client.lock_frame().unwrap();
+    assert!(lock.is_zero_copy());
+    assert_eq!(lock.data().as_ptr(), published.data().as_ptr());
+    assert_eq!(
+        (
+            lock.width(),
+            lock.height(),
+            lock.format(),
+            lock.color_space()
+        ),
+        (4, 3, PixelFormat::Bgra8, ColorSpace::Rec709)
+    );
+
+    // Publishing again swaps in a new frame; the locked one is untouched.
+    server.publish(frame(4, 3, 2));
+    assert!(lock.data().iter().all(|&b| b == 1));
+    assert_eq!(lock.to_frame(), *published);
+    drop(lock);
+    drop(published);
+
+    let lock = client.lock_frame().unwrap();
+    assert_eq!(lock.row(2), &[2; 16][..]);
+    assert_eq!(lock.into_frame(), frame(4, 3, 2));
+    assert!(client.lock_frame().is_none(), "each frame is new once");
+    assert_eq!(client.stats().frames_received, 2);
+}
+
+/// A receiver that only implements `receive_frame`.
+struct Copying(Option<Frame>);
+
+impl FrameReceiver for Copying {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        self.0.take()
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+impl LockingReceiver for Copying {}
+
+#[test]
+fn other_receivers_fall_back_to_a_copy() {
+    let mut receiver: Box<dyn LockingReceiver> = Box::new(Copying(Some(frame(2, 2, 7))));
+    let lock = receiver.lock_frame().unwrap();
+    assert!(!lock.is_zero_copy());
+    assert_eq!(lock.to_frame(), frame(2, 2, 7));
+    assert_eq!(lock.into_frame(), frame(2, 2, 7));
+    assert!(receiver.lock_frame().is_none());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn memfd_lock_maps_the_sealed_frame() {
+    use rusty_syphon_spout::{FrameSender, MemfdClient, MemfdServer};
+
+    let mut server = MemfdServer::bind("frame-lock-memfd").unwrap();
+    let mut client = MemfdClient::connect("frame-lock-memfd").unwrap();
+    assert_eq!(server.client_count(), 1);
+    assert!(client.lock_frame().is_none());
+
+    // A padded source frame arrives packed; the lock reads it in place.
+    let mut padded = Frame::from_vec_with_stride(3, 2, 16, PixelFormat::Rgba8, vec![0; 32])
+        .unwrap()
+        .with_color_space(ColorSpace::DisplayP3);
+    padded.row_mut(0).fill(10);
+    padded.row_mut(1).fill(20);
+    server.send_frame(&padded).unwrap();
+    // Unix sockets deliver on send, so the frame is already queued.
+    let lock = client.lock_frame().unwrap();
+    assert!(lock.is_zero_copy());
+    assert_eq!((lock.width(), lock.height(), lock.stride()), (3, 2, 12));
+    assert_eq!(lock.color_space(), ColorSpace::DisplayP3);
+    assert_eq!(lock.row(0), &[10; 12][..]);
+    assert_eq!(lock.row(1), &[20; 12][..]);
+
+    // Later frames go to new memfds; the mapped one cannot change.
+    server.send_frame(&frame(3, 2, 99)).unwrap();
+    assert_eq!(lock.data(), [[10; 12], [20; 12]].concat());
+    let copy = lock.into_frame();
+    assert_eq!((copy.row(0), copy.row(1)), (&[10; 12][..], &[20; 12][..]));
+
+    let next = client.lock_frame().unwrap();
+    assert_eq!(next.to_frame(), frame(3, 2, 99));
+    assert_eq!(client.last_frame_number(), 2);
+}
@@ -0,0 +1,203 @@
+//! Frame pools and pooled receiving from local servers (every platform) and memfd sockets
+//! (Linux). A counting allocator checks that warmed-up receive loops do not allocate.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rusty_syphon_spout::{
+    ColorSpace, Frame, FramePool, FramePoolConfig, LocalClient, LocalServer, PixelFormat,
+    PooledReceiver,
+};
+
+/// Counts allocations made on threads inside `count_allocations`.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Allocations `f` makes on this thread.
+fn count_allocations(f: impl FnOnce()) -> usize {
+    COUNTING.with(|counting| counting.set(true));
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    f();
+    let after = ALLOCATIONS.load(Ordering::SeqCst);
+    COUNTING.with(|counting| counting.set(false));
+    after - before
+}
+
+fn frame(width: u32, height: u32, seed: u8) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    frame.data_mut().fill(seed);
+    frame
+}
+
+#[test]
+fn frames_are_reused_by_size_and_format() {
+    let pool = FramePool::default();
+    let mut a = pool.get(4, 2, PixelFormat::Rgba8);
+    a.data_mut().fill(9);
+    a.set_color_space(ColorSpace::DisplayP3);
+    drop(a);
+    let stats = pool.stats();
+    assert_eq!(
+        (stats.allocations, stats.idle_frames, stats.idle_bytes),
+        (1, 1, 32)
+    );
+
+    // Same key: the buffer comes back, with its old pixels but the default color space.
+    let b = pool.get(4, 2, PixelFormat::Rgba8);
+    assert_eq!(b.data()[0], 9);
+    assert_eq!(b.color_space(), ColorSpace::default());
+    // Another size or format is a new frame.
+    let c = pool.get(2, 4, PixelFormat::Rgba8);
+    let d = pool.get(4, 2, PixelFormat::Bgra8);
+    assert_eq!(
+        (d.width(), d.height(), d.format()),
+        (4, 2, PixelFormat::Bgra8)
+    );
+    let stats = pool.stats();
+    assert_eq!(
+        (stats.allocations, stats.reuses, stats.idle_frames),
+        (3, 1, 0)
+    );
+    drop((b, c, d));
+    assert_eq!(pool.stats().idle_frames, 3);
+
+    // A frame kept with `into_frame` never comes back.
+    let kept = pool.get(4, 2, PixelFormat::Rgba8).into_frame();
+    assert_eq!((kept.width(), kept.height()), (4, 2));
+    assert_eq!(pool.stats().idle_frames, 2);
+
+    // Copies keep the pixels and color space; adopted frames join the pool.
+    let source = frame(3, 3, 5).with_color_space(ColorSpace::Rec709);
+    let copy = pool.copy_of(&source);
+    assert_eq!(copy, source);
+    drop(pool.adopt(source));
+    drop(copy);
+    assert_eq!(pool.stats().idle_frames, 4);
+    pool.clear();
+    assert_eq!((pool.stats().idle_frames, pool.stats().idle_bytes), (0, 0));
+}
+
+#[test]
+fn depth_and_memory_cap_bound_idle_frames() {
+    let pool = FramePool::new(FramePoolConfig {
+        depth: 2,
+        max_bytes: 1024,
+    });
+    let frames: Vec<_> = (0..3).map(|_| pool.get(8, 8, PixelFormat::Rgba8)).collect();
+    drop(frames);
+    let stats = pool.stats();
+    assert_eq!((stats.idle_frames, stats.discarded), (2, 1), "depth 2");
+
+    // 16x16 RGBA is 1 KiB: it would take the idle bytes past the cap.
+    drop(pool.get(16, 16, PixelFormat::Rgba8));
+    assert_eq!(pool.stats().discarded, 2);
+    assert_eq!(pool.stats().idle_bytes, 512);
+
+    // Rows with padding are not what `get` hands out.
+    let padded = Frame::from_vec_with_stride(2, 2, 16, PixelFormat::Rgba8, vec![0; 32]).unwrap();
+    drop(pool.adopt(padded));
+    assert_eq!(pool.stats().discarded, 3);
+
+    // Lowering the limits frees what no longer fits.
+    pool.set_config(FramePoolConfig {
+        depth: 2,
+        max_bytes: 300,
+    });
+    let stats = pool.stats();
+    assert_eq!(
+        (stats.idle_frames, stats.idle_bytes, stats.discarded),
+        (1, 256, 4)
+    );
+    pool.set_config(FramePoolConfig {
+        depth: 0,
+        max_bytes: 300,
+    });
+    assert_eq!(pool.stats().idle_frames, 0);
+}
+
+#[test]
+fn local_receive_loop_allocates_nothing_once_warm() {
+    let server = LocalServer::new("frame-pool-local");
+    let mut client = LocalClient::connect("frame-pool-local").unwrap();
+    let pool = FramePool::default();
+    server.publish(frame(64, 32, 0));
+    let warm = client.receive_pooled(&pool).unwrap();
+    assert_eq!(warm, frame(64, 32, 0));
+    drop(warm);
+
+    for seed in 1..=10 {
+        server.publish(frame(64, 32, seed));
+        let mut received = None;
+        let allocations = count_allocations(|| {
+            let pooled = client.receive_pooled(&pool).unwrap();
+            received = Some(pooled.data()[0]);
+            assert!(client.receive_pooled(&pool).is_none());
+        });
+        assert_eq!(allocations, 0, "frame {seed}");
+        assert_eq!(received, Some(seed));
+    }
+    let stats = pool.stats();
+    assert_eq!((stats.allocations, stats.reuses), (1, 10));
+    // The count does see allocations: a size the pool has not seen yet needs one.
+    assert!(count_allocations(|| drop(pool.get(8, 8, PixelFormat::Rgba8))) > 0);
+
+    // Through a boxed receiver, as generic code would hold it.
+    let mut boxed: Box<dyn PooledReceiver> = Box::new(client);
+    server.publish(frame(64, 32, 42));
+    assert_eq!(boxed.receive_pooled(&pool).unwrap().data()[0], 42);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn memfd_frames_are_read_into_pooled_buffers() {
+    use rusty_syphon_spout::{FrameSender, MemfdClient, MemfdServer};
+    use std::time::{Duration, Instant};
+
+    let mut server = MemfdServer::bind("frame-pool-memfd").unwrap();
+    let mut client = MemfdClient::connect("frame-pool-memfd").unwrap();
+    assert_eq!(server.client_count(), 1);
+    let pool = FramePool::default();
+    let mut receive = |server: &mut MemfdServer, sent: &Frame| {
+        server.send_frame(sent).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let mut received = None;
+            let allocations = count_allocations(|| received = client.try_recv_pooled(&pool));
+            if let Some(frame) = received {
+                assert_eq!(frame, *sent);
+                return allocations;
+            }
+            assert!(Instant::now() < deadline, "no frame");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    };
+    receive(&mut server, &frame(16, 8, 1));
+    for seed in 2..=6 {
+        let sent = frame(16, 8, seed).with_color_space(ColorSpace::Rec709);
+        assert_eq!(receive(&mut server, &sent), 0, "frame {seed}");
+    }
+    assert_eq!(pool.stats().allocations, 1);
+    assert_eq!(client.last_frame_number(), 6);
+}
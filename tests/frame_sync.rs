@@ -0,0 +1,143 @@
+//! Sender/receiver frame synchronization over local servers (every platform) and memfd
+//! sockets (Linux).
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rusty_syphon_spout::{
+    Frame, FrameReceiver, LocalClient, LocalServer, PixelFormat, SyncMode, SyncReceiver, SyncSender,
+};
+
+fn frame(seed: u8) -> Frame {
+    let mut frame = Frame::new(4, 2, PixelFormat::Rgba8);
+    frame.data_mut().fill(seed);
+    frame
+}
+
+const SHORT: Duration = Duration::from_millis(30);
+const LONG: Duration = Duration::from_secs(5);
+
+#[test]
+fn local_sender_waits_for_any_or_all_clients() {
+    let server = LocalServer::new("frame-sync-local");
+    // Nothing published and nobody attached: nothing to wait for.
+    assert!(server.wait_consumed(SyncMode::All, SHORT));
+    server.publish(frame(1));
+    assert!(server.wait_consumed(SyncMode::All, SHORT));
+
+    let mut a = LocalClient::connect("frame-sync-local").unwrap();
+    let mut b = LocalClient::connect("frame-sync-local").unwrap();
+    server.publish(frame(2));
+    let start = Instant::now();
+    assert!(!server.wait_consumed(SyncMode::Any, SHORT));
+    assert!(start.elapsed() >= SHORT);
+
+    assert_eq!(a.receive_frame(), Some(frame(2)));
+    assert!(a.receive_frame().is_none());
+    assert_eq!(server.consumed_count(), 1, "taking twice counts once");
+    assert!(server.wait_consumed(SyncMode::Any, SHORT));
+    assert!(!server.wait_consumed(SyncMode::All, SHORT));
+    b.latest_frame().unwrap();
+    assert!(server.wait_consumed(SyncMode::All, SHORT));
+
+    // A new frame resets the count; a client detaching no longer holds the sender up.
+    server.publish(frame(3));
+    assert_eq!(server.consumed_count(), 0);
+    a.receive_frame().unwrap();
+    assert!(!server.wait_consumed(SyncMode::All, SHORT));
+    drop(b);
+    assert!(server.wait_consumed(SyncMode::All, SHORT));
+    drop(a);
+    assert_eq!(server.consumed_count(), 0);
+}
+
+#[test]
+fn local_sender_paces_to_a_slow_receiver() {
+    let mut server = LocalServer::new("frame-sync-paced");
+    let mut client = LocalClient::connect("frame-sync-paced").unwrap();
+    let reader = thread::spawn(move || {
+        let mut seen = Vec::new();
+        while let Some(frame) = client.wait_frame(Duration::from_secs(1)) {
+            seen.push(frame.data()[0]);
+            thread::sleep(Duration::from_millis(2));
+        }
+        (seen, client.stats().frames_dropped)
+    });
+    for seed in 1..=20 {
+        assert!(server
+            .send_frame_synced(&frame(seed), SyncMode::All, LONG)
+            .unwrap());
+    }
+    assert!(server.wait_consumed(SyncMode::All, LONG));
+    drop(server);
+    let (seen, dropped) = reader.join().unwrap();
+    assert_eq!(seen, (1..=20).collect::<Vec<u8>>());
+    assert_eq!(dropped, 0);
+}
+
+#[test]
+fn local_receiver_blocks_until_the_next_frame() {
+    let server = LocalServer::new("frame-sync-receiver");
+    let mut client: Box<dyn SyncReceiver> =
+        Box::new(LocalClient::connect("frame-sync-receiver").unwrap());
+    let start = Instant::now();
+    assert!(client.wait_frame(SHORT).is_none());
+    assert!(start.elapsed() >= SHORT);
+
+    let publisher = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        server.publish(frame(7));
+        server
+    });
+    assert_eq!(client.wait_frame(LONG), Some(frame(7)));
+    let server = publisher.join().unwrap();
+
+    // The server going away ends the wait early.
+    let start = Instant::now();
+    drop(server);
+    assert!(client.wait_frame(LONG).is_none());
+    assert!(start.elapsed() < LONG);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn memfd_clients_acknowledge_frames() {
+    use rusty_syphon_spout::{FrameSender, MemfdClient, MemfdServer};
+
+    let mut server = MemfdServer::bind("frame-sync-memfd").unwrap();
+    let mut a = MemfdClient::connect("frame-sync-memfd").unwrap();
+    let mut b = MemfdClient::connect("frame-sync-memfd").unwrap();
+    assert_eq!(server.client_count(), 2);
+    assert!(
+        server.wait_consumed(SyncMode::All, SHORT),
+        "before the first frame"
+    );
+
+    server.send_frame(&frame(1)).unwrap();
+    assert!(!server.wait_consumed(SyncMode::Any, SHORT));
+    assert_eq!(a.wait_frame(LONG), Some(frame(1)));
+    assert!(server.wait_consumed(SyncMode::Any, LONG));
+    assert_eq!(server.consumed_count(), 1);
+    assert!(!server.wait_consumed(SyncMode::All, SHORT));
+
+    // The wait wakes as soon as the other client takes the frame.
+    let reader = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        b.wait_frame(LONG)
+    });
+    assert!(server.wait_consumed(SyncMode::All, LONG));
+    assert_eq!(reader.join().unwrap(), Some(frame(1)));
+
+    // A client that connects after a frame never receives it and is not waited for; one that
+    // received it and disconnects is dropped from the wait.
+    server.send_frame(&frame(2)).unwrap();
+    let _late = MemfdClient::connect("frame-sync-memfd").unwrap();
+    a.wait_frame(LONG).unwrap();
+    assert!(server.wait_consumed(SyncMode::All, LONG));
+    server.send_frame(&frame(3)).unwrap();
+    drop(a);
+    assert!(
+        !server.wait_consumed(SyncMode::Any, SHORT),
+        "late client has not taken it"
+    );
+}
@@ -0,0 +1,178 @@
+//! GStreamer elements with local servers (`--features gstreamer`; needs the GStreamer runtime).
+
+#![cfg(feature = "gstreamer")]
+
+use std::time::{Duration, Instant};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use rusty_syphon_spout::{
+    gst_plugin_register_static, Frame, FrameCompare, LocalClient, LocalServer, PixelFormat,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn init() {
+    gst::init().unwrap();
+    gst_plugin_register_static().unwrap();
+}
+
+fn pattern(width: u32, height: u32, seed: u8) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[(x * 7) as u8, (y * 3) as u8, seed, 255]);
+        }
+    }
+    frame
+}
+
+/// A started pipeline, stopped on drop.
+struct Pipeline(gst::Element);
+
+impl Pipeline {
+    fn start(description: &str) -> Self {
+        let pipeline = gst::parse::launch(description).unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        Self(pipeline)
+    }
+
+    /// The next EOS or error on the bus.
+    fn wait_end(&self, timeout: Duration) -> Option<gst::Message> {
+        self.0.bus().unwrap().timed_pop_filtered(
+            gst::ClockTime::from_mseconds(timeout.as_millis() as u64),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        )
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        let _ = self.0.set_state(gst::State::Null);
+    }
+}
+
+/// Publish `frame` on `server` until `client` sees a frame matching `expected`, and return it.
+fn publish_until_received(
+    server: &LocalServer,
+    frame: &Frame,
+    client: &mut LocalClient,
+    expected: &Frame,
+) -> Frame {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        server.publish(frame.clone());
+        if let Some(received) = client.wait_new_frame(Duration::from_millis(50)) {
+            if received.size() == expected.size()
+                && FrameCompare::new().check(expected, &received).is_ok()
+            {
+                return (*received).clone();
+            }
+        }
+        assert!(Instant::now() < deadline, "frame not relayed");
+    }
+}
+
+/// Connect to the server a sink publishes, once it is up.
+fn connect_sink(name: &str) -> LocalClient {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Some(client) = LocalClient::connect(name) {
+            return client;
+        }
+        assert!(Instant::now() < deadline, "sink server not published");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn elements_register_with_templates() {
+    init();
+    let src = gst::ElementFactory::make("syphonspoutsrc").build().unwrap();
+    let sink = gst::ElementFactory::make("syphonspoutsink")
+        .build()
+        .unwrap();
+    assert_eq!(sink.property::<String>("server-name"), "GStreamer");
+    assert_eq!(sink.property::<String>("backend"), "local");
+    let caps = src.static_pad("src").unwrap().pad_template_caps();
+    assert!(caps.to_string().contains("RGBA"));
+    assert!(caps.to_string().contains("BGRA"));
+}
+
+#[test]
+fn relays_a_local_server_through_a_pipeline() {
+    init();
+    let source = LocalServer::new("gst-relay-in");
+    source.publish(pattern(40, 30, 1));
+    let _pipeline = Pipeline::start(
+        "syphonspoutsrc source=gst-relay-in ! queue ! syphonspoutsink server-name=gst-relay-out",
+    );
+    let mut client = connect_sink("gst-relay-out");
+    let frame = pattern(40, 30, 2);
+    publish_until_received(&source, &frame, &mut client, &frame);
+
+    // A new size renegotiates caps downstream.
+    let larger = pattern(64, 48, 3);
+    publish_until_received(&source, &larger, &mut client, &larger);
+}
+
+#[test]
+fn converts_to_the_format_downstream_asks_for() {
+    init();
+    let source = LocalServer::new("gst-format-in");
+    source.publish(pattern(16, 8, 1));
+    let _pipeline = Pipeline::start(
+        "syphonspoutsrc source=gst-format-in ! video/x-raw,format=BGRA \
+         ! syphonspoutsink server-name=gst-format-out",
+    );
+    let mut client = connect_sink("gst-format-out");
+    let frame = pattern(16, 8, 4);
+    let bgra = frame.to_format(PixelFormat::Bgra8);
+    let received = publish_until_received(&source, &frame, &mut client, &bgra);
+    assert_eq!(received.format(), PixelFormat::Bgra8);
+}
+
+#[test]
+fn source_going_away_ends_the_stream() {
+    init();
+    let source = LocalServer::new("gst-eos-in");
+    source.publish(pattern(8, 8, 1));
+    let pipeline = Pipeline::start("syphonspoutsrc source=gst-eos-in ! fakesink");
+    std::thread::sleep(Duration::from_millis(100));
+    drop(source);
+    let message = pipeline.wait_end(TIMEOUT).expect("EOS");
+    assert!(
+        matches!(message.view(), gst::MessageView::Eos(_)),
+        "{message:?}"
+    );
+}
+
+#[test]
+fn missing_source_or_backend_fails_to_start() {
+    init();
+    let pipeline =
+        gst::parse::launch("syphonspoutsrc source=gst-no-such-source ! fakesink").unwrap();
+    assert!(pipeline.set_state(gst::State::Playing).is_err());
+    let _ = pipeline.set_state(gst::State::Null);
+
+    let pipeline = gst::parse::launch("fakesrc ! syphonspoutsink backend=bogus").unwrap();
+    assert!(pipeline.set_state(gst::State::Playing).is_err());
+    let _ = pipeline.set_state(gst::State::Null);
+}
+
+#[test]
+fn spout_backend_is_rejected() {
+    init();
+    let pipeline = gst::parse::launch("fakesrc ! syphonspoutsink backend=spout").unwrap();
+    assert!(pipeline.set_state(gst::State::Playing).is_err());
+    let message = pipeline
+        .bus()
+        .unwrap()
+        .timed_pop_filtered(gst::ClockTime::from_seconds(1), &[gst::MessageType::Error])
+        .unwrap();
+    let gst::MessageView::Error(err) = message.view() else {
+        unreachable!()
+    };
+    assert!(err.error().matches(gst::LibraryError::Settings), "{err:?}");
+    let _ = pipeline.set_state(gst::State::Null);
+}
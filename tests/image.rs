@@ -0,0 +1,86 @@
+//! `image` crate conversions and `publish_image` / `receive_image` over local servers
+//! (`--features image`).
+
+#![cfg(feature = "image")]
+
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+use rusty_syphon_spout::{
+    Frame, FrameReceiver, FrameSender, ImageReceiver, ImageSender, LocalClient, LocalServer,
+    PixelFormat, ToFrame,
+};
+
+fn pattern(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        Rgba([x as u8 * 10, y as u8 * 20, 7, 200])
+    })
+}
+
+#[test]
+fn frames_convert_to_and_from_images() {
+    let image = pattern(5, 3);
+    let frame = image.to_frame();
+    assert_eq!((frame.width(), frame.height()), (5, 3));
+    assert_eq!(frame.format(), PixelFormat::Rgba8);
+    assert_eq!(&frame.row(2)[4..8], &[10, 40, 7, 200]);
+    assert_eq!(Frame::from(image.clone()), frame);
+    assert_eq!(RgbaImage::from(frame.clone()), image);
+    assert_eq!(frame.to_rgba_image(), image);
+
+    // BGRA with padded rows comes back as packed RGBA.
+    let bgra = frame.to_format(PixelFormat::Bgra8);
+    let mut data = vec![0xee; 24 * 3];
+    for y in 0..3 {
+        data[y * 24..y * 24 + 20].copy_from_slice(bgra.row(y as u32));
+    }
+    let padded = Frame::from_vec_with_stride(5, 3, 24, PixelFormat::Bgra8, data).unwrap();
+    assert_eq!(padded.to_rgba_image(), image);
+    assert_eq!(RgbaImage::from(padded.clone()), image);
+    assert_eq!(
+        DynamicImage::from(padded),
+        DynamicImage::ImageRgba8(image.clone())
+    );
+
+    // Views over a borrowed buffer work too.
+    let view: ImageBuffer<Rgba<u8>, &[u8]> =
+        ImageBuffer::from_raw(5, 3, image.as_raw().as_slice()).unwrap();
+    assert_eq!(view.to_frame(), frame);
+}
+
+#[test]
+fn other_pixel_types_become_rgba() {
+    let rgb = RgbImage::from_fn(2, 2, |x, y| Rgb([x as u8, y as u8, 9]));
+    let frame = rgb.to_frame();
+    assert_eq!(frame.row(1), &[0, 1, 9, 255, 1, 1, 9, 255]);
+    assert_eq!(DynamicImage::ImageRgb8(rgb).to_frame(), frame);
+
+    let gray: GrayImage = ImageBuffer::from_pixel(3, 1, Luma([80]));
+    let frame = DynamicImage::ImageLuma8(gray).to_frame();
+    assert_eq!(frame.row(0), &[80, 80, 80, 255].repeat(3)[..]);
+    let frame = Frame::from(DynamicImage::new_rgba16(4, 2));
+    assert_eq!((frame.width(), frame.height()), (4, 2));
+}
+
+#[test]
+fn publish_and_receive_images() {
+    let mut server = LocalServer::new("image-roundtrip");
+    let mut client = LocalClient::connect("image-roundtrip").unwrap();
+    assert!(client.receive_image().is_none());
+
+    let image = pattern(6, 4);
+    server.publish_image(&image).unwrap();
+    assert_eq!(client.receive_image().unwrap(), image);
+
+    // BGRA frames from the sender side are converted on receive.
+    server
+        .send_frame(&image.to_frame().to_format(PixelFormat::Bgra8))
+        .unwrap();
+    assert_eq!(client.receive_image().unwrap(), image);
+
+    // Through trait objects as returned by discovery.
+    let mut sender: Box<dyn FrameSender> = Box::new(server);
+    let mut receiver: Box<dyn FrameReceiver> = Box::new(client);
+    sender
+        .publish_image(&DynamicImage::ImageRgba8(image.clone()))
+        .unwrap();
+    assert_eq!(receiver.receive_image().unwrap(), image);
+}
@@ -0,0 +1,148 @@
+//! Frame checksums, integrity mode and receiver stats (runs on every platform).
+
+use std::collections::VecDeque;
+
+use rusty_syphon_spout::{
+    frame_checksum, FileMemoryBuffer, Frame, FrameReceiver, FrameSender, IntegrityReceiver,
+    IntegritySender, LocalClient, LocalServer, PixelFormat, INTEGRITY_WINDOW,
+};
+
+fn numbered(n: u8) -> Frame {
+    Frame::from_vec(4, 2, PixelFormat::Rgba8, vec![n; 32]).unwrap()
+}
+
+/// Hands out queued frames, optionally flipping a byte to simulate a torn read.
+struct Queue {
+    frames: VecDeque<Frame>,
+    tear: bool,
+}
+
+impl FrameReceiver for Queue {
+    fn receive_frame(&mut self) -> Option<Frame> {
+        let mut frame = self.frames.pop_front()?;
+        if self.tear {
+            frame.row_mut(1)[0] ^= 0xFF;
+        }
+        Some(frame)
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn checksum_covers_pixels_and_layout_but_not_padding() {
+    let frame = numbered(7);
+    assert_eq!(frame_checksum(&frame), frame_checksum(&numbered(7)));
+    assert_ne!(frame_checksum(&frame), frame_checksum(&numbered(8)));
+    assert_ne!(
+        frame_checksum(&frame),
+        frame_checksum(&frame.clone().to_format(PixelFormat::Bgra8))
+    );
+
+    let mut padded_data = vec![0xAA; 20 * 2];
+    padded_data[..16].fill(7);
+    padded_data[20..36].fill(7);
+    let padded = Frame::from_vec_with_stride(4, 2, 20, PixelFormat::Rgba8, padded_data).unwrap();
+    assert_eq!(frame_checksum(&padded), frame_checksum(&frame));
+}
+
+#[test]
+fn local_frames_verify_through_side_channel() {
+    let name = "integrity-test/local";
+    let server = LocalServer::new(name);
+    let client = LocalClient::connect(name).unwrap();
+    let mut sender = IntegritySender::new(server, FileMemoryBuffer::new(name));
+    let mut receiver = IntegrityReceiver::new(client, FileMemoryBuffer::new(name));
+
+    for n in 0..3 {
+        sender.send_frame(&numbered(n)).unwrap();
+        assert_eq!(receiver.receive_frame(), Some(numbered(n)));
+    }
+    // Two frames published between polls: one is dropped, the latest still verifies.
+    sender.send_frame(&numbered(10)).unwrap();
+    sender.send_frame(&numbered(11)).unwrap();
+    assert_eq!(receiver.receive_frame(), Some(numbered(11)));
+
+    let stats = receiver.stats().unwrap();
+    assert_eq!(stats.frames_received, 4);
+    assert_eq!(stats.frames_verified, 4);
+    assert_eq!(stats.frames_dropped, 1);
+    assert_eq!(stats.frames_corrupted, 0);
+    assert!(stats.last_frame_at.is_some());
+    FileMemoryBuffer::new(name).remove().unwrap();
+}
+
+#[test]
+fn torn_frames_are_reported_and_dropped() {
+    let name = "integrity-test/torn";
+    let mut sender = IntegritySender::new(LocalServer::new(name), FileMemoryBuffer::new(name));
+    let frames: VecDeque<Frame> = (0..2).map(numbered).collect();
+    for frame in &frames {
+        sender.send_frame(frame).unwrap();
+    }
+
+    let queue = Queue {
+        frames: frames.clone(),
+        tear: true,
+    };
+    let mut receiver = IntegrityReceiver::new(queue, FileMemoryBuffer::new(name));
+    assert_eq!(receiver.receive_frame(), None);
+    assert_eq!(receiver.stats().unwrap().frames_corrupted, 1);
+
+    let queue = Queue { frames, tear: true };
+    let mut receiver =
+        IntegrityReceiver::new(queue, FileMemoryBuffer::new(name)).drop_corrupted(false);
+    assert!(receiver.receive_frame().is_some());
+    assert_eq!(receiver.stats().unwrap().frames_corrupted, 1);
+    FileMemoryBuffer::new(name).remove().unwrap();
+}
+
+#[test]
+fn late_reads_verify_within_window() {
+    let name = "integrity-test/window";
+    let mut sender = IntegritySender::new(LocalServer::new(name), FileMemoryBuffer::new(name));
+    let frames: VecDeque<Frame> = (0..INTEGRITY_WINDOW as u8 + 1).map(numbered).collect();
+    for frame in &frames {
+        sender.send_frame(frame).unwrap();
+    }
+    let queue = Queue {
+        frames,
+        tear: false,
+    };
+    let mut receiver =
+        IntegrityReceiver::new(queue, FileMemoryBuffer::new(name)).drop_corrupted(false);
+    while receiver.receive_frame().is_some() {}
+    let stats = receiver.stats().unwrap();
+    // The first frame fell out of the window; the rest verify.
+    assert_eq!(stats.frames_corrupted, 1);
+    assert_eq!(stats.frames_verified, INTEGRITY_WINDOW as u64);
+    FileMemoryBuffer::new(name).remove().unwrap();
+}
+
+#[test]
+fn frames_without_checksums_are_unverified() {
+    let name = "integrity-test/unverified";
+    let queue = Queue {
+        frames: [numbered(1)].into(),
+        tear: false,
+    };
+    let mut receiver = IntegrityReceiver::new(queue, FileMemoryBuffer::new(name));
+    assert!(receiver.receive_frame().is_some());
+    assert_eq!(receiver.stats().unwrap().frames_unverified, 1);
+}
+
+#[test]
+fn local_client_counts_dropped_frames() {
+    let server = LocalServer::new("integrity-test/stats");
+    let mut client = LocalClient::connect("integrity-test/stats").unwrap();
+    for n in 0..3 {
+        server.publish(numbered(n));
+    }
+    assert!(client.new_frame().is_some());
+    server.publish(numbered(3));
+    assert!(client.receive_frame().is_some());
+    let stats = client.stats();
+    assert_eq!((stats.frames_received, stats.frames_dropped), (2, 2));
+}
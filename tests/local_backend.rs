@@ -0,0 +1,89 @@
+//! Pure-Rust in-process backend (runs on every platform).
+
+use rusty_syphon_spout::{
+    local_servers, Frame, FrameReceiver, FrameSender, LocalClient, LocalServer, PixelFormat, Size,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Frame {
+    let data = rgba.repeat((width * height) as usize);
+    Frame::from_vec(width, height, PixelFormat::Rgba8, data).unwrap()
+}
+
+#[test]
+fn server_is_listed_until_dropped() {
+    let server = LocalServer::new("local-backend-listed");
+    let info = local_servers()
+        .into_iter()
+        .find(|s| s.uuid == server.uuid())
+        .expect("server in directory");
+    assert_eq!(info.name, "local-backend-listed");
+    assert_eq!(info.size, None);
+
+    server.publish(solid(4, 2, [1, 2, 3, 4]));
+    assert_eq!(server.info().size, Some(Size::new(4, 2)));
+
+    let uuid = server.uuid().to_string();
+    drop(server);
+    assert!(local_servers().iter().all(|s| s.uuid != uuid));
+    assert!(LocalClient::connect_uuid(&uuid).is_none());
+}
+
+#[test]
+fn client_receives_each_frame_once_without_copying() {
+    let server = LocalServer::new("local-backend-frames");
+    let mut client = LocalClient::connect("local-backend-frames").expect("connect");
+    assert!(server.has_clients());
+    assert!(client.receive_frame().is_none());
+
+    let frame = Arc::new(solid(2, 2, [9, 9, 9, 255]));
+    assert_eq!(server.publish(frame.clone()), 1);
+    assert!(client.has_new_frame());
+    let received = client.new_frame().unwrap();
+    assert!(Arc::ptr_eq(&received, &frame));
+    assert!(client.new_frame().is_none());
+    assert_eq!(client.last_frame_number(), 1);
+
+    drop(client);
+    assert!(!server.has_clients());
+}
+
+#[test]
+fn late_client_does_not_count_earlier_frames_as_dropped() {
+    let server = LocalServer::new("local-backend-late");
+    for seed in 1..=5 {
+        server.publish(solid(1, 1, [seed, 0, 0, 255]));
+    }
+    let mut client = LocalClient::connect_uuid(server.uuid()).unwrap();
+    assert!(client.has_new_frame(), "the current frame is still new");
+    assert_eq!(client.new_frame().unwrap().data(), &[5, 0, 0, 255]);
+    assert_eq!(client.stats().frames_dropped, 0);
+
+    // Frames it misses from now on do count.
+    server.publish(solid(1, 1, [6, 0, 0, 255]));
+    server.publish(solid(1, 1, [7, 0, 0, 255]));
+    client.new_frame().unwrap();
+    assert_eq!(client.stats().frames_dropped, 1);
+}
+
+#[test]
+fn wait_new_frame_wakes_on_publish_and_stop() {
+    let mut server = LocalServer::new("local-backend-wait");
+    let mut client = LocalClient::connect_uuid(server.uuid()).unwrap();
+    assert!(client.wait_new_frame(Duration::from_millis(10)).is_none());
+
+    let publisher = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        server.send_frame(&solid(1, 1, [5, 6, 7, 8])).unwrap();
+        server
+    });
+    let frame = client.wait_new_frame(Duration::from_secs(5)).expect("frame");
+    assert_eq!(frame.data(), &[5, 6, 7, 8]);
+
+    let server = publisher.join().unwrap();
+    assert!(client.is_connected());
+    drop(server);
+    assert!(!client.is_connected());
+    assert!(client.wait_new_frame(Duration::from_secs(5)).is_none());
+}
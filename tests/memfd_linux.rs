@@ -0,0 +1,336 @@
+//! Sealed memfd frames over a Unix socket on Linux.
+
+#![cfg(target_os = "linux")]
+
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::time::Duration;
+
+use rusty_syphon_spout::{
+    ColorSpace, Frame, FrameCompare, FrameReceiver, FrameSender, MemfdClient, MemfdServer,
+    PixelFormat,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn pattern(width: u32, height: u32, seed: u8) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Bgra8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[x as u8 ^ seed, y as u8, seed, 255]);
+        }
+    }
+    frame
+}
+
+fn unique(name: &str) -> String {
+    format!("{name}-{}", std::process::id())
+}
+
+#[test]
+fn roundtrip_keeps_pixels_format_and_color_space() {
+    let name = unique("memfd-roundtrip");
+    let mut server = MemfdServer::bind(&name).unwrap();
+    let mut client = MemfdClient::connect(&name).unwrap();
+    assert_eq!(server.client_count(), 1);
+
+    // Rows padded to a larger stride arrive packed.
+    let padded = pattern(17, 9, 3);
+    let mut data = vec![0xAA; 80 * 9];
+    for y in 0..9 {
+        data[y * 80..y * 80 + 68].copy_from_slice(padded.row(y as u32));
+    }
+    let sent = Frame::from_vec_with_stride(17, 9, 80, PixelFormat::Bgra8, data)
+        .unwrap()
+        .with_color_space(ColorSpace::DisplayP3);
+    assert!(!sent.is_packed());
+    server.send_frame(&sent).unwrap();
+
+    let received = client.recv_timeout(TIMEOUT).expect("frame");
+    assert_eq!(received.format(), PixelFormat::Bgra8);
+    assert_eq!(received.color_space(), ColorSpace::DisplayP3);
+    assert!(received.is_packed());
+    FrameCompare::new().assert(&sent, &received);
+    assert!(client.receive_frame().is_none());
+}
+
+#[test]
+fn received_buffers_are_sealed() {
+    // Peek at the fd the server sends, as a client would, and try to resize and write it.
+    let frame = pattern(8, 8, 1);
+    let memfd = sealed_copy(&frame);
+    let seals = unsafe { libc::fcntl(memfd.as_raw_fd(), libc::F_GET_SEALS) };
+    for seal in [
+        libc::F_SEAL_SHRINK,
+        libc::F_SEAL_GROW,
+        libc::F_SEAL_WRITE,
+        libc::F_SEAL_SEAL,
+    ] {
+        assert_ne!(seals & seal, 0, "seal {seal:#x} missing");
+    }
+    let file = std::fs::File::from(memfd);
+    assert!(file.set_len(4).is_err());
+    assert!(file.set_len(1 << 20).is_err());
+    use std::os::unix::fs::FileExt;
+    assert!(file.write_at(&[0], 0).is_err());
+}
+
+/// Receive one frame's fd through a raw socket connection, bypassing `MemfdClient`.
+fn sealed_copy(frame: &Frame) -> OwnedFd {
+    use std::os::unix::net::UnixDatagram;
+    let name = unique("memfd-sealed");
+    let mut server = MemfdServer::bind(&name).unwrap();
+    let path = server.path().to_path_buf();
+    let socket = unsafe {
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0);
+        assert!(fd >= 0);
+        let mut addr: libc::sockaddr_un = std::mem::zeroed();
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        for (dst, src) in addr.sun_path.iter_mut().zip(path.to_str().unwrap().bytes()) {
+            *dst = src as libc::c_char;
+        }
+        let rc = libc::connect(
+            fd,
+            &addr as *const _ as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t,
+        );
+        assert_eq!(rc, 0);
+        <UnixDatagram as std::os::fd::FromRawFd>::from_raw_fd(fd)
+    };
+    assert_eq!(server.client_count(), 1);
+    server.publish(frame).unwrap();
+
+    let mut buf = [0u8; 64];
+    let mut control = [0u64; 4];
+    unsafe {
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr().cast(),
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = std::mem::size_of_val(&control) as _;
+        assert!(libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) > 0);
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        assert!(!cmsg.is_null());
+        let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<i32>());
+        <OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd)
+    }
+}
+
+#[test]
+fn every_client_gets_the_frame() {
+    let name = unique("memfd-fanout");
+    let mut server = MemfdServer::bind(&name).unwrap();
+    let mut clients: Vec<_> = (0..3)
+        .map(|_| MemfdClient::connect(&name).unwrap())
+        .collect();
+    assert_eq!(server.client_count(), 3);
+    let sent = pattern(16, 9, 7);
+    server.send_frame(&sent).unwrap();
+    for client in &mut clients {
+        let received = client.recv_timeout(TIMEOUT).expect("frame");
+        FrameCompare::new().assert(&sent, &received);
+    }
+}
+
+#[test]
+fn client_keeps_latest_and_counts_drops() {
+    let name = unique("memfd-drops");
+    let mut server = MemfdServer::bind(&name).unwrap();
+    let mut client = MemfdClient::connect(&name).unwrap();
+    for seed in 0..4 {
+        server.send_frame(&pattern(8, 8, seed)).unwrap();
+    }
+    let received = client.recv_timeout(TIMEOUT).expect("frame");
+    FrameCompare::new().assert(&pattern(8, 8, 3), &received);
+    assert_eq!(client.last_frame_number(), 4);
+    let stats = client.stats();
+    assert_eq!(stats.frames_received, 1);
+    assert_eq!(stats.frames_dropped, 3);
+    assert!(stats.last_frame_at.is_some());
+}
+
+#[test]
+fn late_client_does_not_count_earlier_frames_as_dropped() {
+    let name = unique("memfd-late");
+    let mut server = MemfdServer::bind(&name).unwrap();
+    for seed in 0..3 {
+        server.send_frame(&pattern(8, 8, seed)).unwrap();
+    }
+    let mut client = MemfdClient::connect(&name).unwrap();
+    server.send_frame(&pattern(8, 8, 3)).unwrap();
+    let received = client.recv_timeout(TIMEOUT).expect("frame");
+    FrameCompare::new().assert(&pattern(8, 8, 3), &received);
+    assert_eq!(client.last_frame_number(), 4);
+    assert_eq!(client.stats().frames_dropped, 0);
+}
+
+#[test]
+fn client_notices_server_going_away() {
+    let name = unique("memfd-disconnect");
+    let server = MemfdServer::bind(&name).unwrap();
+    let mut client = MemfdClient::connect(&name).unwrap();
+    let path = server.path().to_path_buf();
+    assert!(client.is_connected());
+    drop(server);
+    assert!(!path.exists());
+    assert!(client.recv_timeout(Duration::from_millis(200)).is_none());
+    assert!(!client.is_connected());
+    assert!(MemfdClient::connect(&name).is_err());
+}
+
+/// A raw socket standing in for a server: a client connected to it, and the server's end.
+fn fake_server(name: &str) -> (MemfdClient, OwnedFd, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(unique(name));
+    let _ = std::fs::remove_file(&path);
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (dst, src) in addr.sun_path.iter_mut().zip(path.to_str().unwrap().bytes()) {
+        *dst = src as libc::c_char;
+    }
+    let addr_len = std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+    let listener = unsafe {
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0);
+        assert!(fd >= 0);
+        assert_eq!(libc::bind(fd, &addr as *const _ as *const _, addr_len), 0);
+        assert_eq!(libc::listen(fd, 1), 0);
+        <OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd)
+    };
+    let client = MemfdClient::connect_path(&path).unwrap();
+    let peer = unsafe {
+        let fd = libc::accept(
+            listener.as_raw_fd(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+        assert!(fd >= 0);
+        <OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd)
+    };
+    (client, peer, path)
+}
+
+/// Send `payload` with the fd of /dev/null attached.
+fn send_with_fd(peer: &OwnedFd, payload: &mut [u8]) {
+    let passed = std::fs::File::open("/dev/null").unwrap();
+    send_with_fds(peer, payload, &[passed.as_raw_fd()]);
+}
+
+/// Send `payload` with up to four fds attached.
+fn send_with_fds(peer: &OwnedFd, payload: &mut [u8], fds: &[i32]) {
+    let fds_len = std::mem::size_of_val(fds) as u32;
+    let mut control = [0u64; 4];
+    unsafe {
+        let mut iov = libc::iovec {
+            iov_base: payload.as_mut_ptr().cast(),
+            iov_len: payload.len(),
+        };
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = libc::CMSG_SPACE(fds_len) as _;
+        assert!(msg.msg_controllen <= std::mem::size_of_val(&control));
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len) as _;
+        let data = libc::CMSG_DATA(cmsg).cast::<i32>();
+        for (i, fd) in fds.iter().enumerate() {
+            std::ptr::write_unaligned(data.add(i), *fd);
+        }
+        assert_eq!(
+            libc::sendmsg(peer.as_raw_fd(), &msg, 0),
+            payload.len() as isize
+        );
+    }
+}
+
+#[test]
+fn oversized_packets_are_skipped() {
+    // A fake server sends one byte more than a header, with an fd, as a newer version might.
+    let (mut client, peer, path) = fake_server("memfd-oversized.sock");
+    let mut payload = [0u8; 41];
+    payload[0..4].copy_from_slice(b"RSMF");
+    send_with_fd(&peer, &mut payload);
+
+    assert!(client.recv_timeout(Duration::from_millis(50)).is_none());
+    assert!(
+        client.is_connected(),
+        "a malformed packet is not a disconnect"
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn bad_frames_take_nothing_from_the_pool() {
+    // A well-formed header for a 64x64 RGBA frame, but the fd is not a sealed memfd and the
+    // stride is too short for a row.
+    let (mut client, peer, path) = fake_server("memfd-bad-pooled.sock");
+    let mut header = [0u8; 40];
+    header[0..4].copy_from_slice(b"RSMF");
+    header[4..6].copy_from_slice(&1u16.to_le_bytes());
+    header[8..12].copy_from_slice(&64u32.to_le_bytes());
+    header[12..16].copy_from_slice(&64u32.to_le_bytes());
+    header[16..20].copy_from_slice(&4u32.to_le_bytes());
+    header[24..32].copy_from_slice(&1u64.to_le_bytes());
+    send_with_fd(&peer, &mut header);
+
+    let pool = rusty_syphon_spout::FramePool::default();
+    assert!(client.try_recv_pooled(&pool).is_none());
+    assert_eq!(pool.stats().allocations, 0);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn extra_fds_are_closed() {
+    // Two copies of a pipe's write end arrive with a packet the client rejects. Once the client
+    // has closed both, dropping ours leaves the pipe with no writers.
+    let (mut client, peer, path) = fake_server("memfd-extra-fds.sock");
+    let mut pipe = [0i32; 2];
+    assert_eq!(
+        unsafe { libc::pipe2(pipe.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) },
+        0
+    );
+    let (read_end, write_end) = unsafe {
+        use std::os::fd::FromRawFd;
+        (OwnedFd::from_raw_fd(pipe[0]), OwnedFd::from_raw_fd(pipe[1]))
+    };
+    let mut payload = [0u8; 40];
+    let passed = write_end.as_raw_fd();
+    send_with_fds(&peer, &mut payload, &[passed, passed]);
+    assert!(client.recv_timeout(Duration::from_millis(50)).is_none());
+    drop(write_end);
+
+    let mut byte = 0u8;
+    let read = unsafe { libc::read(read_end.as_raw_fd(), (&mut byte as *mut u8).cast(), 1) };
+    assert_eq!(read, 0, "a write end is still open");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn binding_over_a_live_server_fails() {
+    let name = unique("memfd-taken");
+    let mut server = MemfdServer::bind(&name).unwrap();
+    let err = MemfdServer::bind(&name).err().expect("second bind");
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+    // The first server still has its address.
+    let mut client = MemfdClient::connect(&name).unwrap();
+    let sent = pattern(4, 4, 1);
+    server.send_frame(&sent).unwrap();
+    FrameCompare::new().assert(&sent, &client.recv_timeout(TIMEOUT).expect("frame"));
+}
+
+#[test]
+fn stale_socket_files_are_replaced() {
+    let name = unique("memfd-stale");
+    let path = rusty_syphon_spout::memfd_socket_path(&name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let _ = std::fs::remove_file(&path);
+    drop(std::os::unix::net::UnixDatagram::bind(&path).unwrap());
+    assert!(path.exists());
+    let _server = MemfdServer::bind(&name).unwrap();
+    assert!(MemfdClient::connect(&name).is_ok());
+}
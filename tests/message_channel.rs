@@ -0,0 +1,132 @@
+//! Framed message channel over memory buffers (runs on every platform).
+
+use rusty_syphon_spout::{
+    ChannelError, FileMemoryBuffer, MemoryBuffer, MessageReceiver, MessageSender, PayloadKind,
+    MESSAGE_HEADER_LEN,
+};
+use std::cell::RefCell;
+
+/// In-memory stand-in for a shared buffer, so tests can corrupt or truncate it.
+#[derive(Default)]
+struct TestBuffer(RefCell<Vec<u8>>);
+
+impl MemoryBuffer for TestBuffer {
+    fn write_bytes(&self, data: &[u8]) -> bool {
+        *self.0.borrow_mut() = data.to_vec();
+        true
+    }
+
+    fn read_bytes(&self, out: &mut [u8]) -> usize {
+        let data = self.0.borrow();
+        let n = out.len().min(data.len());
+        out[..n].copy_from_slice(&data[..n]);
+        n
+    }
+}
+
+#[test]
+fn each_message_is_received_once() {
+    let buffer = TestBuffer::default();
+    let mut sender = MessageSender::new(&buffer);
+    let mut receiver = MessageReceiver::new(&buffer);
+
+    assert_eq!(receiver.try_recv(), Ok(None));
+    assert_eq!(sender.send(b"hello"), Ok(1));
+    assert!(receiver.has_new_message());
+
+    let message = receiver.try_recv().unwrap().expect("new message");
+    assert_eq!(message.sequence, 1);
+    assert_eq!(message.session, sender.session());
+    assert_eq!(message.kind, PayloadKind::Bytes);
+    assert_eq!(message.payload, b"hello");
+
+    assert!(!receiver.has_new_message());
+    assert_eq!(receiver.try_recv(), Ok(None));
+
+    sender.send(b"second").unwrap();
+    assert_eq!(receiver.try_recv().unwrap().unwrap().payload, b"second");
+}
+
+#[test]
+fn restarted_sender_is_seen_as_new() {
+    let buffer = TestBuffer::default();
+    let mut receiver = MessageReceiver::new(&buffer);
+    MessageSender::new(&buffer).send(b"first run").unwrap();
+    assert_eq!(receiver.try_recv().unwrap().unwrap().sequence, 1);
+
+    // A new sender starts again at sequence 1 but with a different session.
+    MessageSender::new(&buffer).send(b"second run").unwrap();
+    assert_eq!(receiver.try_recv().unwrap().unwrap().payload, b"second run");
+}
+
+#[test]
+fn corrupt_and_truncated_messages_are_rejected() {
+    let buffer = TestBuffer::default();
+    let mut sender = MessageSender::new(&buffer);
+    let mut receiver = MessageReceiver::new(&buffer);
+
+    sender.send(b"payload").unwrap();
+    buffer.0.borrow_mut()[MESSAGE_HEADER_LEN + 2] ^= 0xFF;
+    assert_eq!(receiver.try_recv(), Err(ChannelError::ChecksumMismatch));
+
+    sender.send(b"payload").unwrap();
+    buffer.0.borrow_mut().truncate(MESSAGE_HEADER_LEN + 3);
+    assert_eq!(receiver.try_recv(), Err(ChannelError::Truncated));
+
+    buffer.write_bytes(b"not a framed message at all, just bytes");
+    assert_eq!(receiver.try_recv(), Err(ChannelError::BadMagic));
+}
+
+#[test]
+fn payload_limits_are_enforced() {
+    let buffer = TestBuffer::default();
+    let mut sender = MessageSender::new(&buffer).with_max_payload(4);
+    assert_eq!(
+        sender.send(b"too long"),
+        Err(ChannelError::TooLarge { len: 8, max: 4 })
+    );
+    assert_eq!(sender.sequence(), 0);
+
+    let mut sender = MessageSender::new(&buffer);
+    sender.send(b"too long").unwrap();
+    let mut receiver = MessageReceiver::new(&buffer).with_max_payload(4);
+    assert_eq!(
+        receiver.try_recv(),
+        Err(ChannelError::TooLarge { len: 8, max: 4 })
+    );
+}
+
+#[test]
+fn file_buffer_roundtrip() {
+    let name = format!("message-channel-test-{}", std::process::id());
+    let buffer = FileMemoryBuffer::new(&name);
+    let mut sender = MessageSender::new(buffer.clone());
+    let mut receiver = MessageReceiver::new(FileMemoryBuffer::new(&name));
+
+    sender.send(&[1, 2, 3]).unwrap();
+    assert_eq!(receiver.try_recv().unwrap().unwrap().payload, vec![1, 2, 3]);
+    assert_eq!(receiver.try_recv(), Ok(None));
+    buffer.remove().unwrap();
+    assert_eq!(receiver.try_recv(), Ok(None));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_values_roundtrip() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Cue {
+        scene: String,
+        intensity: f32,
+    }
+
+    let buffer = TestBuffer::default();
+    let mut sender = MessageSender::new(&buffer);
+    let mut receiver = MessageReceiver::new(&buffer);
+    let cue = Cue {
+        scene: "intro".into(),
+        intensity: 0.5,
+    };
+    sender.send_value(&cue).unwrap();
+    assert_eq!(receiver.try_recv_value::<Cue>(), Ok(Some(cue)));
+    assert_eq!(receiver.try_recv_value::<Cue>(), Ok(None));
+}
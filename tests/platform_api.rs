@@ -23,6 +23,10 @@ mod macos {
             MetalServer::new;
         let _: fn(&ServerDescription, MTLDevicePtr, Option<&std::collections::HashMap<String, String>>, Option<NewFrameCallback>) -> Option<MetalClient> =
             MetalClient::new;
+        let _: fn(&OpenGLServer, u32, u32, &PublishRequest) -> Result<(), PublishError> =
+            OpenGLServer::publish;
+        let _: fn(&MetalServer, MTLTexturePtr, MTLCommandBufferPtr, &PublishRequest) -> Result<(), PublishError> =
+            MetalServer::publish;
     }
 
     #[test]
@@ -53,6 +57,10 @@ mod windows {
         let _: fn(&Spout, u32, u32, u32, u32, bool) -> bool = Spout::sender_send_texture;
         let _: fn(&Spout, u32, u32, u32, bool) -> bool = Spout::sender_send_fbo;
         let _: fn(&Spout, &[u8], u32, u32, u32, bool) -> bool = Spout::sender_send_image;
+        let _: fn(&Spout, u32, u32, &PublishRequest) -> Result<(), PublishError> =
+            Spout::sender_publish_texture;
+        let _: fn(&Spout, &Frame, &PublishRequest) -> Result<(), PublishError> =
+            Spout::sender_publish_frame;
         let _: fn(&Spout) = Spout::sender_release;
         let _: fn(&Spout) -> bool = Spout::sender_is_initialized;
         let _: fn(&Spout) -> u32 = Spout::sender_width;
@@ -90,6 +98,7 @@ mod windows {
         let _: fn(&Spout) -> bool = Spout::is_frame_sync_enabled;
         let _: fn(&Spout, &str, &[u8]) -> bool = Spout::write_memory_buffer;
         let _: fn(&Spout, &str, &mut [u8]) -> usize = Spout::read_memory_buffer;
+        let _: for<'a> fn(&'a Spout, &str) -> SpoutMemoryBuffer<'a> = Spout::memory_buffer;
         let _: fn(&Spout) -> i32 = Spout::max_senders;
         let _: fn(&Spout) -> bool = Spout::buffer_mode;
         let _: fn(&Spout, bool) = Spout::set_buffer_mode;
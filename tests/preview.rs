@@ -0,0 +1,236 @@
+//! Source discovery and the MJPEG preview server, with local sources (runs on every platform).
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rusty_syphon_spout::{
+    connect_source, discover_sources, find_source, Frame, LocalServer, PixelFormat, PreviewConfig,
+    PreviewServer, Size, SourceBackend,
+};
+
+fn pattern(width: u32, height: u32, seed: u8) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[x as u8, y as u8, seed, 255]);
+        }
+    }
+    frame
+}
+
+/// A local server publishing frames at about 100 fps until dropped.
+struct Source {
+    server: Arc<LocalServer>,
+    running: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Source {
+    fn start(name: &str, size: Size) -> Self {
+        let server = Arc::new(LocalServer::new(name));
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let (server, running) = (server.clone(), running.clone());
+            std::thread::spawn(move || {
+                let mut seed = 0u8;
+                while running.load(Ordering::SeqCst) {
+                    server.publish(pattern(size.width, size.height, seed));
+                    seed = seed.wrapping_add(1);
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            })
+        };
+        Self {
+            server,
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    fn id(&self) -> String {
+        format!("local:{}", self.server.uuid())
+    }
+}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        let _ = self.thread.take().unwrap().join();
+    }
+}
+
+/// Status line, headers (lowercased names) and the open reader for the body.
+fn get(addr: SocketAddr, path: &str) -> (String, Vec<(String, String)>, BufReader<TcpStream>) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status).unwrap();
+    let headers = read_headers(&mut reader);
+    (status.trim_end().to_string(), headers, reader)
+}
+
+fn read_headers(reader: &mut BufReader<TcpStream>) -> Vec<(String, String)> {
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            return headers;
+        }
+        let (name, value) = line.split_once(':').unwrap();
+        headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+        .unwrap_or_default()
+}
+
+fn read_body(reader: &mut BufReader<TcpStream>, headers: &[(String, String)]) -> Vec<u8> {
+    let len: usize = header(headers, "content-length").parse().unwrap();
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+    body
+}
+
+/// Width and height from a baseline JPEG's SOF0 segment.
+fn jpeg_size(jpeg: &[u8]) -> (u32, u32) {
+    assert_eq!(&jpeg[..2], &[0xFF, 0xD8], "not a JPEG");
+    let mut pos = 2;
+    loop {
+        let marker = jpeg[pos + 1];
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        if marker == 0xC0 {
+            let h = u16::from_be_bytes([jpeg[pos + 5], jpeg[pos + 6]]);
+            let w = u16::from_be_bytes([jpeg[pos + 7], jpeg[pos + 8]]);
+            return (w as u32, h as u32);
+        }
+        pos += 2 + len;
+    }
+}
+
+#[test]
+fn discovery_lists_and_connects_local_servers() {
+    let source = Source::start("preview-discovery", Size::new(8, 8));
+    let found = discover_sources()
+        .into_iter()
+        .find(|s| s.id() == source.id())
+        .expect("local server listed");
+    assert_eq!(found.backend, SourceBackend::Local);
+    assert_eq!(found.name, "preview-discovery");
+    assert!(found.display_name().starts_with("preview-discovery"));
+    let mut receiver = connect_source(&found).expect("connect");
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while receiver.receive_frame().is_none() {
+        assert!(Instant::now() < deadline, "no frame");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    drop(source);
+    assert!(find_source(&found.id()).is_none());
+}
+
+#[test]
+fn index_lists_sources_with_links() {
+    let source = Source::start("preview <index> & co", Size::new(8, 8));
+    let server = PreviewServer::bind("127.0.0.1:0", PreviewConfig::default()).unwrap();
+    let (status, headers, mut reader) = get(server.local_addr(), "/");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(header(&headers, "content-type").starts_with("text/html"));
+    let html = String::from_utf8(read_body(&mut reader, &headers)).unwrap();
+    assert!(html.contains("preview &lt;index&gt; &amp; co"), "{html}");
+    assert!(html.contains(&format!("/stream/{}", source.id())));
+    assert!(html.contains(&format!("/snapshot/{}", source.id())));
+    // Thumbnails are snapshots, so an open index doesn't hold a stream per source.
+    assert!(html.contains(&format!("<img src=\"/snapshot/{}\"", source.id())));
+    assert!(!html.contains("<img src=\"/stream/"));
+}
+
+#[test]
+fn snapshot_is_a_jpeg_within_the_size_limit() {
+    let source = Source::start("preview-snapshot", Size::new(200, 100));
+    let config = PreviewConfig {
+        max_size: Size::new(64, 64),
+        ..PreviewConfig::default()
+    };
+    let server = PreviewServer::bind("127.0.0.1:0", config).unwrap();
+    let (status, headers, mut reader) =
+        get(server.local_addr(), &format!("/snapshot/{}", source.id()));
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(header(&headers, "content-type"), "image/jpeg");
+    let jpeg = read_body(&mut reader, &headers);
+    assert_eq!(jpeg_size(&jpeg), (64, 32), "scaled to fit, aspect kept");
+}
+
+#[test]
+fn mjpeg_stream_respects_frame_rate() {
+    let source = Source::start("preview-stream", Size::new(32, 24));
+    let config = PreviewConfig {
+        max_fps: 10.0,
+        ..PreviewConfig::default()
+    };
+    let server = PreviewServer::bind("127.0.0.1:0", config).unwrap();
+    let (status, headers, mut reader) =
+        get(server.local_addr(), &format!("/stream/{}", source.id()));
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let content_type = header(&headers, "content-type");
+    let boundary = content_type
+        .strip_prefix("multipart/x-mixed-replace; boundary=")
+        .expect(content_type)
+        .to_string();
+
+    let started = Instant::now();
+    for _ in 0..4 {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(), format!("--{boundary}"));
+        let part = read_headers(&mut reader);
+        assert_eq!(header(&part, "content-type"), "image/jpeg");
+        let jpeg = read_body(&mut reader, &part);
+        assert_eq!(jpeg_size(&jpeg), (32, 24));
+        let mut crlf = [0u8; 2];
+        reader.read_exact(&mut crlf).unwrap();
+    }
+    // The source runs at ~100 fps; four parts at 10 fps take at least three intervals.
+    assert!(started.elapsed() >= Duration::from_millis(280));
+}
+
+#[test]
+fn errors_and_client_limit() {
+    let config = PreviewConfig {
+        max_clients: 1,
+        ..PreviewConfig::default()
+    };
+    let server = PreviewServer::bind("127.0.0.1:0", config).unwrap();
+    let addr = server.local_addr();
+    assert_eq!(get(addr, "/nothing").0, "HTTP/1.1 404 Not Found");
+    assert_eq!(
+        get(addr, "/snapshot/local:no-such-uuid").0,
+        "HTTP/1.1 404 Not Found"
+    );
+
+    // Hold the only slot with an open stream, then ask for another.
+    let source = Source::start("preview-limit", Size::new(8, 8));
+    // The slots of the requests above free up as their threads finish.
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let _held = loop {
+        let (status, _, reader) = get(addr, &format!("/stream/{}", source.id()));
+        if status == "HTTP/1.1 200 OK" {
+            break reader;
+        }
+        assert!(Instant::now() < deadline, "{status}");
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(get(addr, "/").0, "HTTP/1.1 503 Service Unavailable");
+}
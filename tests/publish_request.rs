@@ -0,0 +1,90 @@
+//! `PublishRequest` validation and CPU region extraction (runs on every platform).
+
+use rusty_syphon_spout::{Frame, PixelFormat, PublishError, PublishRequest, Rect, Size};
+
+fn numbered_frame(width: u32, height: u32, stride: usize) -> Frame {
+    let mut data = vec![0xEEu8; stride * height as usize];
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let i = y * stride + x * 4;
+            data[i] = x as u8;
+            data[i + 1] = y as u8;
+            data[i + 2] = 0;
+            data[i + 3] = 255;
+        }
+    }
+    Frame::from_vec_with_stride(width, height, stride, PixelFormat::Rgba8, data).unwrap()
+}
+
+#[test]
+fn full_texture_request_is_valid() {
+    let request = PublishRequest::new(Size::new(64, 32));
+    assert!(request.is_full_texture());
+    assert_eq!(request.publish_region(), Rect::new(0, 0, 64, 32));
+    assert_eq!(request.validate(), Ok(()));
+}
+
+#[test]
+fn invalid_regions_are_rejected() {
+    let size = Size::new(64, 32);
+    assert_eq!(
+        PublishRequest::new(Size::new(0, 32)).validate(),
+        Err(PublishError::EmptyTexture)
+    );
+    assert_eq!(
+        PublishRequest::new(size).region(Rect::new(4, 4, 0, 8)).validate(),
+        Err(PublishError::EmptyRegion)
+    );
+    let region = Rect::new(60, 0, 8, 8);
+    assert_eq!(
+        PublishRequest::new(size).region(region).validate(),
+        Err(PublishError::RegionOutOfBounds { region, texture: size })
+    );
+    let overflowing = Rect::new(u32::MAX, 0, 2, 2);
+    assert!(PublishRequest::new(size).region(overflowing).validate().is_err());
+}
+
+#[test]
+fn request_must_match_frame_size() {
+    let frame = Frame::new(16, 16, PixelFormat::Rgba8);
+    let request = PublishRequest::new(Size::new(32, 16));
+    assert_eq!(
+        frame.extract(&request),
+        Err(PublishError::SizeMismatch {
+            expected: Size::new(32, 16),
+            actual: Size::new(16, 16),
+        })
+    );
+}
+
+#[test]
+fn extract_sub_rectangle_honours_stride() {
+    let frame = numbered_frame(8, 6, 8 * 4 + 12);
+    let request = PublishRequest::new(frame.size()).region(Rect::new(2, 3, 4, 2));
+    let pixels = frame.extract(&request).unwrap();
+    assert_eq!(pixels.len(), 4 * 2 * 4);
+    for row in 0..2 {
+        for col in 0..4 {
+            let i = (row * 4 + col) * 4;
+            assert_eq!(pixels[i], (2 + col) as u8);
+            assert_eq!(pixels[i + 1], (3 + row) as u8);
+        }
+    }
+}
+
+#[test]
+fn extract_full_packed_frame_borrows() {
+    let frame = numbered_frame(8, 6, 8 * 4);
+    let request = PublishRequest::new(frame.size()).flipped(true);
+    let pixels = frame.extract(&request).unwrap();
+    assert!(matches!(pixels, std::borrow::Cow::Borrowed(_)));
+    assert_eq!(&pixels[..], frame.data());
+}
+
+#[test]
+fn frame_rejects_short_buffers_and_strides() {
+    assert!(Frame::from_vec(4, 4, PixelFormat::Bgra8, vec![0; 63]).is_none());
+    assert!(Frame::from_vec_with_stride(4, 4, 12, PixelFormat::Rgba8, vec![0; 64]).is_none());
+    // The last row needs no trailing padding.
+    assert!(Frame::from_vec_with_stride(4, 2, 20, PixelFormat::Rgba8, vec![0; 36]).is_some());
+}
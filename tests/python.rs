@@ -0,0 +1,233 @@
+//! Python module through an embedded interpreter, with local servers (`--features python`).
+//! Tests that move frames through NumPy skip when numpy is not installed.
+
+#![cfg(feature = "python")]
+
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rusty_syphon_spout::{python_module, Frame, LocalClient, LocalServer, PixelFormat};
+
+fn python<R>(f: impl for<'py> FnOnce(Python<'py>) -> R) -> R {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        pyo3::append_to_inittab!(python_module);
+        Python::initialize();
+    });
+    Python::attach(f)
+}
+
+/// Run `code` with `rss` imported; returns its globals.
+fn run<'py>(py: Python<'py>, code: &CStr) -> PyResult<Bound<'py, PyDict>> {
+    let globals = PyDict::new(py);
+    py.run(c"import rusty_syphon_spout as rss", Some(&globals), None)?;
+    py.run(code, Some(&globals), None)?;
+    Ok(globals)
+}
+
+fn get<'py>(globals: &Bound<'py, PyDict>, name: &str) -> Bound<'py, PyAny> {
+    globals.get_item(name).unwrap().expect(name)
+}
+
+fn has_numpy() -> bool {
+    let found = python(|py| py.import("numpy").is_ok());
+    if !found {
+        eprintln!("skipping: numpy not installed");
+    }
+    found
+}
+
+fn pattern(width: u32, height: u32) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[x as u8, y as u8, 7, 255]);
+        }
+    }
+    frame
+}
+
+#[test]
+fn discover_lists_local_servers() {
+    let server = LocalServer::new("py-discover");
+    server.publish(Frame::new(5, 3, PixelFormat::Bgra8));
+    python(|py| {
+        let globals = run(
+            py,
+            c"found = [s for s in rss.discover() if s.name == 'py-discover']
+source = found[0]
+fields = (source.backend, source.id, source.width, source.height, source.format)
+text = repr(source)",
+        )
+        .unwrap();
+        let fields: (String, String, u32, u32, String) = get(&globals, "fields").extract().unwrap();
+        assert_eq!(
+            fields,
+            (
+                "local".to_string(),
+                format!("local:{}", server.uuid()),
+                5,
+                3,
+                "bgra".to_string()
+            )
+        );
+        let text: String = get(&globals, "text").extract().unwrap();
+        assert!(text.starts_with("Source(id="), "{text}");
+    });
+}
+
+#[test]
+fn errors_map_to_python_exceptions() {
+    let _server = LocalServer::new("py-errors");
+    python(|py| {
+        let globals = run(
+            py,
+            c"def error(f):
+    try:
+        f()
+    except Exception as e:
+        return type(e).__name__
+missing = error(lambda: rss.Receiver('py-no-such-source'))
+bad_backend = error(lambda: rss.Sender('py-x', backend='carrier-pigeon'))
+bad_timeout = error(lambda: rss.Receiver('py-errors').wait(-1.0))",
+        )
+        .unwrap();
+        assert_eq!(
+            get(&globals, "missing").extract::<String>().unwrap(),
+            "LookupError"
+        );
+        assert_eq!(
+            get(&globals, "bad_backend").extract::<String>().unwrap(),
+            "ValueError"
+        );
+        assert_eq!(
+            get(&globals, "bad_timeout").extract::<String>().unwrap(),
+            "ValueError"
+        );
+    });
+}
+
+#[test]
+fn wait_releases_the_gil() {
+    let _server = LocalServer::new("py-gil");
+    let waiting = Arc::new(AtomicBool::new(false));
+    let got_gil_while_waiting = Arc::new(AtomicBool::new(false));
+    let other = {
+        let (waiting, got) = (waiting.clone(), got_gil_while_waiting.clone());
+        std::thread::spawn(move || {
+            while !waiting.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+            // Blocks until the waiting thread releases the GIL.
+            python(|_| got.store(waiting.load(Ordering::SeqCst), Ordering::SeqCst));
+        })
+    };
+    python(|py| {
+        let globals = run(py, c"receiver = rss.Receiver('py-gil')").unwrap();
+        let receiver = get(&globals, "receiver");
+        assert!(receiver
+            .getattr("connected")
+            .unwrap()
+            .extract::<bool>()
+            .unwrap());
+        assert!(receiver.call_method0("receive").unwrap().is_none());
+        waiting.store(true, Ordering::SeqCst);
+        let frame = receiver.call_method1("wait", (0.5,)).unwrap();
+        waiting.store(false, Ordering::SeqCst);
+        assert!(frame.is_none(), "no frame was published");
+    });
+    other.join().unwrap();
+    assert!(got_gil_while_waiting.load(Ordering::SeqCst));
+}
+
+#[test]
+fn receiver_returns_numpy_arrays() {
+    if !has_numpy() {
+        return;
+    }
+    let server = LocalServer::new("py-receive");
+    let frame = pattern(6, 4);
+    server.publish(frame.clone());
+    python(|py| {
+        let globals = run(
+            py,
+            c"receiver = rss.Receiver('py-receive')
+rgba = receiver.wait(2.0)
+shape, dtype, pixel = rgba.shape, str(rgba.dtype), tuple(int(v) for v in rgba[2, 3])
+format = receiver.format",
+        )
+        .unwrap();
+        assert_eq!(
+            get(&globals, "shape")
+                .extract::<(usize, usize, usize)>()
+                .unwrap(),
+            (4, 6, 4)
+        );
+        assert_eq!(get(&globals, "dtype").extract::<String>().unwrap(), "uint8");
+        assert_eq!(
+            get(&globals, "pixel")
+                .extract::<(u8, u8, u8, u8)>()
+                .unwrap(),
+            (3, 2, 7, 255)
+        );
+        assert_eq!(get(&globals, "format").extract::<String>().unwrap(), "rgba");
+
+        server.publish(frame.clone());
+        let bgra = get(&globals, "receiver")
+            .call_method1("wait", (2.0, "bgra"))
+            .unwrap();
+        let pixel: Vec<u8> = bgra
+            .get_item((2, 3))
+            .unwrap()
+            .call_method0("tolist")
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(pixel, [7, 2, 3, 255]);
+    });
+}
+
+#[test]
+fn sender_publishes_numpy_arrays() {
+    if !has_numpy() {
+        return;
+    }
+    python(|py| {
+        let globals = run(
+            py,
+            c"import numpy as np
+sender = rss.Sender('py-send')
+image = np.zeros((3, 5, 4), dtype=np.uint8)
+image[1, 2] = (10, 20, 30, 255)
+def send(array, format='rgba'):
+    sender.send(array, format)",
+        )
+        .unwrap();
+        let mut client = LocalClient::connect("py-send").unwrap();
+        let send = get(&globals, "send");
+        send.call1((get(&globals, "image"),)).unwrap();
+        let frame = client.new_frame().unwrap();
+        assert_eq!((frame.width(), frame.height()), (5, 3));
+        assert_eq!(&frame.row(1)[8..12], &[10, 20, 30, 255]);
+
+        // Non-contiguous views and BGRA input are accepted; wrong shapes are not.
+        let view = py.eval(c"image[:, ::2]", Some(&globals), None).unwrap();
+        send.call1((view, "bgra")).unwrap();
+        let frame = client.new_frame().unwrap();
+        assert_eq!(
+            (frame.width(), frame.height(), frame.format()),
+            (3, 3, PixelFormat::Bgra8)
+        );
+        assert_eq!(&frame.row(1)[4..8], &[10, 20, 30, 255]);
+        let rgb = py
+            .eval(c"np.zeros((2, 2, 3), dtype=np.uint8)", Some(&globals), None)
+            .unwrap();
+        let err = send.call1((rgb,)).unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+    });
+}
@@ -0,0 +1,179 @@
+//! Reconnecting receivers following restarted local servers (runs on every platform).
+
+use std::time::{Duration, Instant};
+
+use rusty_syphon_spout::{
+    ConnectionState, Frame, FrameReceiver, LocalServer, PixelFormat, ReconnectConfig,
+    ReconnectEvent, ReconnectingReceiver, SelectorRule, SourceSelector,
+};
+
+fn config() -> ReconnectConfig {
+    ReconnectConfig {
+        initial_delay: Duration::from_millis(5),
+        max_delay: Duration::from_millis(20),
+        ..ReconnectConfig::default()
+    }
+}
+
+fn frame(seed: u8) -> Frame {
+    let mut frame = Frame::new(4, 2, PixelFormat::Rgba8);
+    frame.data_mut().fill(seed);
+    frame
+}
+
+/// Call `receive_frame` until it returns a frame or `timeout` passes.
+fn wait_frame(receiver: &mut ReconnectingReceiver, timeout: Duration) -> Option<Frame> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(frame) = receiver.receive_frame() {
+            return Some(frame);
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn backoff_grows_to_the_cap() {
+    let config = ReconnectConfig {
+        initial_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(500),
+        multiplier: 2.0,
+        ..ReconnectConfig::default()
+    };
+    let delays: Vec<_> = (1..=5).map(|n| config.delay(n).as_millis()).collect();
+    assert_eq!(delays, [100, 200, 400, 500, 500]);
+    let flat = ReconnectConfig {
+        multiplier: 0.5,
+        ..config
+    };
+    assert_eq!(flat.delay(3), Duration::from_millis(100));
+    // Long outages stay at the cap instead of overflowing.
+    let defaults = ReconnectConfig::default();
+    for attempt in [69, 1_000, u32::MAX] {
+        assert_eq!(defaults.delay(attempt), defaults.max_delay);
+    }
+}
+
+#[test]
+fn reconnects_after_the_sender_restarts() {
+    // An app name no local server has: never found.
+    let selector = SourceSelector::name_and_app("reconnect-restart", "");
+    let mut receiver = ReconnectingReceiver::new(selector, config());
+    assert_eq!(receiver.state(), ConnectionState::Connecting);
+
+    // No source yet: attempts back off.
+    assert!(receiver.receive_frame().is_none());
+    std::thread::sleep(Duration::from_millis(30));
+    assert!(receiver.receive_frame().is_none());
+    let events = receiver.drain_events();
+    assert_eq!(
+        events,
+        [
+            ReconnectEvent::RetryScheduled {
+                attempt: 1,
+                delay: Duration::from_millis(5)
+            },
+            ReconnectEvent::RetryScheduled {
+                attempt: 2,
+                delay: Duration::from_millis(10)
+            },
+        ]
+    );
+    assert!(!receiver.is_connected());
+
+    // The sender comes up under this process's app name.
+    let server = LocalServer::new("reconnect-restart");
+    let app_name = rusty_syphon_spout::local_sources()
+        .into_iter()
+        .find(|s| s.uuid == server.uuid())
+        .unwrap()
+        .app_name;
+    let mut receiver = ReconnectingReceiver::new(
+        SourceSelector::name_and_app("reconnect-restart", &app_name),
+        ReconnectConfig {
+            keep_last_frame: true,
+            ..config()
+        },
+    );
+    server.publish(frame(1));
+    assert_eq!(
+        wait_frame(&mut receiver, Duration::from_secs(2)),
+        Some(frame(1))
+    );
+    assert_eq!(receiver.state(), ConnectionState::Connected);
+    assert_eq!(receiver.source().unwrap().uuid, server.uuid());
+    assert!(matches!(
+        receiver.try_recv_event(),
+        Some(ReconnectEvent::Connected {
+            rule: SelectorRule::NameAndApp,
+            ..
+        })
+    ));
+    assert_eq!(receiver.selector().uuid.as_deref(), Some(server.uuid()));
+
+    // The sender goes away: the last frame stays.
+    let old_uuid = server.uuid().to_string();
+    drop(server);
+    assert!(receiver.receive_frame().is_none());
+    assert_eq!(receiver.state(), ConnectionState::Reconnecting);
+    assert!(!receiver.is_connected());
+    assert!(receiver.source().is_none());
+    assert_eq!(receiver.last_frame(), Some(&frame(1)));
+    let events = receiver.drain_events();
+    assert!(
+        matches!(&events[0], ReconnectEvent::Lost { source } if source.uuid == old_uuid),
+        "{events:?}"
+    );
+    assert!(matches!(
+        events[1],
+        ReconnectEvent::RetryScheduled { attempt: 1, .. }
+    ));
+
+    // It restarts under a new UUID and is found by name and app again.
+    let restarted = LocalServer::new("reconnect-restart");
+    restarted.publish(frame(2));
+    assert_eq!(
+        wait_frame(&mut receiver, Duration::from_secs(2)),
+        Some(frame(2))
+    );
+    assert_eq!(receiver.source().unwrap().uuid, restarted.uuid());
+    assert_eq!(receiver.last_frame(), Some(&frame(2)));
+    assert!(receiver.drain_events().iter().any(|event| matches!(
+        event,
+        ReconnectEvent::Connected {
+            rule: SelectorRule::NameAndApp,
+            ..
+        }
+    )));
+}
+
+#[test]
+fn uuid_match_is_remembered_across_restarts_of_other_sources() {
+    // Two servers with the same name: the remembered UUID keeps the receiver on the first one.
+    let first = LocalServer::new("reconnect-twin");
+    let mut receiver = ReconnectingReceiver::new(
+        SourceSelector::pattern(rusty_syphon_spout::NamePattern::glob("reconnect-twin")),
+        config(),
+    );
+    first.publish(frame(3));
+    assert_eq!(
+        wait_frame(&mut receiver, Duration::from_secs(2)),
+        Some(frame(3))
+    );
+    let second = LocalServer::new("reconnect-twin");
+    second.publish(frame(4));
+    first.publish(frame(5));
+    assert_eq!(
+        wait_frame(&mut receiver, Duration::from_secs(2)),
+        Some(frame(5))
+    );
+    assert_eq!(receiver.source().unwrap().uuid, first.uuid());
+    assert!(receiver.last_frame().is_none(), "not kept by default");
+    assert_eq!(
+        receiver.selector().resolve().unwrap().rule,
+        SelectorRule::Uuid
+    );
+}
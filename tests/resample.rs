@@ -0,0 +1,108 @@
+//! Frame resampling filters and scale modes (runs on every platform).
+
+use rusty_syphon_spout::{
+    resample, Frame, PixelFormat, Rect, ResampleFilter, Resampler, ScaleMode, Size,
+};
+
+const FILTERS: [ResampleFilter; 4] = [
+    ResampleFilter::Nearest,
+    ResampleFilter::Bilinear,
+    ResampleFilter::Bicubic,
+    ResampleFilter::Lanczos3,
+];
+
+fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Frame {
+    let data = rgba.repeat((width * height) as usize);
+    Frame::from_vec(width, height, PixelFormat::Rgba8, data).unwrap()
+}
+
+fn gradient(width: u32, height: u32) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[(x * 255 / (width as usize - 1)) as u8, (y * 7) as u8, 128, 255]);
+        }
+    }
+    frame
+}
+
+fn pixel(frame: &Frame, x: u32, y: u32) -> [u8; 4] {
+    let i = x as usize * 4;
+    frame.row(y)[i..i + 4].try_into().unwrap()
+}
+
+#[test]
+fn same_size_is_identity() {
+    let src = gradient(17, 9);
+    for filter in FILTERS {
+        assert_eq!(resample(&src, src.size(), filter), src, "{:?}", filter);
+    }
+}
+
+#[test]
+fn solid_color_stays_solid() {
+    let src = solid(13, 7, [10, 200, 30, 255]);
+    for filter in FILTERS {
+        for size in [Size::new(40, 21), Size::new(5, 3), Size::new(1, 1)] {
+            let out = resample(&src, size, filter);
+            assert_eq!(out.size(), size);
+            assert!(
+                out.data().chunks_exact(4).all(|p| p == [10, 200, 30, 255]),
+                "{:?} to {:?}",
+                filter,
+                size
+            );
+        }
+    }
+}
+
+#[test]
+fn bilinear_upscale_interpolates_between_centers() {
+    let src = Frame::from_vec(2, 1, PixelFormat::Rgba8, vec![0, 0, 0, 255, 200, 0, 0, 255]).unwrap();
+    let out = resample(&src, Size::new(4, 1), ResampleFilter::Bilinear);
+    let reds: Vec<u8> = (0..4).map(|x| pixel(&out, x, 0)[0]).collect();
+    assert_eq!(reds, [0, 50, 150, 200]);
+}
+
+#[test]
+fn transparent_pixels_do_not_bleed_color() {
+    let src = Frame::from_vec(2, 1, PixelFormat::Rgba8, vec![255, 0, 0, 255, 0, 255, 0, 0]).unwrap();
+    let out = resample(&src, Size::new(1, 1), ResampleFilter::Bilinear);
+    assert_eq!(pixel(&out, 0, 0), [255, 0, 0, 128]);
+}
+
+#[test]
+fn fit_letterboxes_and_fill_crops() {
+    let src = solid(200, 100, [255, 255, 255, 255]);
+    let fit = Resampler::new(ResampleFilter::Bilinear)
+        .mode(ScaleMode::Fit)
+        .background([0, 0, 255, 255]);
+    assert_eq!(
+        fit.placement(src.size(), Size::new(100, 100)),
+        (Rect::new(0, 0, 200, 100), Rect::new(0, 25, 100, 50))
+    );
+    let out = fit.resample(&src, Size::new(100, 100));
+    assert_eq!(pixel(&out, 50, 10), [0, 0, 255, 255]);
+    assert_eq!(pixel(&out, 50, 50), [255, 255, 255, 255]);
+    assert_eq!(pixel(&out, 50, 90), [0, 0, 255, 255]);
+
+    let fill = Resampler::new(ResampleFilter::Bilinear).mode(ScaleMode::Fill);
+    assert_eq!(
+        fill.placement(src.size(), Size::new(100, 100)),
+        (Rect::new(50, 0, 100, 100), Rect::new(0, 0, 100, 100))
+    );
+
+    // Letterbox color follows the frame's channel order.
+    let bgra = fit.resample(&src.to_format(PixelFormat::Bgra8), Size::new(100, 100));
+    assert_eq!(pixel(&bgra, 50, 10), [255, 0, 0, 255]);
+}
+
+#[test]
+fn thread_count_does_not_change_output() {
+    let src = gradient(64, 48);
+    for filter in FILTERS {
+        let single = Resampler::new(filter).threads(1).resample(&src, Size::new(37, 91));
+        let multi = Resampler::new(filter).threads(8).resample(&src, Size::new(37, 91));
+        assert_eq!(single, multi, "{:?}", filter);
+    }
+}
@@ -3,6 +3,8 @@
 //! These are intentionally `#[ignore]` because they require a working local
 //! graphics/runtime environment and are not stable on generic CI runners.
 
+#![cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+
 const W: usize = 64;
 const H: usize = 64;
 const BPP: usize = 4;
@@ -28,7 +30,7 @@ mod macos {
     use rusty_syphon_spout::{
         cgl_create_headless_context, cgl_destroy_context, cgl_make_current,
         gl_create_texture_rectangle_rgba8, gl_delete_texture, gl_read_texture_rectangle_rgba8,
-        OpenGLClient, OpenGLServer, GL_TEXTURE_RECTANGLE,
+        OpenGLClient, OpenGLServer, PublishRequest, Size, GL_TEXTURE_RECTANGLE,
     };
     use std::time::Duration;
 
@@ -44,17 +46,10 @@ mod macos {
 
         let server = OpenGLServer::new(Some("rusty-syphon-runtime-roundtrip"), ctx, None)
             .expect("failed to create OpenGL server");
-        server.publish_frame(
-            tex_id,
-            GL_TEXTURE_RECTANGLE,
-            0.0,
-            0.0,
-            W as f64,
-            H as f64,
-            W as f64,
-            H as f64,
-            false,
-        );
+        let request = PublishRequest::new(Size::new(W as u32, H as u32));
+        server
+            .publish(tex_id, GL_TEXTURE_RECTANGLE, &request)
+            .expect("failed to publish frame");
 
         let desc = server.server_description().expect("missing server description");
         let client = OpenGLClient::new(&desc, ctx, None, None).expect("failed to create client");
@@ -0,0 +1,234 @@
+//! RFC 4175 RTP sender and receiver over loopback UDP (runs on every platform).
+
+use std::net::{Ipv4Addr, UdpSocket};
+use std::time::Duration;
+
+use rusty_syphon_spout::{
+    Frame, FrameCompare, LocalClient, LocalServer, PixelFormat, RtpInput, RtpReceiver, RtpSampling,
+    RtpSender, Size,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn pattern(width: u32, height: u32, seed: u8) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[(x * 5) as u8 ^ seed, (y * 9) as u8, seed, 255]);
+        }
+    }
+    frame
+}
+
+fn pair(size: Size, sampling: RtpSampling) -> (RtpSender, RtpReceiver) {
+    let receiver = RtpReceiver::bind((Ipv4Addr::LOCALHOST, 0), size, sampling).unwrap();
+    let sender = RtpSender::connect(receiver.local_addr().unwrap())
+        .unwrap()
+        .sampling(sampling)
+        .mtu(300);
+    (sender, receiver)
+}
+
+#[test]
+fn packets_follow_rfc_4175() {
+    let frame = pattern(50, 6, 1);
+    let (sender, _receiver) = pair(frame.size(), RtpSampling::Rgba);
+    let mut sender = sender.payload_type(97).ssrc(0xDEADBEEF);
+    let packets = sender.packetize(&frame, 1234).unwrap();
+    assert!(packets.len() > 1);
+    let mut payload_bytes = 0;
+    for (i, packet) in packets.iter().enumerate() {
+        assert!(packet.len() <= 300);
+        assert_eq!(packet[0] >> 6, 2, "RTP version");
+        assert_eq!(packet[1] & 0x7F, 97);
+        let last = i + 1 == packets.len();
+        assert_eq!(
+            packet[1] & 0x80 != 0,
+            last,
+            "marker only on the last packet"
+        );
+        assert_eq!(&packet[4..8], &1234u32.to_be_bytes());
+        assert_eq!(&packet[8..12], &0xDEADBEEFu32.to_be_bytes());
+        // Sum the segment lengths from the line headers.
+        let mut pos = 14;
+        loop {
+            payload_bytes += u16::from_be_bytes([packet[pos], packet[pos + 1]]) as usize;
+            let continuation = packet[pos + 4] & 0x80 != 0;
+            pos += 6;
+            if !continuation {
+                break;
+            }
+        }
+    }
+    assert_eq!(payload_bytes, 50 * 6 * 4);
+    let sequences: Vec<u16> = packets
+        .iter()
+        .map(|p| u16::from_be_bytes([p[2], p[3]]))
+        .collect();
+    for pair in sequences.windows(2) {
+        assert_eq!(pair[1], pair[0].wrapping_add(1));
+    }
+}
+
+#[test]
+fn loopback_roundtrip() {
+    let frame = pattern(64, 40, 2);
+    let (mut sender, mut receiver) = pair(frame.size(), RtpSampling::Rgba);
+    sender.send(&frame, 9000).unwrap();
+    let received = receiver.recv_timeout(TIMEOUT).expect("frame");
+    FrameCompare::new().assert(&frame, &received);
+    assert_eq!(receiver.last_timestamp(), Some(9000));
+    assert_eq!(receiver.stats().frames_received, 1);
+}
+
+#[test]
+fn reordered_and_duplicated_packets_reassemble() {
+    let a = pattern(32, 20, 3);
+    let b = pattern(32, 20, 4);
+    let (mut sender, mut receiver) = pair(a.size(), RtpSampling::Bgra);
+    let mut packets_a = sender.packetize(&a, 100).unwrap();
+    let mut packets_b = sender.packetize(&b, 3100).unwrap();
+    packets_a.reverse();
+    packets_b.swap(0, 2);
+    // Interleave the two frames and repeat a packet, as a congested network might.
+    let mut shuffled = Vec::new();
+    for i in 0..packets_a.len().max(packets_b.len()) {
+        shuffled.extend(packets_b.get(i).cloned());
+        shuffled.extend(packets_a.get(i).cloned());
+    }
+    shuffled.insert(3, shuffled[1].clone());
+
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let dest = receiver.local_addr().unwrap();
+    let mut frames = Vec::new();
+    for packet in &shuffled {
+        socket.send_to(packet, dest).unwrap();
+        // Read as we go so each completed frame is seen, not just the newest.
+        std::thread::sleep(Duration::from_millis(1));
+        frames.extend(receiver.try_recv());
+    }
+    while let Some(frame) = receiver.recv_timeout(Duration::from_millis(100)) {
+        frames.push(frame);
+    }
+    assert!(!frames.is_empty());
+    let last = frames.last().unwrap();
+    FrameCompare::new().assert(&b.to_format(PixelFormat::Bgra8), last);
+    assert_eq!(receiver.last_timestamp(), Some(3100));
+    if frames.len() == 2 {
+        FrameCompare::new().assert(&a, &frames[0]);
+    }
+    let stats = receiver.stats();
+    assert_eq!(stats.frames_received + stats.frames_dropped, 2);
+}
+
+#[test]
+fn lost_packet_drops_only_that_frame() {
+    let (mut sender, mut receiver) = pair(Size::new(16, 16), RtpSampling::Rgba);
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let dest = receiver.local_addr().unwrap();
+    let mut lossy = sender.packetize(&pattern(16, 16, 5), 0).unwrap();
+    lossy.remove(1);
+    for packet in lossy {
+        socket.send_to(&packet, dest).unwrap();
+    }
+    let next = pattern(16, 16, 6);
+    sender.send(&next, 3000).unwrap();
+    let received = receiver.recv_timeout(TIMEOUT).expect("frame");
+    FrameCompare::new().assert(&next, &received);
+    let stats = receiver.stats();
+    assert_eq!(stats.frames_received, 1);
+    assert_eq!(stats.frames_dropped, 1);
+    // A late packet of the dropped frame is ignored.
+    let late = sender.packetize(&pattern(16, 16, 5), 0).unwrap();
+    socket.send_to(&late[1], dest).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(50)).is_none());
+}
+
+#[test]
+fn restarted_sender_is_followed() {
+    let (mut old, mut receiver) = pair(Size::new(16, 16), RtpSampling::Rgba);
+    old.send(&pattern(16, 16, 1), 1_000_000).unwrap();
+    assert!(receiver.recv_timeout(TIMEOUT).is_some());
+    drop(old);
+
+    // The new sender has its own SSRC and its timestamps start over lower.
+    let mut new = RtpSender::connect(receiver.local_addr().unwrap())
+        .unwrap()
+        .mtu(300);
+    let frame = pattern(16, 16, 2);
+    new.send(&frame, 5).unwrap();
+    let received = receiver
+        .recv_timeout(TIMEOUT)
+        .expect("frame from the new sender");
+    FrameCompare::new().assert(&frame, &received);
+    assert_eq!(receiver.last_timestamp(), Some(5));
+}
+
+#[test]
+fn repeated_data_does_not_complete_a_frame() {
+    let (mut sender, mut receiver) = pair(Size::new(16, 16), RtpSampling::Rgba);
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let dest = receiver.local_addr().unwrap();
+    let mut packets = sender.packetize(&pattern(16, 16, 7), 0).unwrap();
+    packets.pop();
+    // Resend the first packet under new sequence numbers: more bytes than the lost one carried,
+    // but the same lines.
+    for low in [0x7000u16, 0x7001] {
+        let mut repeat = packets[0].clone();
+        repeat[2..4].copy_from_slice(&low.to_be_bytes());
+        packets.push(repeat);
+    }
+    for packet in &packets {
+        socket.send_to(packet, dest).unwrap();
+    }
+    assert!(receiver.recv_timeout(Duration::from_millis(50)).is_none());
+}
+
+#[test]
+fn ycbcr_422_roundtrip() {
+    let frame = pattern(48, 16, 7);
+    let (mut sender, mut receiver) = pair(frame.size(), RtpSampling::YCbCr422);
+    sender.send(&frame, 0).unwrap();
+    let received = receiver.recv_timeout(TIMEOUT).expect("frame");
+    // Chroma is shared between pixel pairs, so allow for subsampling error.
+    FrameCompare::new()
+        .tolerance(24)
+        .min_psnr(30.0)
+        .assert(&frame, &received);
+
+    assert!(sender.send(&pattern(47, 16, 7), 0).is_err(), "odd width");
+    assert!(RtpReceiver::bind(
+        (Ipv4Addr::LOCALHOST, 0),
+        Size::new(47, 2),
+        RtpSampling::YCbCr422
+    )
+    .is_err());
+}
+
+#[test]
+fn input_publishes_as_local_server_and_sender_forwards() {
+    let size = Size::new(24, 12);
+    let receiver = RtpReceiver::bind((Ipv4Addr::LOCALHOST, 0), size, RtpSampling::Rgba).unwrap();
+    let input = RtpInput::start(receiver, "rtp-test-input").unwrap();
+    let mut client = LocalClient::connect("rtp-test-input").expect("input server");
+
+    // Forward frames from a local source into the RTP stream.
+    let source = LocalServer::new("rtp-test-source");
+    let mut source_client = LocalClient::connect("rtp-test-source").unwrap();
+    let mut sender = RtpSender::connect(input.local_addr()).unwrap();
+    let frame = pattern(24, 12, 8);
+    source.publish(frame.clone());
+    assert!(sender.forward(&mut source_client).unwrap());
+    assert!(!sender.forward(&mut source_client).unwrap());
+
+    let received = client.wait_new_frame(TIMEOUT).expect("published frame");
+    FrameCompare::new().assert(&frame, &received);
+    assert_eq!(input.server_info().name, "rtp-test-input");
+    let sdp = sender.sdp(size).unwrap();
+    assert!(sdp.contains("a=rtpmap:96 raw/90000"), "{sdp}");
+    assert!(
+        sdp.contains("sampling=RGBA; width=24; height=12; depth=8"),
+        "{sdp}"
+    );
+    assert!(sdp.contains(&format!("m=video {} RTP/AVP 96", input.local_addr().port())));
+}
@@ -0,0 +1,113 @@
+//! Plain-data source descriptions with local servers (runs on every platform; run with
+//! `--features serde` for the JSON round trip).
+
+use std::time::SystemTime;
+
+use rusty_syphon_spout::{
+    discover_sources, snapshot_sources, Frame, LocalServer, PixelFormat, ServerDirectory, Size,
+    SourceBackend, SourceDescription,
+};
+
+fn describe(name: &str) -> SourceDescription {
+    let source = discover_sources()
+        .into_iter()
+        .find(|s| s.name == name)
+        .expect("local server is discoverable");
+    SourceDescription::from(&source)
+}
+
+#[test]
+fn descriptions_find_and_connect_live_sources() {
+    let server = LocalServer::new("desc-live");
+    server.publish(Frame::new(8, 4, PixelFormat::Bgra8));
+    let before = SystemTime::now();
+    let desc = describe("desc-live");
+    assert_eq!(desc.backend, SourceBackend::Local);
+    assert_eq!(desc.uuid, server.uuid());
+    assert_eq!(desc.id(), format!("local:{}", server.uuid()));
+    assert_eq!(desc.size, Some(Size::new(8, 4)));
+    assert_eq!(desc.format, Some(PixelFormat::Bgra8));
+    assert!(desc.discovered_at >= before && desc.discovered_at <= SystemTime::now());
+
+    // The live info follows the source; the description keeps what was seen.
+    server.publish(Frame::new(16, 9, PixelFormat::Bgra8));
+    let live = desc.find().unwrap();
+    assert_eq!(live.size, Some(Size::new(16, 9)));
+    assert_eq!(desc.to_source_info().size, Some(Size::new(8, 4)));
+    let mut receiver = desc.connect().unwrap();
+    assert!(receiver.is_connected());
+    assert_eq!(receiver.receive_frame().unwrap().size(), Size::new(16, 9));
+
+    drop(receiver);
+    drop(server);
+    assert!(desc.find().is_none());
+    assert!(desc.connect().is_none());
+}
+
+#[test]
+fn snapshots_share_one_timestamp() {
+    let _a = LocalServer::new("desc-snapshot-a");
+    let _b = LocalServer::new("desc-snapshot-b");
+    let snapshot: Vec<_> = snapshot_sources()
+        .into_iter()
+        .filter(|d| d.name.starts_with("desc-snapshot-"))
+        .collect();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(snapshot[0].discovered_at, snapshot[1].discovered_at);
+}
+
+#[test]
+fn syphon_directory_descriptions() {
+    // Without Syphon there is no directory; where there is one, every server with a UUID maps.
+    if let Some(directory) = ServerDirectory::shared() {
+        for server in directory.servers() {
+            if let Some(desc) = SourceDescription::from_server_description(&server) {
+                assert_eq!(desc.backend, SourceBackend::Syphon);
+                assert_eq!(Some(desc.uuid), server.uuid());
+            }
+        }
+    }
+}
+
+#[test]
+fn dxgi_formats_map_to_pixel_formats() {
+    assert_eq!(PixelFormat::from_dxgi_format(87), Some(PixelFormat::Bgra8));
+    assert_eq!(PixelFormat::from_dxgi_format(91), Some(PixelFormat::Bgra8));
+    assert_eq!(PixelFormat::from_dxgi_format(28), Some(PixelFormat::Rgba8));
+    assert_eq!(PixelFormat::from_dxgi_format(29), Some(PixelFormat::Rgba8));
+    assert_eq!(PixelFormat::from_dxgi_format(10), None);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn descriptions_serialize_as_plain_data() {
+    let server = LocalServer::new("desc-serde");
+    server.publish(Frame::new(3, 2, PixelFormat::Rgba8));
+    let mut desc = describe("desc-serde");
+    desc.discovered_at = SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1_500);
+
+    let json = serde_json::to_value(&desc).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "backend": "local",
+            "uuid": server.uuid(),
+            "name": "desc-serde",
+            "app_name": desc.app_name,
+            "size": { "width": 3, "height": 2 },
+            "format": "rgba8",
+            "discovered_at": { "secs_since_epoch": 1, "nanos_since_epoch": 500_000_000 },
+        })
+    );
+    let back: SourceDescription = serde_json::from_value(json).unwrap();
+    assert_eq!(back, desc);
+    assert!(back.find().is_some());
+
+    // Sizes and formats are optional.
+    let syphon: SourceDescription = serde_json::from_str(
+        r#"{"backend":"syphon","uuid":"u","name":"n","app_name":"a",
+            "discovered_at":{"secs_since_epoch":0,"nanos_since_epoch":0}}"#,
+    )
+    .unwrap();
+    assert_eq!((syphon.size, syphon.format), (None, None));
+}
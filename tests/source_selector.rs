@@ -0,0 +1,152 @@
+//! Source selectors over local servers and synthetic source lists (runs on every platform; run
+//! with `--features serde` for the JSON round trip).
+
+use rusty_syphon_spout::{
+    local_sources, LocalServer, NamePattern, SelectorRule, SourceBackend, SourceDescription,
+    SourceInfo, SourceSelector,
+};
+
+fn source(backend: SourceBackend, uuid: &str, name: &str, app_name: &str) -> SourceInfo {
+    SourceInfo {
+        backend,
+        uuid: uuid.to_string(),
+        name: name.to_string(),
+        app_name: app_name.to_string(),
+        size: None,
+        format: None,
+    }
+}
+
+fn sources() -> Vec<SourceInfo> {
+    vec![
+        source(SourceBackend::Local, "l-1", "Preview", "Editor"),
+        source(SourceBackend::Syphon, "s-1", "Main Output", "Resolume"),
+        source(SourceBackend::Syphon, "s-2", "Camera 2", "OBS"),
+        source(SourceBackend::Spout, "Camera 1", "Camera 1", ""),
+    ]
+}
+
+#[test]
+fn rules_apply_in_priority_order() {
+    let list = sources();
+    // The saved UUID is stale after a restart: falls back to name and app.
+    let mut selector = SourceSelector::name_and_app("Main Output", "Resolume");
+    selector.uuid = Some("s-old".into());
+    let found = selector.resolve_in(&list).unwrap();
+    assert_eq!(
+        (found.source.uuid.as_str(), found.rule),
+        ("s-1", SelectorRule::NameAndApp)
+    );
+
+    // A live UUID wins over a name that matches another source.
+    selector.uuid = Some("s-2".into());
+    let found = selector.resolve_in(&list).unwrap();
+    assert_eq!(
+        (found.source.uuid.as_str(), found.rule),
+        ("s-2", SelectorRule::Uuid)
+    );
+
+    // Unless the priority says otherwise; rules left out are never tried.
+    let by_name = selector
+        .clone()
+        .with_priority(&[SelectorRule::NameAndApp, SelectorRule::Uuid]);
+    assert_eq!(by_name.resolve_in(&list).unwrap().source.uuid, "s-1");
+    let pattern_only = selector
+        .with_pattern(NamePattern::glob("Camera *"))
+        .with_priority(&[SelectorRule::Pattern]);
+    let found = pattern_only.resolve_in(&list).unwrap();
+    assert_eq!(
+        (found.source.uuid.as_str(), found.rule),
+        ("s-2", SelectorRule::Pattern)
+    );
+
+    // The backend filter applies to every rule.
+    let mut spout_cameras = SourceSelector::pattern(NamePattern::glob("Camera ?"));
+    spout_cameras.backend = Some(SourceBackend::Spout);
+    assert_eq!(
+        spout_cameras.resolve_in(&list).unwrap().source.uuid,
+        "Camera 1"
+    );
+    spout_cameras.backend = Some(SourceBackend::Local);
+    assert!(spout_cameras.resolve_in(&list).is_none());
+
+    // Name without app is not a NameAndApp rule.
+    let name_only = SourceSelector {
+        name: Some("Preview".into()),
+        ..SourceSelector::default()
+    };
+    assert!(name_only.resolve_in(&list).is_none());
+    assert!(SourceSelector::default().resolve_in(&list).is_none());
+}
+
+#[test]
+fn glob_and_regex_patterns() {
+    let glob = NamePattern::glob("Cam*ra ?");
+    assert!(glob.matches("Camera 1"));
+    assert!(glob.matches("Camra 2"));
+    assert!(glob.matches("Cam-era-ra 3"));
+    assert!(!glob.matches("Camera 12"));
+    assert!(!glob.matches("My Camera 1"));
+    assert!(NamePattern::glob("*").matches(""));
+    assert!(NamePattern::glob("**a*").matches("banana"));
+    assert!(NamePattern::glob("ünï?ode").matches("ünïcode"));
+    assert!(!NamePattern::glob("").matches("x"));
+
+    let regex = NamePattern::regex(r"^Camera \d+$").unwrap();
+    assert!(regex.matches("Camera 12"));
+    assert!(!regex.matches("Camera A"));
+    assert!(NamePattern::regex("Output").unwrap().matches("Main Output"));
+    assert!(NamePattern::regex("(").is_err());
+
+    assert_eq!(
+        NamePattern::regex("a+").unwrap(),
+        NamePattern::regex("a+").unwrap()
+    );
+    assert_ne!(NamePattern::glob("a+"), NamePattern::regex("a+").unwrap());
+    assert_eq!(regex.as_str(), r"^Camera \d+$");
+}
+
+#[test]
+fn selectors_find_restarted_local_servers() {
+    let server = LocalServer::new("selector-restart");
+    let desc = SourceDescription::from(
+        &local_sources()
+            .into_iter()
+            .find(|s| s.name == "selector-restart")
+            .unwrap(),
+    );
+    let mut selector = SourceSelector::from_description(&desc);
+    assert_eq!(selector.resolve().unwrap().rule, SelectorRule::Uuid);
+
+    // Same name and app, new UUID.
+    drop(server);
+    assert!(selector.resolve().is_none());
+    let restarted = LocalServer::new("selector-restart");
+    let found = selector.resolve().unwrap();
+    assert_eq!(found.rule, SelectorRule::NameAndApp);
+    assert_eq!(found.source.uuid, restarted.uuid());
+
+    selector.remember(&found.source);
+    assert_eq!(selector.resolve().unwrap().rule, SelectorRule::Uuid);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn selectors_serialize() {
+    let selector = SourceSelector::name_and_app("Main Output", "Resolume")
+        .with_pattern(NamePattern::regex("^Main").unwrap())
+        .with_priority(&[SelectorRule::Pattern, SelectorRule::NameAndApp]);
+    let json = serde_json::to_value(&selector).unwrap();
+    assert_eq!(json["pattern"], serde_json::json!({ "regex": "^Main" }));
+    assert_eq!(
+        json["priority"],
+        serde_json::json!(["pattern", "name_and_app"])
+    );
+    let back: SourceSelector = serde_json::from_value(json).unwrap();
+    assert_eq!(back, selector);
+
+    // Missing fields take their defaults; bad regexes are rejected.
+    let glob: SourceSelector = serde_json::from_str(r#"{"pattern":{"glob":"Cam*"}}"#).unwrap();
+    assert_eq!(glob, SourceSelector::pattern(NamePattern::glob("Cam*")));
+    assert!(serde_json::from_str::<SourceSelector>(r#"{"pattern":{"regex":"("}}"#).is_err());
+}
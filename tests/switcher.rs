@@ -0,0 +1,142 @@
+//! Source switching with local servers (runs on every platform).
+
+use std::time::Duration;
+
+use rusty_syphon_spout::{
+    Frame, FrameReceiver, LocalClient, LocalServer, PixelFormat, SourceSwitcher, SwitchState,
+    Transition,
+};
+
+fn solid(width: u32, height: u32, format: PixelFormat, rgba: [u8; 4]) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for px in frame.data_mut().chunks_exact_mut(4) {
+        px.copy_from_slice(&rgba);
+    }
+    frame.to_format(format)
+}
+
+fn pixel(frame: &Frame) -> [u8; 4] {
+    let rgba = frame.to_format(PixelFormat::Rgba8);
+    rgba.row(0)[..4].try_into().unwrap()
+}
+
+#[test]
+fn cut_waits_for_the_first_frame() {
+    let a = LocalServer::new("switch-cut-a");
+    let b = LocalServer::new("switch-cut-b");
+    let mut switcher = SourceSwitcher::default();
+    assert_eq!(switcher.state(), SwitchState::Idle);
+    assert!(switcher.receive_frame().is_none());
+
+    switcher.switch_to(LocalClient::connect("switch-cut-a").unwrap());
+    assert_eq!(switcher.state(), SwitchState::Waiting);
+    a.publish(solid(4, 4, PixelFormat::Rgba8, [255, 0, 0, 255]));
+    assert_eq!(pixel(&switcher.receive_frame().unwrap()), [255, 0, 0, 255]);
+    assert_eq!(switcher.state(), SwitchState::Live);
+    assert!(switcher.is_connected());
+    assert!(switcher.receive_frame().is_none(), "nothing new");
+
+    // B is connected but has no frame yet: A stays on the output.
+    switcher.switch_to(LocalClient::connect("switch-cut-b").unwrap());
+    a.publish(solid(4, 4, PixelFormat::Rgba8, [200, 0, 0, 255]));
+    assert_eq!(pixel(&switcher.receive_frame().unwrap()), [200, 0, 0, 255]);
+    assert_eq!(switcher.state(), SwitchState::Waiting);
+
+    b.publish(solid(8, 2, PixelFormat::Bgra8, [0, 0, 255, 255]));
+    let out = switcher.receive_frame().unwrap();
+    assert_eq!(
+        (out.width(), out.height(), out.format()),
+        (8, 2, PixelFormat::Bgra8)
+    );
+    assert_eq!(pixel(&out), [0, 0, 255, 255]);
+    assert_eq!(switcher.state(), SwitchState::Live);
+
+    // A no longer feeds the output.
+    a.publish(solid(4, 4, PixelFormat::Rgba8, [255, 0, 0, 255]));
+    assert!(switcher.receive_frame().is_none());
+}
+
+#[test]
+fn next_source_lost_before_its_first_frame_is_dropped() {
+    let a = LocalServer::new("switch-lost-a");
+    let b = LocalServer::new("switch-lost-b");
+    let mut switcher = SourceSwitcher::default();
+    switcher.switch_to(LocalClient::connect("switch-lost-a").unwrap());
+    a.publish(solid(2, 2, PixelFormat::Rgba8, [1, 2, 3, 255]));
+    switcher.receive_frame().unwrap();
+
+    switcher.switch_to(LocalClient::connect("switch-lost-b").unwrap());
+    drop(b);
+    assert!(switcher.receive_frame().is_none());
+    assert_eq!(switcher.state(), SwitchState::Live);
+    assert_eq!(pixel(switcher.current_frame().unwrap()), [1, 2, 3, 255]);
+
+    // An explicit cancel works the same way.
+    let _c = LocalServer::new("switch-lost-c");
+    switcher.switch_to(LocalClient::connect("switch-lost-c").unwrap());
+    switcher.cancel_switch();
+    assert_eq!(switcher.state(), SwitchState::Live);
+}
+
+#[test]
+fn crossfade_blends_then_settles_on_the_next_source() {
+    let a = LocalServer::new("switch-fade-a");
+    let b = LocalServer::new("switch-fade-b");
+    let mut out = LocalServer::new("switch-fade-out");
+    let mut viewer = LocalClient::connect("switch-fade-out").unwrap();
+    let mut switcher = SourceSwitcher::new(Transition::Crossfade(Duration::from_millis(300)));
+
+    switcher.switch_to(LocalClient::connect("switch-fade-a").unwrap());
+    a.publish(solid(4, 4, PixelFormat::Rgba8, [0, 0, 0, 255]));
+    // The first source has nothing to fade from: it cuts in.
+    assert!(switcher.publish(&mut out).unwrap());
+    assert_eq!(pixel(&viewer.receive_frame().unwrap()), [0, 0, 0, 255]);
+
+    switcher.switch_to(LocalClient::connect("switch-fade-b").unwrap());
+    assert!(!switcher.publish(&mut out).unwrap());
+    b.publish(solid(2, 2, PixelFormat::Rgba8, [255, 255, 255, 255]));
+
+    // Every call during the fade produces a frame at the next source's size, getting brighter.
+    let mut levels = Vec::new();
+    while switcher.publish(&mut out).unwrap() {
+        let frame = viewer.receive_frame().unwrap();
+        assert_eq!((frame.width(), frame.height()), (2, 2));
+        levels.push(pixel(&frame)[0]);
+        if let SwitchState::Fading { progress } = switcher.state() {
+            assert!((0.0..=1.0).contains(&progress));
+        } else {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(levels.len() >= 3, "{levels:?}");
+    assert!(levels.windows(2).all(|w| w[0] <= w[1]), "{levels:?}");
+    assert!(levels[0] < 128, "{levels:?}");
+    assert_eq!(*levels.last().unwrap(), 255);
+    assert_eq!(switcher.state(), SwitchState::Live);
+    assert_eq!(pixel(switcher.current_frame().unwrap()), [255; 4]);
+}
+
+#[test]
+fn changing_the_transition_mid_fade_keeps_the_fade_running() {
+    let a = LocalServer::new("switch-retime-a");
+    let b = LocalServer::new("switch-retime-b");
+    let mut switcher = SourceSwitcher::new(Transition::Crossfade(Duration::from_secs(10)));
+
+    switcher.switch_to(LocalClient::connect("switch-retime-a").unwrap());
+    a.publish(solid(2, 2, PixelFormat::Rgba8, [0, 0, 0, 255]));
+    assert!(switcher.receive_frame().is_some());
+    switcher.switch_to(LocalClient::connect("switch-retime-b").unwrap());
+    b.publish(solid(2, 2, PixelFormat::Rgba8, [255, 255, 255, 255]));
+    assert!(switcher.receive_frame().is_some());
+
+    switcher.set_transition(Transition::Cut);
+    match switcher.state() {
+        SwitchState::Fading { progress } => assert!(progress < 0.5, "{progress}"),
+        state => panic!("{state:?}"),
+    }
+    b.publish(solid(2, 2, PixelFormat::Rgba8, [255, 255, 255, 255]));
+    let frame = switcher.receive_frame().unwrap();
+    assert!(pixel(&frame)[0] < 128, "{:?}", pixel(&frame));
+    assert!(matches!(switcher.state(), SwitchState::Fading { .. }));
+}
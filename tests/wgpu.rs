@@ -0,0 +1,152 @@
+//! wgpu interop through the staged path on a software adapter (`--features wgpu`). Skips when no
+//! adapter is available (needs Mesa's llvmpipe through EGL, or lavapipe).
+
+#![cfg(feature = "wgpu")]
+
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use rusty_syphon_spout::{
+    pixel_format_for_texture, read_wgpu_texture, write_wgpu_texture, Frame, FrameCompare,
+    LocalClient, LocalServer, PixelFormat, SourceBackend, WgpuError, WgpuPath, WgpuReceiver,
+    WgpuSender,
+};
+
+/// Native wgpu futures resolve once the device has done the work, so polling is enough.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::yield_now();
+    }
+}
+
+fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let mut desc = wgpu::InstanceDescriptor::new_without_display_handle();
+    desc.backends = wgpu::Backends::GL | wgpu::Backends::VULKAN | wgpu::Backends::METAL;
+    let instance = wgpu::Instance::new(desc);
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()));
+    let Ok(adapter) = adapter else {
+        eprintln!("skipping: no wgpu adapter");
+        return None;
+    };
+    block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()
+}
+
+fn pattern(width: u32, height: u32, seed: u8) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[(x * 3) as u8, (y * 5) as u8, seed, 255]);
+        }
+    }
+    frame
+}
+
+fn texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+#[test]
+fn texture_round_trips_through_frames() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+    // 70 pixels wide: 280-byte rows need padding to the 256-byte copy alignment.
+    let frame = pattern(70, 9, 1);
+    for format in [
+        wgpu::TextureFormat::Rgba8Unorm,
+        wgpu::TextureFormat::Bgra8Unorm,
+    ] {
+        let tex = texture(&device, 70, 9, format);
+        write_wgpu_texture(&queue, &frame, &tex).unwrap();
+        let read = read_wgpu_texture(&device, &queue, &tex).unwrap();
+        assert_eq!(Some(read.format()), pixel_format_for_texture(format));
+        FrameCompare::new().assert(&frame, &read);
+    }
+
+    let small = texture(&device, 8, 8, wgpu::TextureFormat::Rgba8Unorm);
+    assert!(matches!(
+        write_wgpu_texture(&queue, &frame, &small),
+        Err(WgpuError::SizeMismatch)
+    ));
+    let float = texture(&device, 8, 8, wgpu::TextureFormat::Rgba16Float);
+    assert!(matches!(
+        read_wgpu_texture(&device, &queue, &float),
+        Err(WgpuError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn sender_publishes_textures_to_local_clients() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+    let mut sender = WgpuSender::new(&device, &queue, SourceBackend::Local, "wgpu-send").unwrap();
+    assert_eq!(sender.path(), WgpuPath::Staged);
+    let mut client = LocalClient::connect("wgpu-send").unwrap();
+
+    let tex = texture(&device, 33, 17, wgpu::TextureFormat::Bgra8UnormSrgb);
+    for seed in 0..3 {
+        let frame = pattern(33, 17, seed);
+        write_wgpu_texture(&queue, &frame, &tex).unwrap();
+        sender.publish(&tex).unwrap();
+        let published = client.new_frame().expect("published frame");
+        assert_eq!(published.format(), PixelFormat::Bgra8);
+        FrameCompare::new().assert(&frame, &published);
+    }
+}
+
+#[test]
+fn receiver_uploads_frames_and_follows_size_changes() {
+    let Some((device, queue)) = device() else {
+        return;
+    };
+    let server = LocalServer::new("wgpu-receive");
+    let client = LocalClient::connect("wgpu-receive").unwrap();
+    let mut receiver = WgpuReceiver::new(&device, &queue, Box::new(client));
+    assert!(!receiver.receive().unwrap());
+    assert!(receiver.texture().is_none());
+
+    for (width, height) in [(16, 16), (16, 16), (40, 24)] {
+        let frame = pattern(width, height, width as u8);
+        server.publish(frame.clone());
+        assert!(receiver.receive().unwrap());
+        let tex = receiver.texture().unwrap();
+        assert_eq!((tex.width(), tex.height()), (width, height));
+        let read = read_wgpu_texture(&device, &queue, tex).unwrap();
+        FrameCompare::new().assert(&frame, &read);
+    }
+
+    // Into a caller-owned BGRA texture: channels are swapped on upload.
+    let own = texture(&device, 40, 24, wgpu::TextureFormat::Bgra8Unorm);
+    let frame = pattern(40, 24, 9);
+    server.publish(frame.clone());
+    assert!(receiver.receive_into(&own).unwrap());
+    let read = read_wgpu_texture(&device, &queue, &own).unwrap();
+    assert_eq!(read.format(), PixelFormat::Bgra8);
+    FrameCompare::new().assert(&frame, &read);
+    assert!(receiver.is_connected());
+}
@@ -0,0 +1,155 @@
+//! YUV layouts and RGB↔YUV conversion (runs on every platform).
+
+use rusty_syphon_spout::{
+    ColorSpace, Frame, PixelFormat, YuvFormat, YuvFrame, YuvMatrix, YuvRange,
+};
+
+const FORMATS: [YuvFormat; 4] = [
+    YuvFormat::Nv12,
+    YuvFormat::I420,
+    YuvFormat::Uyvy,
+    YuvFormat::P010,
+];
+
+fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Frame {
+    Frame::from_vec(
+        width,
+        height,
+        PixelFormat::Rgba8,
+        rgba.repeat((width * height) as usize),
+    )
+    .unwrap()
+}
+
+/// Smooth image, so chroma subsampling loses little.
+fn gradient(width: u32, height: u32) -> Frame {
+    let mut frame = Frame::new(width, height, PixelFormat::Rgba8);
+    for y in 0..height {
+        for (x, px) in frame.row_mut(y).chunks_exact_mut(4).enumerate() {
+            px.copy_from_slice(&[(40 + x * 3) as u8, (30 + y * 4) as u8, 160, 255]);
+        }
+    }
+    frame
+}
+
+#[test]
+fn plane_layouts_handle_odd_sizes() {
+    assert_eq!(YuvFormat::I420.frame_len(3, 3), 9 + 4 + 4);
+    assert_eq!(YuvFormat::Nv12.frame_len(3, 3), 9 + 2 * 4);
+    assert_eq!(YuvFormat::Uyvy.frame_len(3, 2), 2 * 8);
+    assert_eq!(YuvFormat::P010.frame_len(2, 2), 8 + 4);
+    assert_eq!(YuvFormat::P010.plane_stride(1, 4), 8);
+    assert!(YuvFrame::from_vec(4, 4, YuvFormat::Nv12, vec![0; 23]).is_none());
+    let frame = YuvFrame::from_vec(4, 4, YuvFormat::I420, vec![0; 24]).unwrap();
+    assert_eq!(frame.plane(0).len(), 16);
+    assert_eq!(frame.plane(2).len(), 4);
+}
+
+#[test]
+fn reference_levels() {
+    let white = YuvFrame::from_frame(
+        &solid(2, 2, [255; 4]),
+        YuvFormat::I420,
+        YuvMatrix::Bt709,
+        YuvRange::Limited,
+    );
+    assert_eq!(white.plane(0), &[235; 4]);
+    assert_eq!(white.plane(1), &[128]);
+    let black = YuvFrame::from_frame(
+        &solid(2, 2, [0, 0, 0, 255]),
+        YuvFormat::I420,
+        YuvMatrix::Bt709,
+        YuvRange::Full,
+    );
+    assert_eq!(black.plane(0), &[0; 4]);
+
+    // BT.601 full-range red: Y = 0.299 * 255, Cr saturates.
+    let red = YuvFrame::from_frame(
+        &solid(2, 2, [255, 0, 0, 255]),
+        YuvFormat::Nv12,
+        YuvMatrix::Bt601,
+        YuvRange::Full,
+    );
+    assert_eq!(red.plane(0)[0], 76);
+    assert_eq!(red.plane(1), &[85, 255]);
+
+    // P010 limited white is 940 in the high 10 bits.
+    let white = YuvFrame::from_frame(
+        &solid(2, 2, [255; 4]),
+        YuvFormat::P010,
+        YuvMatrix::Bt2020,
+        YuvRange::Limited,
+    );
+    assert_eq!(
+        u16::from_le_bytes([white.plane(0)[0], white.plane(0)[1]]),
+        940 << 6
+    );
+    assert_eq!(
+        u16::from_le_bytes([white.plane(1)[0], white.plane(1)[1]]),
+        512 << 6
+    );
+}
+
+#[test]
+fn uyvy_byte_order() {
+    let mut frame = solid(2, 1, [0, 0, 0, 255]);
+    frame.row_mut(0)[4..8].copy_from_slice(&[255, 255, 255, 255]);
+    let yuv = YuvFrame::from_frame(&frame, YuvFormat::Uyvy, YuvMatrix::Bt709, YuvRange::Limited);
+    assert_eq!(yuv.data(), &[128, 16, 128, 235]);
+}
+
+#[test]
+fn round_trip_every_format_matrix_and_range() {
+    let source = gradient(6, 5);
+    for format in FORMATS {
+        for matrix in [YuvMatrix::Bt601, YuvMatrix::Bt709, YuvMatrix::Bt2020] {
+            for range in [YuvRange::Limited, YuvRange::Full] {
+                let yuv = YuvFrame::from_frame(&source, format, matrix, range);
+                assert_eq!(yuv.data().len(), format.frame_len(6, 5));
+                let back = yuv.to_frame(PixelFormat::Rgba8);
+                // Shared chroma per 2x2 block costs a few levels on this gradient.
+                for y in 0..5 {
+                    for (a, b) in source.row(y).iter().zip(back.row(y)) {
+                        assert!(
+                            a.abs_diff(*b) <= 5,
+                            "{format:?} {matrix:?} {range:?}: {a} vs {b}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn grey_round_trips_exactly_and_bgra_is_supported() {
+    let grey = solid(4, 4, [90, 90, 90, 255]);
+    for format in FORMATS {
+        let yuv = YuvFrame::from_frame(
+            &grey.to_format(PixelFormat::Bgra8),
+            format,
+            YuvMatrix::Bt709,
+            YuvRange::Full,
+        );
+        assert_eq!(yuv.to_frame(PixelFormat::Rgba8), grey, "{format:?}");
+    }
+}
+
+#[test]
+fn retagging_changes_decode() {
+    let source = solid(2, 2, [255, 0, 0, 255]);
+    let yuv = YuvFrame::from_frame(&source, YuvFormat::I420, YuvMatrix::Bt601, YuvRange::Full);
+    let wrong = yuv
+        .clone()
+        .with_matrix(YuvMatrix::Bt709)
+        .with_range(YuvRange::Limited);
+    assert_ne!(wrong.to_frame(PixelFormat::Rgba8), source);
+    assert_eq!(
+        YuvMatrix::for_color_space(ColorSpace::Rec2020Pq),
+        YuvMatrix::Bt2020
+    );
+    assert_eq!(
+        YuvMatrix::for_color_space(ColorSpace::Srgb),
+        YuvMatrix::Bt709
+    );
+}